@@ -1,12 +1,14 @@
 use crate::statement::{comment, statement, Comment, Statement};
 use crate::terminals::ws0;
 use crate::AsciiSequence;
+use nom::branch::alt;
 use nom::bytes::complete::{tag, take_until, take_while};
 use nom::character::complete::char;
-use nom::multi::{many0, many_m_n, separated_list1};
+use nom::combinator::{consumed, recognize};
+use nom::multi::{many0, many1};
 use nom::sequence::delimited;
 use nom::Parser;
-use nom::{multi::many1, sequence::preceded};
+use nom::sequence::preceded;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Document<'a> {
@@ -20,8 +22,24 @@ impl<'a> Document<'a> {
             major_parts,
         }
     }
+
+    /// Top-level statements of every heading, in file order.
+    pub fn statements(&self) -> impl Iterator<Item = &Statement<'a>> {
+        self.major_parts.iter().flat_map(|major_part| {
+            major_part.statements.iter().chain(major_part.sections.iter().flat_map(|section| {
+                section.statements.iter().chain(section.subsections.iter().flat_map(|subsection| {
+                    subsection.statements.iter().chain(
+                        subsection
+                            .subsubsections
+                            .iter()
+                            .flat_map(|subsubsection| subsubsection.statements.iter()),
+                    )
+                }))
+            }))
+        })
+    }
 }
-pub fn document(input: &[u8]) -> nom::IResult<&[u8], Document, nom::error::Error<&[u8]>> {
+pub fn document(input: &[u8]) -> nom::IResult<&[u8], Document<'_>, nom::error::Error<&[u8]>> {
     (
         many0(preceded(ws0, comment)),
         many0(preceded(ws0, major_part)),
@@ -30,8 +48,17 @@ pub fn document(input: &[u8]) -> nom::IResult<&[u8], Document, nom::error::Error
         .parse(input)
 }
 
+/// The parts of a heading comment: the whole `$( ... $)`, the title between
+/// the two rules, and the text after the closing rule.
+pub type HeadingComment<'a> = (AsciiSequence<'a>, (AsciiSequence<'a>, AsciiSequence<'a>));
+
+/// Headings that skip a level (a subsection directly under a major part, for
+/// example) are parsed into an implicit heading of the missing level. An
+/// implicit heading has an empty `heading`, `title` and `heading_comments`
+/// located where its first child starts; see `is_implicit`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MajorPart<'a> {
+    pub heading: AsciiSequence<'a>,
     pub title: AsciiSequence<'a>,
     pub heading_comments: AsciiSequence<'a>,
     pub statements: Vec<Statement<'a>>,
@@ -39,37 +66,51 @@ pub struct MajorPart<'a> {
 }
 impl<'a> MajorPart<'a> {
     pub fn new(
-        ((title, heading_comments), statements, sections): (
-            (AsciiSequence<'a>, AsciiSequence<'a>),
+        ((heading, (title, heading_comments)), statements, sections): (
+            HeadingComment<'a>,
             Vec<Statement<'a>>,
             Vec<Section<'a>>,
         ),
     ) -> Self {
         Self {
+            heading,
             title,
             heading_comments,
             statements,
             sections,
         }
     }
+
+    pub fn is_implicit(&self) -> bool {
+        self.heading.is_empty()
+    }
 }
 pub fn major_part_heading_comment<'a>(
     input: &'a [u8],
 ) -> nom::IResult<&'a [u8], (AsciiSequence<'a>, AsciiSequence<'a>), nom::error::Error<&'a [u8]>> {
     heading_comment('#', '#').parse(input)
 }
-pub fn major_part(input: &[u8]) -> nom::IResult<&[u8], MajorPart, nom::error::Error<&[u8]>> {
-    (
-        major_part_heading_comment,
-        many0(preceded(ws0, statement)),
-        many0(preceded(ws0, section)),
-    )
-        .map(MajorPart::new)
-        .parse(input)
+pub fn major_part(input: &[u8]) -> nom::IResult<&[u8], MajorPart<'_>, nom::error::Error<&[u8]>> {
+    alt((
+        (
+            consumed(major_part_heading_comment).map(|(heading, parts)| (AsciiSequence::from(heading), parts)),
+            many0(preceded(ws0, statement)),
+            many0(preceded(ws0, section)),
+        ),
+        (
+            implicit_heading,
+            many1(preceded(ws0, statement)),
+            many0(preceded(ws0, section)),
+        ),
+        (implicit_heading, many0(preceded(ws0, statement)), many1(preceded(ws0, section))),
+    ))
+    .map(MajorPart::new)
+    .parse(input)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Section<'a> {
+    pub heading: AsciiSequence<'a>,
     pub title: AsciiSequence<'a>,
     pub heading_comments: AsciiSequence<'a>,
     pub statements: Vec<Statement<'a>>,
@@ -77,37 +118,46 @@ pub struct Section<'a> {
 }
 impl<'a> Section<'a> {
     pub fn new(
-        ((title, heading_comments), statements, subsections): (
-            (AsciiSequence<'a>, AsciiSequence<'a>),
+        ((heading, (title, heading_comments)), statements, subsections): (
+            HeadingComment<'a>,
             Vec<Statement<'a>>,
             Vec<SubSection<'a>>,
         ),
     ) -> Self {
         Self {
+            heading,
             title,
             heading_comments,
             statements,
             subsections,
         }
     }
+
+    pub fn is_implicit(&self) -> bool {
+        self.heading.is_empty()
+    }
 }
 pub fn section_heading_comment<'a>(
     input: &'a [u8],
 ) -> nom::IResult<&'a [u8], (AsciiSequence<'a>, AsciiSequence<'a>), nom::error::Error<&'a [u8]>> {
     heading_comment('*', '#').parse(input)
 }
-pub fn section(input: &[u8]) -> nom::IResult<&[u8], Section, nom::error::Error<&[u8]>> {
-    (
-        section_heading_comment,
-        many0(preceded(ws0, statement)),
-        many0(preceded(ws0, sub_section)),
-    )
-        .map(Section::new)
-        .parse(input)
+pub fn section(input: &[u8]) -> nom::IResult<&[u8], Section<'_>, nom::error::Error<&[u8]>> {
+    alt((
+        (
+            consumed(section_heading_comment).map(|(heading, parts)| (AsciiSequence::from(heading), parts)),
+            many0(preceded(ws0, statement)),
+            many0(preceded(ws0, sub_section)),
+        ),
+        (implicit_heading, many0(preceded(ws0, statement)), many1(preceded(ws0, sub_section))),
+    ))
+    .map(Section::new)
+    .parse(input)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SubSection<'a> {
+    pub heading: AsciiSequence<'a>,
     pub title: AsciiSequence<'a>,
     pub heading_comments: AsciiSequence<'a>,
     pub statements: Vec<Statement<'a>>,
@@ -115,49 +165,54 @@ pub struct SubSection<'a> {
 }
 impl<'a> SubSection<'a> {
     pub fn new(
-        ((title, heading_comments), statements, subsubsections): (
-            (AsciiSequence<'a>, AsciiSequence<'a>),
+        ((heading, (title, heading_comments)), statements, subsubsections): (
+            HeadingComment<'a>,
             Vec<Statement<'a>>,
             Vec<SubSubSection<'a>>,
         ),
     ) -> Self {
         Self {
+            heading,
             title,
             heading_comments,
             statements,
             subsubsections,
         }
     }
+
+    pub fn is_implicit(&self) -> bool {
+        self.heading.is_empty()
+    }
 }
 pub fn sub_section_heading_comment<'a>(
     input: &'a [u8],
 ) -> nom::IResult<&'a [u8], (AsciiSequence<'a>, AsciiSequence<'a>), nom::error::Error<&'a [u8]>> {
     heading_comment('-', '=').parse(input)
 }
-pub fn sub_section(input: &[u8]) -> nom::IResult<&[u8], SubSection, nom::error::Error<&[u8]>> {
-    (
-        sub_section_heading_comment,
-        many0(preceded(ws0, statement)),
-        many0(preceded(ws0, sub_sub_section)),
-    )
-        .map(SubSection::new)
-        .parse(input)
+pub fn sub_section(input: &[u8]) -> nom::IResult<&[u8], SubSection<'_>, nom::error::Error<&[u8]>> {
+    alt((
+        (
+            consumed(sub_section_heading_comment).map(|(heading, parts)| (AsciiSequence::from(heading), parts)),
+            many0(preceded(ws0, statement)),
+            many0(preceded(ws0, sub_sub_section)),
+        ),
+        (implicit_heading, many0(preceded(ws0, statement)), many1(preceded(ws0, sub_sub_section))),
+    ))
+    .map(SubSection::new)
+    .parse(input)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SubSubSection<'a> {
+    pub heading: AsciiSequence<'a>,
     pub title: AsciiSequence<'a>,
     pub heading_comments: AsciiSequence<'a>,
     pub statements: Vec<Statement<'a>>,
 }
 impl<'a> SubSubSection<'a> {
-    pub fn new(
-        ((title, heading_comments), statements): (
-            (AsciiSequence<'a>, AsciiSequence<'a>),
-            Vec<Statement<'a>>,
-        ),
-    ) -> Self {
+    pub fn new(((heading, (title, heading_comments)), statements): (HeadingComment<'a>, Vec<Statement<'a>>)) -> Self {
         Self {
+            heading,
             title,
             heading_comments,
             statements,
@@ -171,15 +226,40 @@ pub fn sub_sub_section_heading_comment<'a>(
 }
 pub fn sub_sub_section(
     input: &[u8],
-) -> nom::IResult<&[u8], SubSubSection, nom::error::Error<&[u8]>> {
+) -> nom::IResult<&[u8], SubSubSection<'_>, nom::error::Error<&[u8]>> {
     (
-        sub_sub_section_heading_comment,
+        consumed(sub_sub_section_heading_comment).map(|(heading, parts)| (AsciiSequence::from(heading), parts)),
         many0(preceded(ws0, statement)),
     )
         .map(SubSubSection::new)
         .parse(input)
 }
 
+/// An empty heading at the current position, for a heading level the
+/// database skips.
+fn implicit_heading(input: &[u8]) -> nom::IResult<&[u8], HeadingComment<'_>, nom::error::Error<&[u8]>> {
+    let empty = AsciiSequence::from(&input[..0]);
+    Ok((input, (empty, (empty, empty))))
+}
+
+/// A heading level from 1 (major part) to 4 (subsubsection), with the title
+/// and free text of the heading comment.
+pub type LeveledHeading<'a> = (usize, AsciiSequence<'a>, AsciiSequence<'a>);
+
+/// Any of the four heading comments.
+pub fn any_heading_comment(input: &[u8]) -> nom::IResult<&[u8], LeveledHeading<'_>, nom::error::Error<&[u8]>> {
+    alt((
+        major_part_heading_comment.map(|(title, comments)| (1, title, comments)),
+        section_heading_comment.map(|(title, comments)| (2, title, comments)),
+        sub_section_heading_comment.map(|(title, comments)| (3, title, comments)),
+        sub_sub_section_heading_comment.map(|(title, comments)| (4, title, comments)),
+    ))
+    .parse(input)
+}
+
+/// A heading comment: a rule line, the title on a line of its own, a second
+/// rule line, then free text up to `$)`. Returns the title, trimmed, and the
+/// free text.
 pub fn heading_comment<'a>(
     inner: char,
     outter: char,
@@ -191,21 +271,32 @@ pub fn heading_comment<'a>(
     delimited(
         tag("$("),
         (
-            preceded(ws0, delimited_separated_list1(inner, outter)).map(AsciiSequence::from),
+            preceded(ws0, heading_title(inner, outter)),
             take_until("$)").map(AsciiSequence::from),
         ),
         tag("$)"),
     )
 }
 
-pub fn delimited_separated_list1<'a>(
+/// A title line between two rules such as `#*#*#*#*`, which start with
+/// `outter`, alternate with `inner`, and are at least four characters long.
+pub fn heading_title<'a>(
     inner: char,
     outter: char,
 ) -> impl Parser<&'a [u8], Output = AsciiSequence<'a>, Error = nom::error::Error<&'a [u8]>> + 'a {
+    let rule = move || {
+        recognize((
+            char(outter),
+            char(inner),
+            char(outter),
+            char(inner),
+            take_while(move |c: u8| c == inner as u8 || c == outter as u8),
+        ))
+    };
     delimited(
-        separated_list1(char(inner), char(outter)),
+        rule(),
         delimited(ws0, take_while(|c: u8| c != b'\n'), ws0),
-        separated_list1(char(inner), char(outter)),
+        rule(),
     )
-    .map(AsciiSequence::from)
+    .map(|title: &[u8]| AsciiSequence::from(title.trim_ascii()))
 }
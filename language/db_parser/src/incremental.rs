@@ -0,0 +1,368 @@
+use std::ops::Range;
+
+use nom::multi::many0;
+use nom::sequence::preceded;
+use nom::Parser;
+
+use crate::document::{Document, MajorPart, Section, SubSection, SubSubSection};
+use crate::statement::{
+    statement, AxiomaticAssertion, Comment, ConstantDeclaration, DisjoinVariableDeclaration, EssentialHypothesis,
+    FloatingHypothesis, Proof, ProvableAssertion, Statement, VariableDeclaration,
+};
+use crate::terminals::{ws0, CompressedProof, Label, Symbol};
+use crate::AsciiSequence;
+
+/// A change to a database: the bytes `range` of the old text replaced by
+/// `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: Vec<u8>,
+}
+impl TextEdit {
+    /// The text after the edit.
+    pub fn apply(&self, input: &[u8]) -> Vec<u8> {
+        [&input[..self.range.start], &self.text, &input[self.range.end..]].concat()
+    }
+}
+
+/// Parses `new_input`, the text of `old` after `edit`, by parsing again
+/// only the statements the edit touches and moving the rest of `old` over.
+///
+/// Statements and headings are the points where parsing resyncs: the
+/// touched statements must lie under one heading, and the new text in their
+/// place must parse as statements to the end. Otherwise, and for edits to a
+/// heading or to text the old parse did not reach, this returns `None` and
+/// the whole text has to be parsed again.
+pub fn reparse<'n>(old: &Document, old_input: &[u8], new_input: &'n [u8], edit: &TextEdit) -> Option<Document<'n>> {
+    let mut units = Units {
+        input: old_input,
+        units: Vec::new(),
+        end: 0,
+        lists: 0,
+    };
+    for comment in &old.heading_comments {
+        let end = units.after(comment.comment, b"$)")?;
+        units.push(end, None);
+    }
+    units.document(old)?;
+
+    // An insertion where two units meet may join them into one token, so
+    // both count as touched.
+    let touched: Vec<&Unit> = units
+        .units
+        .iter()
+        .filter(|unit| unit.range.start <= edit.range.end && edit.range.start <= unit.range.end)
+        .collect();
+    let (first, last) = (touched.first()?, touched.last()?);
+    let list = first.list?;
+    if edit.range.end > last.range.end || touched.iter().any(|unit| unit.list != Some(list)) {
+        return None;
+    }
+
+    // The touched statements run from the end of the unit before them to
+    // the end of the last, which moves with the edit.
+    let old_region = first.range.start..last.range.end;
+    let new_end = (old_region.end + edit.text.len()).checked_sub(edit.range.len())?;
+    let region = new_input.get(old_region.start..new_end)?;
+    let (rest, replacement) = many0(preceded(ws0, statement)).parse(region).ok()?;
+    let (rest, _) = ws0::<_, nom::error::Error<&[u8]>>(rest).ok()?;
+    if !rest.is_empty() {
+        return None;
+    }
+
+    let mut rebase = Rebase {
+        old_input,
+        new_input,
+        region: old_region,
+        shift: new_end as isize - last.range.end as isize,
+        list,
+        replace: first.index..last.index + 1,
+        replacement: Some(replacement),
+        lists: 0,
+    };
+    Some(rebase.document(old))
+}
+
+/// A top-level statement or a heading, with the whitespace before it.
+#[derive(Debug)]
+struct Unit {
+    range: Range<usize>,
+    /// The statement list of a statement, numbered in file order; `None` for
+    /// headings and the comments at the top of the database.
+    list: Option<usize>,
+    index: usize,
+}
+
+struct Units<'i> {
+    input: &'i [u8],
+    units: Vec<Unit>,
+    /// End of the last unit.
+    end: usize,
+    lists: usize,
+}
+impl Units<'_> {
+    fn push(&mut self, end: usize, list: Option<(usize, usize)>) {
+        self.units.push(Unit {
+            range: self.end..end,
+            list: list.map(|(list, _)| list),
+            index: list.map_or(0, |(_, index)| index),
+        });
+        self.end = end;
+    }
+
+    /// The end of `terminator`, which must follow `sequence` after
+    /// whitespace.
+    fn after(&self, sequence: AsciiSequence, terminator: &[u8]) -> Option<usize> {
+        let start = sequence.offset_in(self.input) + sequence.as_bytes().len();
+        let gap = self.input[start..].iter().take_while(|c| c.is_ascii_whitespace()).count();
+        let end = start + gap + terminator.len();
+        (self.input.get(start + gap..end)? == terminator).then_some(end)
+    }
+
+    fn statement_end(&self, statement: &Statement) -> Option<usize> {
+        match statement {
+            Statement::ConstantDeclaration(ConstantDeclaration { symbol_list })
+            | Statement::VariableDeclaration(VariableDeclaration { symbol_list })
+            | Statement::DisjoinVariableDeclaration(DisjoinVariableDeclaration { symbol_list })
+            | Statement::FloatingHypothesis(FloatingHypothesis { symbol_list, .. })
+            | Statement::EssentialHypothesis(EssentialHypothesis { symbol_list, .. })
+            | Statement::AxiomaticAssertion(AxiomaticAssertion { symbol_list, .. }) => {
+                self.after(symbol_list.last()?.0, b"$.")
+            }
+            Statement::ProvableAssertion(assertion) => self.after(assertion.proof.compressed_proofs.last()?.0, b"$."),
+            Statement::Block(statements) => {
+                let end = self.statement_end(statements.last()?)?;
+                self.after(AsciiSequence(&self.input[end..end]), b"$}")
+            }
+            Statement::Comment(comment) => self.after(comment.comment, b"$)"),
+        }
+    }
+
+    fn heading(&mut self, heading: AsciiSequence) {
+        if !heading.is_empty() {
+            self.push(heading.offset_in(self.input) + heading.as_bytes().len(), None);
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> Option<()> {
+        let list = self.lists;
+        self.lists += 1;
+        for (index, statement) in statements.iter().enumerate() {
+            let end = self.statement_end(statement)?;
+            self.push(end, Some((list, index)));
+        }
+        Some(())
+    }
+
+    fn document(&mut self, document: &Document) -> Option<()> {
+        for major_part in &document.major_parts {
+            self.heading(major_part.heading);
+            self.statements(&major_part.statements)?;
+            for section in &major_part.sections {
+                self.heading(section.heading);
+                self.statements(&section.statements)?;
+                for subsection in &section.subsections {
+                    self.heading(subsection.heading);
+                    self.statements(&subsection.statements)?;
+                    for subsubsection in &subsection.subsubsections {
+                        self.heading(subsubsection.heading);
+                        self.statements(&subsubsection.statements)?;
+                    }
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+/// Moves a parsed document over to the text after an edit.
+struct Rebase<'o, 'n> {
+    old_input: &'o [u8],
+    new_input: &'n [u8],
+    /// The bytes of the old text parsed again.
+    region: Range<usize>,
+    /// How far text after the region moved.
+    shift: isize,
+    /// The statement list and the statements in it that were parsed again,
+    /// with what replaces them.
+    list: usize,
+    replace: Range<usize>,
+    replacement: Option<Vec<Statement<'n>>>,
+    lists: usize,
+}
+impl<'n> Rebase<'_, 'n> {
+    fn sequence(&self, sequence: AsciiSequence) -> AsciiSequence<'n> {
+        let offset = sequence.offset_in(self.old_input);
+        let offset = if offset >= self.region.end {
+            offset.checked_add_signed(self.shift).unwrap()
+        } else if offset > self.region.start {
+            // Only the empty heading of an implicit part, placed where its
+            // first statement started, is left inside the region.
+            self.region.start
+        } else {
+            offset
+        };
+        AsciiSequence(&self.new_input[offset..offset + sequence.as_bytes().len()])
+    }
+
+    fn symbols(&self, symbols: &[Symbol]) -> Vec<Symbol<'n>> {
+        symbols.iter().map(|symbol| Symbol(self.sequence(symbol.0))).collect()
+    }
+
+    fn label(&self, label: Label) -> Label<'n> {
+        Label(self.sequence(label.0))
+    }
+
+    fn statement(&self, statement: &Statement) -> Statement<'n> {
+        match statement {
+            Statement::ConstantDeclaration(declaration) => {
+                Statement::ConstantDeclaration(ConstantDeclaration::new(self.symbols(&declaration.symbol_list)))
+            }
+            Statement::VariableDeclaration(declaration) => {
+                Statement::VariableDeclaration(VariableDeclaration::new(self.symbols(&declaration.symbol_list)))
+            }
+            Statement::DisjoinVariableDeclaration(declaration) => Statement::DisjoinVariableDeclaration(
+                DisjoinVariableDeclaration::new(self.symbols(&declaration.symbol_list)),
+            ),
+            Statement::FloatingHypothesis(hypothesis) => Statement::FloatingHypothesis(FloatingHypothesis::new((
+                self.label(hypothesis.label),
+                self.symbols(&hypothesis.symbol_list),
+            ))),
+            Statement::EssentialHypothesis(hypothesis) => Statement::EssentialHypothesis(EssentialHypothesis::new((
+                self.label(hypothesis.label),
+                self.symbols(&hypothesis.symbol_list),
+            ))),
+            Statement::AxiomaticAssertion(assertion) => Statement::AxiomaticAssertion(AxiomaticAssertion::new((
+                self.label(assertion.label),
+                self.symbols(&assertion.symbol_list),
+            ))),
+            Statement::ProvableAssertion(assertion) => Statement::ProvableAssertion(ProvableAssertion::new((
+                self.label(assertion.label),
+                self.symbols(&assertion.symbol_list),
+                Proof {
+                    labels: assertion.proof.labels.iter().map(|&label| self.label(label)).collect(),
+                    compressed_proofs: assertion
+                        .proof
+                        .compressed_proofs
+                        .iter()
+                        .map(|proof| CompressedProof(self.sequence(proof.0)))
+                        .collect(),
+                    compressed: assertion.proof.compressed,
+                },
+            ))),
+            Statement::Block(statements) => Statement::Block(
+                statements.iter().map(|statement| Box::new(self.statement(statement))).collect(),
+            ),
+            Statement::Comment(comment) => Statement::Comment(Comment::new(self.sequence(comment.comment))),
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> Vec<Statement<'n>> {
+        let list = self.lists;
+        self.lists += 1;
+        if list != self.list {
+            return statements.iter().map(|statement| self.statement(statement)).collect();
+        }
+        let replacement = self.replacement.take().unwrap_or_default();
+        let before = statements[..self.replace.start].iter().map(|statement| self.statement(statement));
+        let after = statements[self.replace.end..].iter().map(|statement| self.statement(statement));
+        before.chain(replacement).chain(after).collect()
+    }
+
+    fn document(&mut self, document: &Document) -> Document<'n> {
+        let heading_comments = document
+            .heading_comments
+            .iter()
+            .map(|comment| Comment::new(self.sequence(comment.comment)))
+            .collect();
+        let major_parts = document.major_parts.iter().map(|major_part| self.major_part(major_part)).collect();
+        Document::new((heading_comments, major_parts))
+    }
+
+    fn major_part(&mut self, major_part: &MajorPart) -> MajorPart<'n> {
+        MajorPart {
+            heading: self.sequence(major_part.heading),
+            title: self.sequence(major_part.title),
+            heading_comments: self.sequence(major_part.heading_comments),
+            statements: self.statements(&major_part.statements),
+            sections: major_part.sections.iter().map(|section| self.section(section)).collect(),
+        }
+    }
+
+    fn section(&mut self, section: &Section) -> Section<'n> {
+        Section {
+            heading: self.sequence(section.heading),
+            title: self.sequence(section.title),
+            heading_comments: self.sequence(section.heading_comments),
+            statements: self.statements(&section.statements),
+            subsections: section.subsections.iter().map(|subsection| self.subsection(subsection)).collect(),
+        }
+    }
+
+    fn subsection(&mut self, subsection: &SubSection) -> SubSection<'n> {
+        SubSection {
+            heading: self.sequence(subsection.heading),
+            title: self.sequence(subsection.title),
+            heading_comments: self.sequence(subsection.heading_comments),
+            statements: self.statements(&subsection.statements),
+            subsubsections: subsection
+                .subsubsections
+                .iter()
+                .map(|subsubsection| self.subsubsection(subsubsection))
+                .collect(),
+        }
+    }
+
+    fn subsubsection(&mut self, subsubsection: &SubSubSection) -> SubSubSection<'n> {
+        SubSubSection {
+            heading: self.sequence(subsubsection.heading),
+            title: self.sequence(subsubsection.title),
+            heading_comments: self.sequence(subsubsection.heading_comments),
+            statements: self.statements(&subsubsection.statements),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::document;
+
+    const DEMO: &[u8] = include_bytes!("../../fixtures/demo.mm");
+
+    /// The edit replacing the first `old` in the demo database by `new`.
+    fn edit(old: &str, new: &str) -> TextEdit {
+        let start = DEMO.windows(old.len()).position(|w| w == old.as_bytes()).unwrap();
+        TextEdit {
+            range: start..start + old.len(),
+            text: new.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn reparse_agrees_with_a_full_parse() {
+        let edits = [
+            ("ABCDE $.", "ABCDE $.\n  th1 $p |- ( ph -> ph ) $= ? $."),
+            ("( ax-mp ) ABCDE", "( ax-mp ) BACDE"),
+            ("wn $a wff -. ph $.", "wn $a wff -. ps $."),
+            ("$( Negation is a wff. $)", ""),
+        ];
+        let (_, old) = document(DEMO).unwrap();
+        for (from, to) in edits {
+            let edit = edit(from, to);
+            let new_input = edit.apply(DEMO);
+            let (_, parsed) = document(&new_input).unwrap();
+            assert_eq!(reparse(&old, DEMO, &new_input, &edit), Some(parsed), "{to}");
+        }
+    }
+
+    #[test]
+    fn reparse_gives_up_on_headings_and_broken_statements() {
+        let (_, old) = document(DEMO).unwrap();
+        for (from, to) in [("Axioms", "Rules"), ("wi $a wff ( ph -> ps ) $.", "wi $a wff ( ph -> ps )")] {
+            let edit = edit(from, to);
+            assert_eq!(reparse(&old, DEMO, &edit.apply(DEMO), &edit), None, "{to}");
+        }
+    }
+}
@@ -1,23 +1,52 @@
 pub mod document;
+pub mod incremental;
 pub mod terminals;
+pub mod typesetting;
+pub mod unicode;
 pub mod statement;
 pub mod utils;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct AsciiSequence<'a>(&'a [u8]);
+impl<'a> AsciiSequence<'a> {
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Byte offset of this sequence within `input`, which it must borrow from.
+    pub fn offset_in(&self, input: &[u8]) -> usize {
+        self.0.as_ptr() as usize - input.as_ptr() as usize
+    }
+
+    /// Byte offset of this sequence within `input`, or `None` if it does not
+    /// borrow from `input`.
+    pub fn offset_within(&self, input: &[u8]) -> Option<usize> {
+        let start = (self.0.as_ptr() as usize).checked_sub(input.as_ptr() as usize)?;
+        (start + self.0.len() <= input.len()).then_some(start)
+    }
+}
 
 impl<'a> std::fmt::Debug for AsciiSequence<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", String::from_utf8_lossy(self.0))
     }
 }
+impl<'a> std::fmt::Display for AsciiSequence<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(self.0))
+    }
+}
 impl<'a> From<&'a [u8]> for AsciiSequence<'a> {
     fn from(s: &'a [u8]) -> Self {
         Self(s)
     }
 }
-impl<'a> Into<&'a [u8]> for AsciiSequence<'a> {
-    fn into(self) -> &'a [u8] {
-        self.0
+impl<'a> From<AsciiSequence<'a>> for &'a [u8] {
+    fn from(s: AsciiSequence<'a>) -> Self {
+        s.0
     }
 }
\ No newline at end of file
@@ -1,10 +1,13 @@
-use crate::document::{major_part_heading_comment, section, sub_section, sub_sub_section};
-use crate::terminals::{compressed_proof, label, symbol, ws0, ws1, CompressedProof, Label, Symbol};
+use crate::document::any_heading_comment;
+use crate::terminals::{
+    compressed_proof, is_unknown_proof_token, label, symbol, ws0, ws1, CompressedProof, Label, Symbol,
+};
+use crate::utils::take_one;
 use crate::AsciiSequence;
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_until};
 use nom::combinator::not;
-use nom::multi::{many0, many1, separated_list1, separated_list0};
+use nom::multi::{many0, many1, separated_list1};
 use nom::sequence::{preceded, terminated};
 use nom::{error::Error, sequence::delimited, IResult, Parser};
 
@@ -22,7 +25,7 @@ pub enum Statement<'a> {
     // IncludeFile(IncludeFile<'a>),
     // Unit,
 }
-pub fn statement(input: &[u8]) -> IResult<&[u8], Statement, Error<&[u8]>> {
+pub fn statement(input: &[u8]) -> IResult<&[u8], Statement<'_>, Error<&[u8]>> {
     alt((
         constant_declaration.map(Statement::ConstantDeclaration),
         variable_declaration.map(Statement::VariableDeclaration),
@@ -61,7 +64,7 @@ impl<'a> ConstantDeclaration<'a> {
         Self { symbol_list }
     }
 }
-pub fn constant_declaration(input: &[u8]) -> IResult<&[u8], ConstantDeclaration, Error<&[u8]>> {
+pub fn constant_declaration(input: &[u8]) -> IResult<&[u8], ConstantDeclaration<'_>, Error<&[u8]>> {
     delimited(
         (tag("$c"), ws1),
         separated_list1(ws1, symbol),
@@ -73,14 +76,14 @@ pub fn constant_declaration(input: &[u8]) -> IResult<&[u8], ConstantDeclaration,
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct VariableDeclaration<'a> {
-    symbol_list: Vec<Symbol<'a>>,
+    pub symbol_list: Vec<Symbol<'a>>,
 }
 impl<'a> VariableDeclaration<'a> {
     pub fn new(symbol_list: Vec<Symbol<'a>>) -> Self {
         Self { symbol_list }
     }
 }
-pub fn variable_declaration(input: &[u8]) -> IResult<&[u8], VariableDeclaration, Error<&[u8]>> {
+pub fn variable_declaration(input: &[u8]) -> IResult<&[u8], VariableDeclaration<'_>, Error<&[u8]>> {
     delimited(
         (tag("$v"), ws1),
         separated_list1(ws1, symbol),
@@ -92,7 +95,7 @@ pub fn variable_declaration(input: &[u8]) -> IResult<&[u8], VariableDeclaration,
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DisjoinVariableDeclaration<'a> {
-    symbol_list: Vec<Symbol<'a>>,
+    pub symbol_list: Vec<Symbol<'a>>,
 }
 impl<'a> DisjoinVariableDeclaration<'a> {
     pub fn new(symbol_list: Vec<Symbol<'a>>) -> Self {
@@ -101,7 +104,7 @@ impl<'a> DisjoinVariableDeclaration<'a> {
 }
 pub fn disjoin_variable_declaration(
     input: &[u8],
-) -> IResult<&[u8], DisjoinVariableDeclaration, Error<&[u8]>> {
+) -> IResult<&[u8], DisjoinVariableDeclaration<'_>, Error<&[u8]>> {
     delimited(
         (tag("$d"), ws1),
         separated_list1(ws1, symbol),
@@ -113,15 +116,15 @@ pub fn disjoin_variable_declaration(
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FloatingHypothesis<'a> {
-    label: Label<'a>,
-    symbol_list: Vec<Symbol<'a>>,
+    pub label: Label<'a>,
+    pub symbol_list: Vec<Symbol<'a>>,
 }
 impl<'a> FloatingHypothesis<'a> {
     pub fn new((label, symbol_list): (Label<'a>, Vec<Symbol<'a>>)) -> Self {
         Self { label, symbol_list }
     }
 }
-pub fn floating_hypothesis(input: &[u8]) -> IResult<&[u8], FloatingHypothesis, Error<&[u8]>> {
+pub fn floating_hypothesis(input: &[u8]) -> IResult<&[u8], FloatingHypothesis<'_>, Error<&[u8]>> {
     (
         terminated(label, ws1),
         delimited(
@@ -136,15 +139,15 @@ pub fn floating_hypothesis(input: &[u8]) -> IResult<&[u8], FloatingHypothesis, E
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct EssentialHypothesis<'a> {
-    label: Label<'a>,
-    symbol_list: Vec<Symbol<'a>>,
+    pub label: Label<'a>,
+    pub symbol_list: Vec<Symbol<'a>>,
 }
 impl<'a> EssentialHypothesis<'a> {
     pub fn new((label, symbol_list): (Label<'a>, Vec<Symbol<'a>>)) -> Self {
         Self { label, symbol_list }
     }
 }
-pub fn essential_hypothesis(input: &[u8]) -> IResult<&[u8], EssentialHypothesis, Error<&[u8]>> {
+pub fn essential_hypothesis(input: &[u8]) -> IResult<&[u8], EssentialHypothesis<'_>, Error<&[u8]>> {
     (
         terminated(label, ws1),
         delimited(
@@ -167,7 +170,7 @@ impl<'a> AxiomaticAssertion<'a> {
         Self { label, symbol_list }
     }
 }
-pub fn axiomatic_assertion(input: &[u8]) -> IResult<&[u8], AxiomaticAssertion, Error<&[u8]>> {
+pub fn axiomatic_assertion(input: &[u8]) -> IResult<&[u8], AxiomaticAssertion<'_>, Error<&[u8]>> {
     (
         terminated(label, ws1),
         delimited(
@@ -182,9 +185,9 @@ pub fn axiomatic_assertion(input: &[u8]) -> IResult<&[u8], AxiomaticAssertion, E
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ProvableAssertion<'a> {
-    label: Label<'a>,
-    symbol_list: Vec<Symbol<'a>>,
-    proof: Proof<'a>,
+    pub label: Label<'a>,
+    pub symbol_list: Vec<Symbol<'a>>,
+    pub proof: Proof<'a>,
 }
 impl<'a> ProvableAssertion<'a> {
     pub fn new((label, symbol_list, proof): (Label<'a>, Vec<Symbol<'a>>, Proof<'a>)) -> Self {
@@ -195,7 +198,7 @@ impl<'a> ProvableAssertion<'a> {
         }
     }
 }
-pub fn provable_assertion(input: &[u8]) -> IResult<&[u8], ProvableAssertion, Error<&[u8]>> {
+pub fn provable_assertion(input: &[u8]) -> IResult<&[u8], ProvableAssertion<'_>, Error<&[u8]>> {
     (
         terminated(label, ws1),
         delimited(
@@ -211,31 +214,61 @@ pub fn provable_assertion(input: &[u8]) -> IResult<&[u8], ProvableAssertion, Err
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Proof<'a> {
-    labels: Vec<Label<'a>>,
-    compressed_proofs: Vec<CompressedProof<'a>>,
+    pub labels: Vec<Label<'a>>,
+    pub compressed_proofs: Vec<CompressedProof<'a>>,
+    /// Whether the proof has a parenthesized label list; a proof of only
+    /// `?` steps has none.
+    pub compressed: bool,
 }
 impl<'a> Proof<'a> {
     pub fn new((labels, compressed_proofs): (Vec<Label<'a>>, Vec<CompressedProof<'a>>)) -> Self {
         Self {
             labels,
             compressed_proofs,
+            compressed: true,
+        }
+    }
+
+    /// A proof of `?` steps without a label list, as in `$= ? $.`.
+    pub fn unknown(steps: Vec<CompressedProof<'a>>) -> Self {
+        Self {
+            labels: Vec::new(),
+            compressed_proofs: steps,
+            compressed: false,
+        }
+    }
+
+    /// The length of the proof written on one line, as `( label ... )
+    /// LETTERS` when it is compressed, so that line breaks and indentation
+    /// do not count.
+    pub fn size(&self) -> usize {
+        let steps: usize = self.compressed_proofs.iter().map(|steps| steps.0.as_bytes().len()).sum();
+        match self.compressed {
+            true => self.labels.iter().map(|label| label.0.as_bytes().len() + 1).sum::<usize>() + steps + 4,
+            false => steps + self.compressed_proofs.len().saturating_sub(1),
         }
     }
 }
-pub fn proof(input: &[u8]) -> IResult<&[u8], Proof, Error<&[u8]>> {
-    (
-        delimited(
-            tag("("),
-            many0(preceded(ws1, label)),
-            (ws0, tag(")")),
-        ),
-        many1(preceded(ws0, compressed_proof)),
-    )
-        .map(Proof::new)
-        .parse(input)
+/// A compressed proof, or a proof of only `?` steps as in `$= ? $.`, which
+/// has no labels.
+pub fn proof(input: &[u8]) -> IResult<&[u8], Proof<'_>, Error<&[u8]>> {
+    alt((
+        (
+            delimited(
+                tag("("),
+                many0(preceded(ws1, label)),
+                (ws0, tag(")")),
+            ),
+            many1(preceded(ws0, compressed_proof)),
+        )
+            .map(Proof::new),
+        many1(preceded(ws0, take_one(is_unknown_proof_token)))
+            .map(|unknown| Proof::unknown(unknown.into_iter().map(|c| CompressedProof(c.into())).collect())),
+    ))
+    .parse(input)
 }
 
-pub fn block(input: &[u8]) -> IResult<&[u8], Vec<Box<Statement>>, Error<&[u8]>> {
+pub fn block(input: &[u8]) -> IResult<&[u8], Vec<Box<Statement<'_>>>, Error<&[u8]>> {
     delimited(tag("${"), many0(preceded(ws0, statement)), (ws0, tag("$}"))) 
         .map(|statements| statements.into_iter().map(Box::new).collect())
         .parse(input)
@@ -248,18 +281,35 @@ pub fn block(input: &[u8]) -> IResult<&[u8], Vec<Box<Statement>>, Error<&[u8]>>
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Comment<'a> {
-    comment: AsciiSequence<'a>,
+    pub comment: AsciiSequence<'a>,
 }
 impl<'a> Comment<'a> {
     pub fn new(comment: AsciiSequence<'a>) -> Self {
         Self { comment }
     }
 }
-pub fn comment(input: &[u8]) -> IResult<&[u8], Comment, Error<&[u8]>> {
-    not(major_part_heading_comment)
-        .and(not(section))
-        .and(not(sub_section))
-        .and(not(sub_sub_section))
+/// Hands out the description comments of assertions: the last comment
+/// before an `$a` or `$p` statement that no earlier assertion took.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Descriptions<'a> {
+    last: Option<AsciiSequence<'a>>,
+}
+impl<'a> Descriptions<'a> {
+    /// Takes the statements in file order, those of blocks included, and
+    /// returns the description of each `$a` and `$p` statement.
+    pub fn describe(&mut self, statement: &Statement<'a>) -> Option<AsciiSequence<'a>> {
+        match statement {
+            Statement::Comment(comment) => {
+                self.last = Some(comment.comment);
+                None
+            }
+            Statement::AxiomaticAssertion(_) | Statement::ProvableAssertion(_) => self.last.take(),
+            _ => None,
+        }
+    }
+}
+pub fn comment(input: &[u8]) -> IResult<&[u8], Comment<'_>, Error<&[u8]>> {
+    not(any_heading_comment)
         .and(delimited(
             (tag("$("), ws0),
             take_until("$)"),
@@ -270,3 +320,41 @@ pub fn comment(input: &[u8]) -> IResult<&[u8], Comment, Error<&[u8]>> {
         .map(Comment::new)
         .parse(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::document;
+
+    #[test]
+    fn proof_size_ignores_layout() {
+        let (_, compressed) = proof(b"( wi ax-1\n      ax-mp ) ABADC\n      ABEF").unwrap();
+        assert!(compressed.compressed);
+        assert_eq!(compressed.size(), "( wi ax-1 ax-mp ) ABADCABEF".len());
+
+        let (_, unknown) = proof(b"? \n ?").unwrap();
+        assert!(!unknown.compressed);
+        assert_eq!(unknown.size(), "? ?".len());
+    }
+
+    #[test]
+    fn descriptions_go_to_the_next_assertion() {
+        let input = b"$c wff |- $. $v ph $.
+            $( Unused. $) $( First. $) wph $f wff ph $. ax-1 $a |- ph $. ax-2 $a |- ph $.
+            ${ $( Third. $) ax-3 $a |- ph $. $}";
+        let (_, document) = document(input).unwrap();
+        let mut descriptions = Descriptions::default();
+        let mut described = Vec::new();
+        fn visit<'a>(statement: &Statement<'a>, descriptions: &mut Descriptions<'a>, described: &mut Vec<String>) {
+            if let Statement::Block(statements) = statement {
+                statements.iter().for_each(|statement| visit(statement, descriptions, described));
+            } else if let Some(description) = descriptions.describe(statement) {
+                described.push(description.to_string().trim_end().to_string());
+            } else if matches!(statement, Statement::AxiomaticAssertion(_)) {
+                described.push(String::new());
+            }
+        }
+        document.statements().for_each(|statement| visit(statement, &mut descriptions, &mut described));
+        assert_eq!(described, ["First.", "", "Third."]);
+    }
+}
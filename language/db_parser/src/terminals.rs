@@ -1,20 +1,24 @@
 use crate::utils::take_one;
 use crate::AsciiSequence;
 use nom::branch::alt;
-use nom::bytes::complete::take_while;
 use nom::combinator::recognize;
-use nom::multi::{many0, many1};
+use nom::multi::many0;
 use nom::sequence::preceded;
 use nom::{
-    bytes::complete::take_while1, error::Error, IResult, Input, OutputMode, PResult, Parser,
+    bytes::complete::take_while1, error::Error, IResult, OutputMode, PResult, Parser,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Label<'a>(pub AsciiSequence<'a>);
+impl<'a> std::fmt::Display for Label<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
 pub fn is_label_token(c: u8) -> bool {
     c.is_ascii_alphanumeric() || c == b'_' || c == b'-' || c == b'.'
 }
-pub fn label(input: &[u8]) -> IResult<&[u8], Label, Error<&[u8]>> {
+pub fn label(input: &[u8]) -> IResult<&[u8], Label<'_>, Error<&[u8]>> {
     take_while1(is_label_token)
         .map(AsciiSequence::from)
         .map(Label)
@@ -23,18 +27,23 @@ pub fn label(input: &[u8]) -> IResult<&[u8], Label, Error<&[u8]>> {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Symbol<'a>(pub AsciiSequence<'a>);
+impl<'a> std::fmt::Display for Symbol<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
 pub fn is_symbol_token(c: u8) -> bool {
     if c == b'$' {
         return false;
     }
 
     c.is_ascii_alphanumeric()
-        || b'!' <= c && c <= b'/'
-        || b':' <= c && c <= b'@'
-        || b'[' <= c && c <= b'`'
-        || b'{' <= c && c <= b'~'
+        || (b'!'..=b'/').contains(&c)
+        || (b':'..=b'@').contains(&c)
+        || (b'['..=b'`').contains(&c)
+        || (b'{'..=b'~').contains(&c)
 }
-pub fn symbol(input: &[u8]) -> IResult<&[u8], Symbol, Error<&[u8]>> {
+pub fn symbol(input: &[u8]) -> IResult<&[u8], Symbol<'_>, Error<&[u8]>> {
     take_while1(is_symbol_token)
         .map(AsciiSequence::from)
         .map(Symbol)
@@ -54,18 +63,46 @@ impl<'a> Parser<&'a [u8]> for Symbol<'a> {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CompressedProof<'a>(pub AsciiSequence<'a>);
+impl<'a> CompressedProof<'a> {
+    /// Decodes the proof number, or `None` for a `Z` save marker or a `?`.
+    /// `A` to `T` are the final digit 1..=20, each preceding `U` to `Y` is a
+    /// base-5 digit 1..=5 weighted by 20.
+    pub fn number(&self) -> Option<usize> {
+        let bytes: &[u8] = self.0.into();
+        let mut number = 0;
+        for &c in bytes.iter().filter(|c| !c.is_ascii_whitespace()) {
+            if is_compressed_proof_heading_token(c) {
+                number = number * 5 + (c - b'U' + 1) as usize;
+            } else if is_compressed_proof_token(c) {
+                return Some(number * 20 + (c - b'A' + 1) as usize);
+            } else {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Whether this is a `?`, a step not proved yet.
+    pub fn is_unknown(&self) -> bool {
+        self.0.as_bytes() == b"?"
+    }
+}
 pub fn is_compressed_proof_heading_token(c: u8) -> bool {
-    b'U' <= c && c <= b'Y'
+    (b'U'..=b'Y').contains(&c)
 }
 pub fn is_compressed_proof_token(c: u8) -> bool {
-    b'A' <= c && c <= b'T'
+    (b'A'..=b'T').contains(&c)
 }
 pub fn is_later_occuring_compressed_proof_token(c: u8) -> bool {
     c == b'Z'
 }
-pub fn compressed_proof(input: &[u8]) -> IResult<&[u8], CompressedProof, Error<&[u8]>> {
+pub fn is_unknown_proof_token(c: u8) -> bool {
+    c == b'?'
+}
+pub fn compressed_proof(input: &[u8]) -> IResult<&[u8], CompressedProof<'_>, Error<&[u8]>> {
     alt((
         take_one(is_later_occuring_compressed_proof_token),
+        take_one(is_unknown_proof_token),
         recognize((
             many0(preceded(ws0, take_one(is_compressed_proof_heading_token))),
             preceded(ws0, take_one(is_compressed_proof_token)),
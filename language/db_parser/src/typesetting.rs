@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::terminals::ws0;
+use crate::AsciiSequence;
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_until, take_while1};
+use nom::character::complete::char;
+use nom::combinator::{opt, value};
+use nom::multi::{many0, separated_list1};
+use nom::sequence::{delimited, preceded, terminated};
+use nom::{IResult, Parser};
+
+/// The typesetting definitions of a `$t` comment: how each math symbol is
+/// written in HTML, in the alternative (usually Unicode) HTML, and in LaTeX,
+/// plus the other settings such as `htmltitle`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Typesetting {
+    pub htmldef: HashMap<String, String>,
+    pub althtmldef: HashMap<String, String>,
+    pub latexdef: HashMap<String, String>,
+    /// Statements without `as`, such as `htmltitle "Metamath Proof Explorer";`,
+    /// by keyword.
+    pub settings: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypesettingError {
+    /// Byte offset in the database of the statement that does not parse.
+    pub offset: usize,
+}
+impl Display for TypesettingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot parse the $t comment at byte {}", self.offset)
+    }
+}
+impl std::error::Error for TypesettingError {}
+
+/// One statement of a `$t` comment: a keyword, the quoted strings before
+/// `as`, if any, and those after it. Strings joined with `+` are one string.
+pub type TypesettingStatement = (String, Option<String>, Option<String>);
+
+/// Finds the `$t` comment of a database and parses its definitions. A
+/// database without one has no definitions.
+pub fn typesetting(input: &[u8]) -> Result<Typesetting, TypesettingError> {
+    let mut result = Typesetting::default();
+    let Some(body) = typesetting_comment(input) else {
+        return Ok(result);
+    };
+    let mut rest = body;
+    loop {
+        let (after, _) = skip(rest).unwrap();
+        if after.is_empty() {
+            return Ok(result);
+        }
+        let error = || TypesettingError {
+            offset: AsciiSequence(after).offset_in(input),
+        };
+        let (after, (keyword, name, definition)) = typesetting_statement(after).map_err(|_| error())?;
+        match (keyword.as_str(), name, definition) {
+            ("htmldef", Some(name), Some(definition)) => result.htmldef.insert(name, definition),
+            ("althtmldef", Some(name), Some(definition)) => result.althtmldef.insert(name, definition),
+            ("latexdef", Some(name), Some(definition)) => result.latexdef.insert(name, definition),
+            (_, Some(setting), None) => result.settings.insert(keyword, setting),
+            _ => return Err(error()),
+        };
+        rest = after;
+    }
+}
+
+/// The text of the first `$( $t ... $)` comment.
+fn typesetting_comment(input: &[u8]) -> Option<&[u8]> {
+    let mut position = 0;
+    while let Some(start) = input[position..].windows(2).position(|w| w == b"$(") {
+        let start = position + start + 2;
+        let end = start + input[start..].windows(2).position(|w| w == b"$)")?;
+        let body = &input[start..end];
+        let trimmed = body.trim_ascii_start();
+        if trimmed.starts_with(b"$t") && trimmed.get(2).is_none_or(u8::is_ascii_whitespace) {
+            return Some(&trimmed[2..]);
+        }
+        position = end + 2;
+    }
+    None
+}
+
+/// Whitespace and `/* ... */` comments.
+fn skip(input: &[u8]) -> IResult<&[u8], ()> {
+    value((), (ws0, many0((tag("/*"), take_until("*/"), tag("*/"), ws0)))).parse(input)
+}
+
+fn quoted(input: &[u8]) -> IResult<&[u8], String> {
+    // A doubled quote stands for the quote character itself.
+    let single = delimited(char('\''), many0(alt((take_while1(|c| c != b'\''), value(&b"'"[..], tag("''"))))), char('\''));
+    let double = delimited(char('"'), many0(alt((take_while1(|c| c != b'"'), value(&b"\""[..], tag("\"\""))))), char('"'));
+    alt((single, double))
+        .map(|parts: Vec<&[u8]>| String::from_utf8_lossy(&parts.concat()).into_owned())
+        .parse(input)
+}
+
+fn joined(input: &[u8]) -> IResult<&[u8], String> {
+    separated_list1((skip, char('+'), skip), quoted)
+        .map(|parts| parts.concat())
+        .parse(input)
+}
+
+pub fn typesetting_statement(input: &[u8]) -> IResult<&[u8], TypesettingStatement> {
+    terminated(
+        (
+            take_while1(|c: u8| c.is_ascii_alphanumeric()).map(|keyword| String::from_utf8_lossy(keyword).into_owned()),
+            opt(preceded(skip, joined)),
+            opt(preceded((skip, tag("as"), skip), joined)),
+        ),
+        (skip, char(';')),
+    )
+    .parse(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "$( Header comment $)\n$c ( ) -> wff $.\n";
+
+    #[test]
+    fn definitions_are_read_by_kind() {
+        let input = format!(
+            "{HEADER}$( $t
+  htmldef \"->\" as ' &rarr; ';
+  althtmldef \"->\" as ' &#8594; ';
+  latexdef \"->\" as \"\\rightarrow\";
+  htmltitle \"Demo Explorer\";
+$)"
+        );
+        let typesetting = typesetting(input.as_bytes()).unwrap();
+        assert_eq!(typesetting.htmldef["->"], " &rarr; ");
+        assert_eq!(typesetting.althtmldef["->"], " &#8594; ");
+        assert_eq!(typesetting.latexdef["->"], "\\rightarrow");
+        assert_eq!(typesetting.settings["htmltitle"], "Demo Explorer");
+        assert_eq!(typesetting.htmldef.len(), 1);
+    }
+
+    #[test]
+    fn strings_are_joined_and_comments_skipped() {
+        let input = format!(
+            "{HEADER}$( $t /* The definitions
+  of the parentheses. */
+  htmldef \"(\" as \"<span>\" +
+    '(' /* between the parts */ + \"</span>\";
+  htmldef 'it''s' as \"say \"\"hi\"\"\";
+$)"
+        );
+        let typesetting = typesetting(input.as_bytes()).unwrap();
+        assert_eq!(typesetting.htmldef["("], "<span>(</span>");
+        assert_eq!(typesetting.htmldef["it's"], "say \"hi\"");
+    }
+
+    #[test]
+    fn only_a_t_comment_holds_definitions() {
+        assert_eq!(typesetting(HEADER.as_bytes()).unwrap(), Typesetting::default());
+        let input = format!("{HEADER}$( $tail htmldef \"(\" as \"x\"; $)");
+        assert_eq!(typesetting(input.as_bytes()).unwrap(), Typesetting::default());
+    }
+
+    #[test]
+    fn a_statement_that_does_not_parse_is_reported_at_its_offset() {
+        let input = format!("{HEADER}$( $t htmldef \"(\" as \"x\"; htmldef \")\" \"y\"; $)");
+        let offset = input.find("htmldef \")\"").unwrap();
+        assert_eq!(typesetting(input.as_bytes()), Err(TypesettingError { offset }));
+        // A missing semicolon.
+        let input = format!("{HEADER}$( $t htmldef \"(\" as \"x\" $)");
+        let offset = input.find("htmldef").unwrap();
+        assert_eq!(typesetting(input.as_bytes()), Err(TypesettingError { offset }));
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use crate::typesetting::Typesetting;
+
+/// Unicode for common set.mm symbols, used for symbols the database gives no
+/// usable `althtmldef`.
+const FALLBACK: &[(&str, &str)] = &[
+    ("|-", "⊢"),
+    ("->", "→"),
+    ("<->", "↔"),
+    ("-.", "¬"),
+    ("/\\", "∧"),
+    ("\\/", "∨"),
+    ("-/\\", "⊼"),
+    ("\\/_", "⊻"),
+    ("T.", "⊤"),
+    ("F.", "⊥"),
+    ("A.", "∀"),
+    ("E.", "∃"),
+    ("E!", "∃!"),
+    ("E*", "∃*"),
+    ("e.", "∈"),
+    ("e/", "∉"),
+    ("=/=", "≠"),
+    ("C_", "⊆"),
+    ("C.", "⊊"),
+    ("u.", "∪"),
+    ("i^i", "∩"),
+    ("\\", "∖"),
+    ("(/)", "∅"),
+    ("~P", "𝒫"),
+    ("U.", "⋃"),
+    ("|^|", "⋂"),
+    ("X.", "×"),
+    ("o.", "∘"),
+    ("|`", "↾"),
+    ("~~", "≈"),
+    ("~<", "≺"),
+    ("~<_", "≼"),
+    ("<_", "≤"),
+    ("x.", "·"),
+    ("-u", "-"),
+    ("om", "ω"),
+    ("_V", "V"),
+    ("RR", "ℝ"),
+    ("CC", "ℂ"),
+    ("NN", "ℕ"),
+    ("NN0", "ℕ₀"),
+    ("ZZ", "ℤ"),
+    ("QQ", "ℚ"),
+    ("+oo", "+∞"),
+    ("-oo", "-∞"),
+    ("ph", "φ"),
+    ("ps", "ψ"),
+    ("ch", "χ"),
+    ("th", "θ"),
+    ("ta", "τ"),
+    ("et", "η"),
+    ("ze", "ζ"),
+    ("si", "σ"),
+    ("rh", "ρ"),
+    ("mu", "μ"),
+    ("la", "λ"),
+    ("ka", "κ"),
+];
+
+/// Named HTML entities that `althtmldef` strings commonly use.
+const ENTITIES: &[(&str, &str)] = &[
+    ("amp", "&"),
+    ("lt", "<"),
+    ("gt", ">"),
+    ("quot", "\""),
+    ("apos", "'"),
+    ("nbsp", " "),
+    ("rarr", "→"),
+    ("harr", "↔"),
+    ("not", "¬"),
+    ("and", "∧"),
+    ("or", "∨"),
+    ("forall", "∀"),
+    ("exist", "∃"),
+    ("isin", "∈"),
+    ("notin", "∉"),
+    ("ne", "≠"),
+    ("le", "≤"),
+    ("ge", "≥"),
+    ("sube", "⊆"),
+    ("sub", "⊂"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("empty", "∅"),
+    ("times", "×"),
+    ("middot", "·"),
+    ("phi", "φ"),
+    ("psi", "ψ"),
+    ("chi", "χ"),
+    ("theta", "θ"),
+    ("tau", "τ"),
+    ("eta", "η"),
+    ("zeta", "ζ"),
+    ("sigma", "σ"),
+    ("rho", "ρ"),
+    ("mu", "μ"),
+    ("lambda", "λ"),
+    ("kappa", "κ"),
+    ("omega", "ω"),
+];
+
+/// Writes math symbols as Unicode text for terminal output: each symbol as
+/// the plain text of its `althtmldef`, or else as the built-in fallback, or
+/// else as it is.
+#[derive(Debug, Clone, Default)]
+pub struct UnicodeRenderer {
+    symbols: HashMap<String, String>,
+}
+impl UnicodeRenderer {
+    /// A renderer with only the built-in fallback.
+    pub fn fallback() -> Self {
+        Self {
+            symbols: FALLBACK.iter().map(|&(symbol, text)| (symbol.to_string(), text.to_string())).collect(),
+        }
+    }
+
+    /// A renderer using the `althtmldef` of a database where it has any
+    /// text, and the built-in fallback for other symbols.
+    pub fn new(typesetting: &Typesetting) -> Self {
+        let mut renderer = Self::fallback();
+        for (symbol, definition) in &typesetting.althtmldef {
+            let text = html_text(definition);
+            // Definitions that are only an image have no text to use.
+            if !text.is_empty() {
+                renderer.symbols.insert(symbol.clone(), text);
+            }
+        }
+        renderer
+    }
+
+    pub fn symbol<'s>(&'s self, symbol: &'s str) -> &'s str {
+        self.symbols.get(symbol).map_or(symbol, String::as_str)
+    }
+
+    /// A math string, with its symbols separated by whitespace, rendered
+    /// symbol by symbol and joined with single spaces.
+    pub fn render(&self, math: &str) -> String {
+        math.split_ascii_whitespace().map(|symbol| self.symbol(symbol)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// The text of an HTML fragment: tags are dropped, entities decoded and
+/// surrounding whitespace trimmed.
+fn html_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '<' => {
+                rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+                continue;
+            }
+            '&' => {
+                if let Some((decoded, length)) = entity(rest) {
+                    text.push_str(&decoded);
+                    rest = &rest[length..];
+                    continue;
+                }
+                text.push(c);
+            }
+            _ => text.push(c),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    text.trim().to_string()
+}
+
+/// Decodes the entity `input` starts with, returning its text and length.
+fn entity(input: &str) -> Option<(String, usize)> {
+    let end = input.find(';')?;
+    let name = &input[1..end];
+    let decoded = match name.strip_prefix('#') {
+        Some(number) => {
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)?.to_string()
+        }
+        None => ENTITIES.iter().find(|(entity, _)| *entity == name)?.1.to_string(),
+    };
+    Some((decoded, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallback_covers_common_symbols() {
+        let renderer = UnicodeRenderer::fallback();
+        assert_eq!(renderer.render("|- ( ph -> -. ps )"), "⊢ ( φ → ¬ ψ )");
+        assert_eq!(renderer.render("  A.  x\nE! y  "), "∀ x ∃! y");
+        assert_eq!(renderer.symbol("unknown"), "unknown");
+        // Every symbol is listed once.
+        let mut symbols: Vec<&str> = FALLBACK.iter().map(|(symbol, _)| *symbol).collect();
+        symbols.sort_unstable();
+        symbols.dedup();
+        assert_eq!(symbols.len(), FALLBACK.len());
+    }
+
+    #[test]
+    fn althtmldef_text_overrides_the_fallback() {
+        let mut typesetting = Typesetting::default();
+        let mut define = |symbol: &str, html: &str| typesetting.althtmldef.insert(symbol.to_string(), html.to_string());
+        define("->", " <span class=\"arrow\">&#x21D2;</span> ");
+        define("ph", "<i>&#966;</i>");
+        define("A.", "<img src=\"forall.gif\">");
+        define("x", "<i>x</i>");
+        let renderer = UnicodeRenderer::new(&typesetting);
+        assert_eq!(renderer.render("( ph -> x )"), "( φ ⇒ x )");
+        // An image has no text, so the fallback is used.
+        assert_eq!(renderer.symbol("A."), "∀");
+    }
+
+    #[test]
+    fn entities_are_decoded() {
+        assert_eq!(html_text("&rarr;"), "→");
+        assert_eq!(html_text("&#8594;&#x2192;&#X2192;"), "→→→");
+        assert_eq!(html_text("&lt;&amp;&gt;"), "<&>");
+        // Unknown or malformed entities are kept as they are.
+        assert_eq!(html_text("&bogus; &#xZZ; &"), "&bogus; &#xZZ; &");
+        assert_eq!(entity("&amp; rest"), Some(("&".to_string(), 5)));
+        assert_eq!(entity("&#1114112;"), None);
+    }
+}
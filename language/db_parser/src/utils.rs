@@ -5,9 +5,9 @@ use nom::{IResult, Input};
 //     move |(a, b)| (a, f(b))
 // }
 
-pub fn take_one(
-    predicate: impl Fn(u8) -> bool,
-) -> impl FnMut(&[u8]) -> IResult<&[u8], &[u8], Error<&[u8]>> {
+pub type BytesResult<'a> = IResult<&'a [u8], &'a [u8], Error<&'a [u8]>>;
+
+pub fn take_one(predicate: impl Fn(u8) -> bool) -> impl FnMut(&[u8]) -> BytesResult<'_> {
     move |input| {
         let mut iter = input.iter_elements();
         match iter.next() {
@@ -0,0 +1,350 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+use db_parser::typesetting::Typesetting;
+use finder::title_tree::{escape_html, TitleTree};
+use verifier::database::{Database, EntryKind};
+use verifier::display::{show_proof, ShowOptions};
+use verifier::trace::TraceBack;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SiteOptions {
+    /// Typeset math with `althtmldef`, usually Unicode, instead of `htmldef`.
+    pub alt: bool,
+}
+
+/// One file of the generated site.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    /// File name relative to the site directory.
+    pub path: String,
+    pub contents: String,
+}
+
+/// Builds a static site for a database: `index.html` with the table of
+/// contents and the statements under each heading, and a `<label>.html`
+/// page per assertion in the style of metamath's `mpeuni` pages.
+///
+/// `input` is the text `database` and `tree` were built from.
+pub fn site(
+    input: &[u8],
+    database: &Database,
+    tree: &TitleTree,
+    typesetting: &Typesetting,
+    options: SiteOptions,
+) -> Vec<Page> {
+    let site = Site {
+        database,
+        syntax_types: database
+            .entries
+            .iter()
+            .filter(|entry| entry.kind == EntryKind::FloatingHypothesis)
+            .map(|entry| entry.typecode().to_string())
+            .collect(),
+        typesetting,
+        options,
+    };
+    let assertions: Vec<usize> = database.assertions().map(|(index, _)| index).collect();
+    let mut trace = TraceBack::new(database, true);
+    let mut pages = vec![Page {
+        path: "index.html".to_string(),
+        contents: site.index(input, tree, &assertions),
+    }];
+    for (position, &index) in assertions.iter().enumerate() {
+        let previous = position.checked_sub(1).map(|p| assertions[p]);
+        let next = assertions.get(position + 1).copied();
+        pages.push(Page {
+            path: format!("{}.html", database.entries[index].label),
+            contents: site.assertion(index, previous, next, &mut trace),
+        });
+    }
+    pages
+}
+
+/// Writes `pages` into `directory`, creating it if needed.
+pub fn write_site(directory: &Path, pages: &[Page]) -> io::Result<()> {
+    std::fs::create_dir_all(directory)?;
+    for page in pages {
+        std::fs::write(directory.join(&page.path), &page.contents)?;
+    }
+    Ok(())
+}
+
+struct Site<'s, 'a> {
+    database: &'s Database<'a>,
+    /// Typecodes of variables, such as `wff`: `$a` statements with these are
+    /// syntax definitions rather than axioms.
+    syntax_types: HashSet<String>,
+    typesetting: &'s Typesetting,
+    options: SiteOptions,
+}
+impl Site<'_, '_> {
+    fn setting(&self, keyword: &str) -> &str {
+        self.typesetting.settings.get(keyword).map_or("", String::as_str)
+    }
+
+    fn header(&self, o: &mut String, title: &str) {
+        let site_title = self.setting("htmltitle");
+        let title = if site_title.is_empty() { escape_html(title) } else { format!("{} - {site_title}", escape_html(title)) };
+        o.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        let _ = writeln!(o, "<title>{title}</title>");
+        o.push_str(self.setting("htmlcss"));
+        o.push_str("\n</head>\n<body>\n");
+    }
+
+    /// A math string typeset with the `htmldef` or `althtmldef` of each
+    /// symbol. The definitions carry their own spacing, as in metamath;
+    /// symbols without one are shown as they are, between spaces.
+    fn math<'m>(&self, symbols: impl IntoIterator<Item = &'m str>) -> String {
+        let definitions = if self.options.alt { &self.typesetting.althtmldef } else { &self.typesetting.htmldef };
+        let html: String = symbols
+            .into_iter()
+            .map(|symbol| definitions.get(symbol).cloned().unwrap_or_else(|| format!(" {} ", escape_html(symbol))))
+            .collect();
+        format!("<span class=\"math\">{}</span>", html.trim())
+    }
+
+    /// A description comment with metamath's markup: math between backquotes,
+    /// `~ label` references, and blank lines between paragraphs.
+    fn comment(&self, comment: &str) -> String {
+        let mut paragraphs = vec![String::new()];
+        for line in comment.lines() {
+            if line.trim().is_empty() {
+                if !paragraphs.last().unwrap().is_empty() {
+                    paragraphs.push(String::new());
+                }
+            } else {
+                let paragraph = paragraphs.last_mut().unwrap();
+                paragraph.push_str(line);
+                paragraph.push('\n');
+            }
+        }
+
+        let mut o = String::new();
+        for paragraph in paragraphs.iter().filter(|paragraph| !paragraph.is_empty()) {
+            let mut html = Vec::new();
+            for (i, part) in paragraph.split('`').enumerate() {
+                if i % 2 == 1 {
+                    html.push(self.math(part.split_ascii_whitespace()));
+                    continue;
+                }
+                let mut words = part.split_ascii_whitespace();
+                while let Some(word) = words.next() {
+                    match word {
+                        "~" => html.extend(words.next().map(|label| {
+                            format!("<a href=\"{label}.html\">{label}</a>", label = escape_html(label))
+                        })),
+                        _ => html.push(escape_html(word)),
+                    }
+                }
+            }
+            let _ = writeln!(o, "<p>{}</p>", html.join(" "));
+        }
+        o
+    }
+
+    fn link(&self, index: usize) -> String {
+        format!("<a href=\"{label}.html\">{label}</a>", label = self.database.entries[index].label)
+    }
+
+    fn index(&self, input: &[u8], tree: &TitleTree, assertions: &[usize]) -> String {
+        let mut o = String::new();
+        self.header(&mut o, "Table of Contents");
+        let _ = writeln!(o, "<h1>{}</h1>", escape_html(self.setting("htmltitle")));
+        o.push_str(&tree.to_html());
+
+        // Each heading lists the statements between it and the next heading.
+        // Assertions are in file order, so each heading's are a run of them.
+        let offsets: Vec<usize> =
+            assertions.iter().map(|&index| self.database.entries[index].label.0.offset_in(input)).collect();
+        let headings = tree.flatten();
+        for (i, node) in headings.iter().enumerate() {
+            let end = headings.get(i + 1).map_or(node.end, |next| next.offset.min(node.end));
+            let _ = writeln!(o, "<h3 id=\"{}\">{} {}</h3>", node.anchor(), node.ord_string(), escape_html(&node.title));
+            let run = offsets.partition_point(|&offset| offset < node.offset)..offsets.partition_point(|&offset| offset < end);
+            let links: Vec<String> = assertions[run].iter().map(|&index| self.link(index)).collect();
+            if !links.is_empty() {
+                let _ = writeln!(o, "<p>{}</p>", links.join(" "));
+            }
+        }
+        o.push_str("</body>\n</html>\n");
+        o
+    }
+
+    fn assertion(&self, index: usize, previous: Option<usize>, next: Option<usize>, trace: &mut TraceBack) -> String {
+        let database = self.database;
+        let entry = &database.entries[index];
+        let label = entry.label.to_string();
+        let mut o = String::new();
+        self.header(&mut o, &label);
+
+        o.push_str("<nav>");
+        if let Some(previous) = previous {
+            let _ = write!(o, "&lt; {} | ", self.link(previous));
+        }
+        o.push_str("<a href=\"index.html\">Contents</a>");
+        if let Some(next) = next {
+            let _ = write!(o, " | {} &gt;", self.link(next));
+        }
+        o.push_str("</nav>\n");
+
+        let kind = match entry.kind {
+            EntryKind::ProvableAssertion => "Theorem",
+            _ if label.starts_with("df-") => "Definition",
+            _ if self.syntax_types.contains(&entry.typecode().to_string()) => "Syntax definition",
+            _ => "Axiom",
+        };
+        let _ = writeln!(o, "<h1>{kind} {}</h1>", escape_html(&label));
+        if let Some(comment) = entry.comment {
+            o.push_str("<h2>Description</h2>\n");
+            o.push_str(&self.comment(&comment.to_string()));
+        }
+
+        let symbols = |index: usize| database.entries[index].symbols.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let essential: Vec<usize> = entry
+            .frame
+            .hypotheses
+            .iter()
+            .copied()
+            .filter(|&h| database.entries[h].kind == EntryKind::EssentialHypothesis)
+            .collect();
+        if !essential.is_empty() {
+            o.push_str("<h2>Hypotheses</h2>\n<table>\n<tr><th>Ref</th><th>Expression</th></tr>\n");
+            for &hypothesis in &essential {
+                let _ = writeln!(
+                    o,
+                    "<tr><td>{}</td><td>{}</td></tr>",
+                    database.entries[hypothesis].label,
+                    self.math(symbols(hypothesis).iter().map(String::as_str))
+                );
+            }
+            o.push_str("</table>\n");
+        }
+        o.push_str("<h2>Assertion</h2>\n<table>\n<tr><th>Ref</th><th>Expression</th></tr>\n");
+        let _ = writeln!(
+            o,
+            "<tr><td>{}</td><td>{}</td></tr>\n</table>",
+            escape_html(&label),
+            self.math(symbols(index).iter().map(String::as_str))
+        );
+
+        if entry.kind == EntryKind::ProvableAssertion {
+            o.push_str("<h2>Proof</h2>\n");
+            match show_proof(database, index, ShowOptions::default()) {
+                Ok(listing) => {
+                    o.push_str("<table class=\"proof\">\n<tr><th>Step</th><th>Hyp</th><th>Ref</th><th>Expression</th></tr>\n");
+                    for step in &listing.steps {
+                        let hypotheses: Vec<String> = step.hypotheses.iter().map(|n| n.to_string()).collect();
+                        let reference = match database.index_of(&step.label) {
+                            Some(used) if database.entries[used].is_assertion() => self.link(used),
+                            _ => escape_html(&step.label),
+                        };
+                        let _ = writeln!(
+                            o,
+                            "<tr><td>{}</td><td>{}</td><td>{reference}</td><td><span class=\"depth\">{}</span> {}</td></tr>",
+                            step.number,
+                            hypotheses.join(", "),
+                            step.depth,
+                            self.math(step.formula.split(' '))
+                        );
+                    }
+                    o.push_str("</table>\n");
+                }
+                Err(error) => {
+                    let _ = writeln!(o, "<p class=\"error\">The proof cannot be shown: {}</p>", escape_html(&error.to_string()));
+                }
+            }
+        }
+
+        if entry.kind == EntryKind::ProvableAssertion {
+            let report = trace.report(index);
+            for (heading, labels) in [("axioms", &report.axioms), ("definitions", &report.definitions)] {
+                if labels.is_empty() {
+                    continue;
+                }
+                let links: Vec<String> = labels.iter().map(|l| format!("<a href=\"{l}.html\">{l}</a>")).collect();
+                let _ = writeln!(o, "<p>This theorem was proved from {heading}: {}</p>", links.join(" "));
+            }
+            for skipped in &report.skipped {
+                let _ = writeln!(o, "<p>Not followed: {}</p>", escape_html(skipped));
+            }
+        }
+        o.push_str("</body>\n</html>\n");
+        o
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+    const TYPESETTING: &str = "$( $t
+  htmldef \"->\" as ' &rarr; '; althtmldef \"->\" as ' &#8594; ';
+  htmldef \"ph\" as '<i>ph</i>'; althtmldef \"ph\" as '&#966;';
+  htmltitle \"Demo Explorer\";
+$)
+";
+
+    fn demo_site(options: SiteOptions) -> Vec<Page> {
+        let text = DEMO.replacen("$c ", &format!("{TYPESETTING}$c "), 1);
+        let input = text.as_bytes();
+        let (_, document) = db_parser::document::document(input).unwrap();
+        let database = Database::new(&document).unwrap();
+        let typesetting = db_parser::typesetting::typesetting(input).unwrap();
+        site(input, &database, &TitleTree::from_document(&document, input), &typesetting, options)
+    }
+
+    fn page<'p>(pages: &'p [Page], path: &str) -> &'p str {
+        &pages.iter().find(|page| page.path == path).unwrap().contents
+    }
+
+    #[test]
+    fn site_has_an_index_and_a_page_per_assertion() {
+        let pages = demo_site(SiteOptions::default());
+        let paths: Vec<&str> = pages.iter().map(|page| page.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "index.html", "wn.html", "wi.html", "ax-mp.html", "ax-1.html", "ax-2.html", "a1i.html", "mp2b.html",
+                "mp2c.html", "mp1.html"
+            ]
+        );
+        let index = page(&pages, "index.html");
+        assert!(index.contains("<title>Table of Contents - Demo Explorer</title>"));
+        assert!(index.contains("<p><a href=\"a1i.html\">a1i</a> <a href=\"mp2b.html\">mp2b</a>"));
+    }
+
+    #[test]
+    fn theorem_page_shows_the_statement_and_proof() {
+        let pages = demo_site(SiteOptions::default());
+        let a1i = page(&pages, "a1i.html");
+        assert!(a1i.contains("<title>a1i - Demo Explorer</title>"));
+        assert!(a1i.contains(concat!(
+            "<nav>&lt; <a href=\"ax-2.html\">ax-2</a> | <a href=\"index.html\">Contents</a>",
+            " | <a href=\"mp2b.html\">mp2b</a> &gt;</nav>",
+        )));
+        assert!(a1i.contains("<h1>Theorem a1i</h1>"));
+        assert!(a1i.contains("<p>Inference introducing an antecedent. (Contributed by NM, 29-Dec-1992.)</p>"));
+        assert!(a1i.contains("<tr><td>a1i.1</td><td><span class=\"math\">|- <i>ph</i></span></td></tr>"));
+        // Symbols without a definition keep the spaces around them.
+        let statement = "<span class=\"math\">|-  (  ps  &rarr; <i>ph</i> )</span>";
+        assert!(a1i.contains(&format!("<tr><td>a1i</td><td>{statement}</td></tr>")));
+        let step = "<tr><td>3</td><td>1, 2</td><td><a href=\"ax-mp.html\">ax-mp</a></td>";
+        assert!(a1i.contains(&format!("{step}<td><span class=\"depth\">0</span> {statement}</td></tr>")));
+        assert!(a1i.contains(
+            "<p>This theorem was proved from axioms: <a href=\"ax-mp.html\">ax-mp</a> <a href=\"ax-1.html\">ax-1</a></p>"
+        ));
+
+        assert!(page(&pages, "wi.html").contains("<h1>Syntax definition wi</h1>"));
+        assert!(page(&pages, "ax-1.html").contains("<h1>Axiom ax-1</h1>"));
+    }
+
+    #[test]
+    fn alternative_definitions_are_used_on_request() {
+        let pages = demo_site(SiteOptions { alt: true });
+        assert!(page(&pages, "ax-1.html").contains("|-  ( &#966; &#8594;  (  ps  &#8594; &#966; )  )"));
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use db_parser::typesetting::Typesetting;
+use verifier::database::{Database, EntryKind};
+use verifier::display::{show_proof, ListedStep, ShowOptions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofLayout {
+    /// metamath's step, hypotheses, reference and expression columns.
+    Tabular,
+    /// The hypotheses as premises above a rule, then one numbered line per
+    /// step justified by the assertion used and the lines it uses.
+    Fitch,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LatexOptions {
+    /// Also write the proof of each theorem in this layout.
+    pub proof: Option<ProofLayout>,
+    /// Wrap the output in a document that compiles on its own.
+    pub standalone: bool,
+}
+
+/// Typesets the assertions at `indices`: their hypotheses above a rule, the
+/// assertion below it, and optionally the proof. Math symbols are written
+/// with their `latexdef`.
+pub fn latex(database: &Database, typesetting: &Typesetting, indices: &[usize], options: LatexOptions) -> String {
+    let writer = Writer { database, typesetting };
+    let mut o = String::new();
+    if options.standalone {
+        o.push_str("\\documentclass{article}\n\\usepackage{amsmath,amssymb}\n\\begin{document}\n\n");
+    }
+    for &index in indices {
+        writer.assertion(&mut o, index, options.proof);
+        o.push('\n');
+    }
+    if options.standalone {
+        o.push_str("\\end{document}\n");
+    }
+    o
+}
+
+/// Escapes the characters LaTeX treats specially in text.
+pub fn escape_latex(s: &str) -> String {
+    let mut o = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => o.push_str("\\textbackslash{}"),
+            '~' => o.push_str("\\textasciitilde{}"),
+            '^' => o.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                o.push('\\');
+                o.push(c);
+            }
+            _ => o.push(c),
+        }
+    }
+    o
+}
+
+struct Writer<'w, 'a> {
+    database: &'w Database<'a>,
+    typesetting: &'w Typesetting,
+}
+impl Writer<'_, '_> {
+    /// A math string in `$...$`. Symbols without a `latexdef` are set in
+    /// typewriter type, as text so that `escape_latex` applies.
+    fn math<'m>(&self, symbols: impl IntoIterator<Item = &'m str>) -> String {
+        let latex: Vec<String> = symbols
+            .into_iter()
+            .map(|symbol| match self.typesetting.latexdef.get(symbol) {
+                Some(definition) => definition.clone(),
+                None => format!("\\text{{\\texttt{{{}}}}}", escape_latex(symbol)),
+            })
+            .collect();
+        format!("${}$", latex.join(" "))
+    }
+
+    fn statement(&self, index: usize) -> String {
+        let symbols: Vec<String> = self.database.entries[index].symbols.iter().map(|s| s.to_string()).collect();
+        self.math(symbols.iter().map(String::as_str))
+    }
+
+    fn label(&self, index: usize) -> String {
+        format!("\\texttt{{{}}}", escape_latex(&self.database.entries[index].label.to_string()))
+    }
+
+    fn assertion(&self, o: &mut String, index: usize, layout: Option<ProofLayout>) {
+        let database = self.database;
+        let entry = &database.entries[index];
+        let kind = match entry.kind {
+            EntryKind::ProvableAssertion => "Theorem",
+            _ => "Axiom",
+        };
+        let _ = writeln!(o, "\\paragraph{{{kind} {}}}\n", self.label(index));
+        o.push_str("\\begin{tabular}{ll}\n");
+        let mut hypotheses = entry
+            .frame
+            .hypotheses
+            .iter()
+            .filter(|&&hypothesis| database.entries[hypothesis].kind == EntryKind::EssentialHypothesis)
+            .peekable();
+        if hypotheses.peek().is_some() {
+            for &hypothesis in hypotheses {
+                let _ = writeln!(o, "{} & {} \\\\", self.label(hypothesis), self.statement(hypothesis));
+            }
+            o.push_str("\\hline\n");
+        }
+        let _ = writeln!(o, "{} & {}\n\\end{{tabular}}\n", self.label(index), self.statement(index));
+
+        let Some(layout) = layout.filter(|_| entry.kind == EntryKind::ProvableAssertion) else {
+            return;
+        };
+        match show_proof(database, index, ShowOptions::default()) {
+            Ok(listing) => match layout {
+                ProofLayout::Tabular => self.tabular(o, &listing.steps),
+                ProofLayout::Fitch => self.fitch(o, &listing.steps),
+            },
+            Err(error) => {
+                let _ = writeln!(o, "\\emph{{The proof cannot be shown: {}}}\n", escape_latex(&error.to_string()));
+            }
+        }
+    }
+
+    fn tabular(&self, o: &mut String, steps: &[ListedStep]) {
+        o.push_str("\\begin{tabular}{rlll}\nStep & Hyp & Ref & Expression \\\\\n\\hline\n");
+        for step in steps {
+            let hypotheses: Vec<String> = step.hypotheses.iter().map(|n| n.to_string()).collect();
+            let _ = writeln!(
+                o,
+                "{} & {} & \\texttt{{{}}} & \\hspace{{{}em}}{} \\\\",
+                step.number,
+                hypotheses.join(", "),
+                escape_latex(&step.label),
+                step.depth,
+                self.math(step.formula.split(' '))
+            );
+        }
+        o.push_str("\\end{tabular}\n\n");
+    }
+
+    fn fitch(&self, o: &mut String, steps: &[ListedStep]) {
+        // Hypothesis steps become premises, numbered first and written once.
+        let mut lines: HashMap<usize, usize> = HashMap::new();
+        let mut premises: Vec<&ListedStep> = Vec::new();
+        for step in steps.iter().filter(|step| step.keyword == "$e") {
+            match premises.iter().position(|premise| premise.label == step.label) {
+                Some(position) => lines.insert(step.number, position + 1),
+                None => {
+                    premises.push(step);
+                    lines.insert(step.number, premises.len())
+                }
+            };
+        }
+        o.push_str("\\begin{tabular}{r|ll}\n");
+        for (i, premise) in premises.iter().enumerate() {
+            let formula = self.math(premise.formula.split(' '));
+            let _ = writeln!(o, "{} & {formula} & \\texttt{{{}}} \\\\", i + 1, escape_latex(&premise.label));
+        }
+        if !premises.is_empty() {
+            o.push_str("\\cline{2-2}\n");
+        }
+        let derived = steps.iter().filter(|step| step.keyword != "$e");
+        for (next, step) in (premises.len() + 1..).zip(derived) {
+            lines.insert(step.number, next);
+            let used: Vec<String> = step.hypotheses.iter().map(|n| lines[n].to_string()).collect();
+            let justification = match used.is_empty() {
+                true => format!("\\texttt{{{}}}", escape_latex(&step.label)),
+                false => format!("\\texttt{{{}}} {}", escape_latex(&step.label), used.join(", ")),
+            };
+            let _ = writeln!(o, "{next} & {} & {justification} \\\\", self.math(step.formula.split(' ')));
+        }
+        o.push_str("\\end{tabular}\n\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &[u8] = include_bytes!("../../fixtures/demo.mm");
+
+    #[test]
+    fn symbols_without_latexdef_are_escaped_as_text() {
+        let (_, document) = db_parser::document::document(DEMO).unwrap();
+        let database = Database::new(&document).unwrap();
+        let mut typesetting = Typesetting::default();
+        typesetting.latexdef.insert("ph".to_string(), r"\varphi".to_string());
+        let writer = Writer {
+            database: &database,
+            typesetting: &typesetting,
+        };
+        let expected = [
+            r"\varphi",
+            r"\text{\texttt{\textbackslash{}}}",
+            r"\text{\texttt{\textasciitilde{}}}",
+            r"\text{\texttt{\textasciicircum{}}}",
+            r"\text{\texttt{\_}}",
+        ];
+        assert_eq!(writer.math(["ph", "\\", "~", "^", "_"]), format!("${}$", expected.join(" ")));
+    }
+}
@@ -1,9 +1,113 @@
 use std::fmt::Display;
 
+/// Base statements filed under their headings. A heading with an empty
+/// title stands in for one the input does not show, such as the part a
+/// range starts in; it is not printed.
 #[derive(Clone, Debug, Default)]
 pub struct Document {
     pub major_parts: Vec<MajorPart>,
 }
+impl Document {
+    pub fn push_major_part(&mut self, title: String) {
+        self.major_parts.push(MajorPart {
+            title,
+            statements: Vec::new(),
+            sections: Vec::new(),
+        });
+    }
+
+    pub fn push_section(&mut self, title: String) {
+        if self.major_parts.is_empty() {
+            self.push_major_part(String::new());
+        }
+        self.major_parts.last_mut().unwrap().sections.push(Section {
+            title,
+            statements: Vec::new(),
+            subsections: Vec::new(),
+        });
+    }
+
+    pub fn push_subsection(&mut self, title: String) {
+        if self.major_parts.last().is_none_or(|major_part| major_part.sections.is_empty()) {
+            self.push_section(String::new());
+        }
+        let section = self.major_parts.last_mut().unwrap().sections.last_mut().unwrap();
+        section.subsections.push(SubSection {
+            title,
+            statements: Vec::new(),
+            subsubsections: Vec::new(),
+        });
+    }
+
+    pub fn push_subsubsection(&mut self, title: String) {
+        if self
+            .major_parts
+            .last()
+            .and_then(|major_part| major_part.sections.last())
+            .is_none_or(|section| section.subsections.is_empty())
+        {
+            self.push_subsection(String::new());
+        }
+        let section = self.major_parts.last_mut().unwrap().sections.last_mut().unwrap();
+        section.subsections.last_mut().unwrap().subsubsections.push(SubsubSection {
+            title,
+            statements: Vec::new(),
+        });
+    }
+
+    /// Appends `statement` to the innermost heading opened so far.
+    pub fn push_statement(&mut self, statement: Statement) {
+        if self.major_parts.is_empty() {
+            self.push_major_part(String::new());
+        }
+        if let Some(major_part) = self.major_parts.last_mut() {
+            if let Some(section) = major_part.sections.last_mut() {
+                if let Some(subsection) = section.subsections.last_mut() {
+                    if let Some(subsubsection) = subsection.subsubsections.last_mut() {
+                        subsubsection.statements.push(statement);
+                    } else {
+                        subsection.statements.push(statement);
+                    }
+                } else {
+                    section.statements.push(statement);
+                }
+            } else {
+                major_part.statements.push(statement);
+            }
+        }
+    }
+
+    /// Replaces every math symbol of every statement with `f` of it, such as
+    /// its Unicode rendering.
+    pub fn map_symbols(&mut self, f: impl Fn(&str) -> String) {
+        let mut statements: Vec<&mut Statement> = Vec::new();
+        for major_part in &mut self.major_parts {
+            statements.extend(&mut major_part.statements);
+            for section in &mut major_part.sections {
+                statements.extend(&mut section.statements);
+                for subsection in &mut section.subsections {
+                    statements.extend(&mut subsection.statements);
+                    for subsubsection in &mut subsection.subsubsections {
+                        statements.extend(&mut subsubsection.statements);
+                    }
+                }
+            }
+        }
+        for statement in statements {
+            let symbols = match statement {
+                Statement::ConstantDeclaration(const_decl) => &mut const_decl.symbol_list,
+                Statement::AxiomaticAssertion(assert)
+                | Statement::DefinitionAssertion(assert)
+                | Statement::WffAssertion(assert)
+                | Statement::ClassAssertion(assert)
+                | Statement::OtherAssertion(assert) => &mut assert.symbol_list,
+            };
+            for symbol in symbols {
+                *symbol = f(symbol);
+            }
+        }
+    }
+}
 impl Display for Document {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for major_part in &self.major_parts {
@@ -21,14 +125,12 @@ pub struct MajorPart {
 }
 impl Display for MajorPart {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "# {}", self.title)?;
+        let indent = write_heading(f, "#", &self.title)?;
         for statement in &self.statements {
-            writeln!(f, "    {}", statement)?;
+            writeln!(f, "{indent}{}", statement)?;
         }
         for section in &self.sections {
-            for line in format!("{}", section).split("\n") {
-                writeln!(f, "    {}", line)?;
-            }
+            write_nested(f, indent, section)?;
         }
         Ok(())
     }
@@ -42,14 +144,12 @@ pub struct Section {
 }
 impl Display for Section {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "## {}", self.title)?;
+        let indent = write_heading(f, "##", &self.title)?;
         for statement in &self.statements {
-            writeln!(f, "    {}", statement)?;
+            writeln!(f, "{indent}{}", statement)?;
         }
         for subsection in &self.subsections {
-            for line in format!("{}", subsection).split("\n") {
-                writeln!(f, "    {}", line)?;
-            }
+            write_nested(f, indent, subsection)?;
         }
         Ok(())
     }
@@ -63,14 +163,12 @@ pub struct SubSection {
 }
 impl Display for SubSection {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "### {}", self.title)?;
+        let indent = write_heading(f, "###", &self.title)?;
         for statement in &self.statements {
-            writeln!(f, "    {}", statement)?;
+            writeln!(f, "{indent}{}", statement)?;
         }
         for subsubsection in &self.subsubsections {
-            for line in format!("{}", subsubsection).split("\n") {
-                writeln!(f, "    {}", line)?;
-            }
+            write_nested(f, indent, subsubsection)?;
         }
         Ok(())
     }
@@ -83,14 +181,35 @@ pub struct SubsubSection {
 }
 impl Display for SubsubSection {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "#### {}", self.title)?;
+        let indent = write_heading(f, "####", &self.title)?;
         for statement in &self.statements {
-            writeln!(f, "    {}", statement)?;
+            writeln!(f, "{indent}{}", statement)?;
         }
         Ok(())
     }
 }
 
+/// Writes the heading line of a titled heading and returns the indentation
+/// of what it contains. An implicit heading writes nothing and indents
+/// nothing.
+fn write_heading(f: &mut std::fmt::Formatter<'_>, marker: &str, title: &str) -> Result<&'static str, std::fmt::Error> {
+    if title.is_empty() {
+        return Ok("");
+    }
+    writeln!(f, "{marker} {title}")?;
+    Ok("    ")
+}
+
+fn write_nested(f: &mut std::fmt::Formatter<'_>, indent: &str, heading: &impl Display) -> std::fmt::Result {
+    if indent.is_empty() {
+        return heading.fmt(f);
+    }
+    for line in format!("{}", heading).split("\n") {
+        writeln!(f, "{indent}{}", line)?;
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug)]
 pub enum Statement {
     ConstantDeclaration(ConstantDeclaration),
@@ -100,6 +219,22 @@ pub enum Statement {
     ClassAssertion(AxiomaticAssertion),
     OtherAssertion(AxiomaticAssertion),
 }
+impl Statement {
+    /// Classifies a `$a` statement by the set.mm label conventions.
+    pub fn from_axiomatic_assertion(axiom_assert: AxiomaticAssertion) -> Self {
+        if axiom_assert.label.starts_with("ax") {
+            Self::AxiomaticAssertion(axiom_assert)
+        } else if axiom_assert.label.starts_with("df") {
+            Self::DefinitionAssertion(axiom_assert)
+        } else if axiom_assert.label.starts_with('w') {
+            Self::WffAssertion(axiom_assert)
+        } else if axiom_assert.label.starts_with('c') {
+            Self::ClassAssertion(axiom_assert)
+        } else {
+            Self::OtherAssertion(axiom_assert)
+        }
+    }
+}
 impl Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1,26 +1,24 @@
-use std::{fs::File, io::{Read, Write as IoWrite}, fmt::Write as FmtWrite};
-use nom::{bytes::complete::tag, Parser};
+use db_parser::statement::Statement as DbStatement;
+use nom::Parser;
 
-pub fn find_and_write_base_statements(file_path: &str, from: usize, to: usize) {
-    let mut file = File::open(file_path).unwrap();
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap();
+use crate::ast::{AxiomaticAssertion, ConstantDeclaration, Document, Statement};
 
+/// Scans `input` for `$c` and `$a` statements and files them under the
+/// headings they appear in.
+pub fn base_statements(input: &[u8]) -> Document {
     let mut lookbehind = b' ';
     let mut buffer = Vec::new();
     let mut is_comment = false;
     let mut is_const_decl = false;
     let mut is_axiom_assert = false;
-    let mut document = crate::ast::Document::default();
+    let mut document = Document::default();
     let mut last_nonspace_start = None;
     let mut last_nonspace_end = None;
     let mut last_nonspace_start_candidate = 0;
     let mut axiom_buffer = Vec::new();
     let mut axiom_label = String::new();
 
-    let mut o = String::new();
-
-    for (i, &b) in contents.as_bytes()[from..to].into_iter().enumerate() {
+    for (i, &b) in input.iter().enumerate() {
         if lookbehind.is_ascii_whitespace() && !b.is_ascii_whitespace() {
             last_nonspace_start_candidate = i;
         }
@@ -28,7 +26,7 @@ pub fn find_and_write_base_statements(file_path: &str, from: usize, to: usize) {
             last_nonspace_start = Some(last_nonspace_start_candidate);
             last_nonspace_end = Some(i - 1);
             if is_axiom_assert {
-                let symbol = String::from_utf8_lossy(&contents.as_bytes()[from..to][last_nonspace_start.unwrap()..=last_nonspace_end.unwrap()]).to_string();
+                let symbol = String::from_utf8_lossy(&input[last_nonspace_start_candidate..i]).to_string();
                 if symbol.as_str() != "$a" {
                     axiom_buffer.push(symbol);
                 }
@@ -41,8 +39,12 @@ pub fn find_and_write_base_statements(file_path: &str, from: usize, to: usize) {
             is_const_decl = true;
         }
         if !is_comment && lookbehind == b'$' && b == b'a' {
-            axiom_label = String::from_utf8_lossy(&contents.as_bytes()[from..to][last_nonspace_start.unwrap()..=last_nonspace_end.unwrap()]).into_owned();
-            is_axiom_assert = true;
+            // When `input` starts between a label and its `$a`, there is no
+            // label; the statement is skipped up to its `$.`.
+            if let (Some(start), Some(end)) = (last_nonspace_start, last_nonspace_end) {
+                axiom_label = String::from_utf8_lossy(&input[start..=end]).into_owned();
+                is_axiom_assert = true;
+            }
         }
         if is_comment | is_const_decl | is_axiom_assert {
             buffer.push(lookbehind);
@@ -51,133 +53,31 @@ pub fn find_and_write_base_statements(file_path: &str, from: usize, to: usize) {
             is_comment = false;
             buffer.push(b);
             if let Ok((_, (title, _))) = db_parser::document::heading_comment('#', '#').parse(&buffer) {
-                let title = String::from_utf8_lossy(title.into()).into_owned();
-                document.major_parts.push(crate::ast::MajorPart {
-                    title,
-                    statements: Vec::new(),
-                    sections: Vec::new(),
-                });
+                document.push_major_part(String::from_utf8_lossy(title.into()).into_owned());
             } else if let Ok((_, (title, _))) = db_parser::document::heading_comment('*', '#').parse(&buffer) {
-                let title = String::from_utf8_lossy(title.into()).into_owned();
-                if document.major_parts.last().is_none() {
-                    document.major_parts.push(crate::ast::MajorPart {
-                        title: String::from("EmptyMajorPart"),
-                        statements: Vec::new(),
-                        sections: Vec::new(),
-                    });
-                }
-                document.major_parts.last_mut().unwrap().sections.push(crate::ast::Section {
-                    title,
-                    statements: Vec::new(),
-                    subsections: Vec::new(),
-                });
+                document.push_section(String::from_utf8_lossy(title.into()).into_owned());
             } else if let Ok((_, (title, _))) = db_parser::document::heading_comment('-', '=').parse(&buffer) {
-                let title = String::from_utf8_lossy(title.into()).into_owned();
-                if document.major_parts.last().is_none() {
-                    document.major_parts.push(crate::ast::MajorPart {
-                        title: String::from("EmptyMajorPart"),
-                        statements: Vec::new(),
-                        sections: Vec::new(),
-                    });
-                }
-                if document.major_parts.last().unwrap().sections.last().is_none() {
-                    document.major_parts.last_mut().unwrap().sections.push(crate::ast::Section {
-                        title: String::from("EmptySection"),
-                        statements: Vec::new(),
-                        subsections: Vec::new(),
-                    });
-                }
-                document.major_parts.last_mut().unwrap().sections.last_mut().unwrap().subsections.push(crate::ast::SubSection {
-                    title,
-                    statements: Vec::new(),
-                    subsubsections: Vec::new(),
-                });
+                document.push_subsection(String::from_utf8_lossy(title.into()).into_owned());
             } else if let Ok((_, (title, _))) = db_parser::document::heading_comment('.', '-').parse(&buffer) {
-                let title = String::from_utf8_lossy(title.into()).into_owned();
-                if document.major_parts.last().is_none() {
-                    document.major_parts.push(crate::ast::MajorPart {
-                        title: String::from("EmptyMajorPart"),
-                        statements: Vec::new(),
-                        sections: Vec::new(),
-                    });
-                }
-                if document.major_parts.last().unwrap().sections.last().is_none() {
-                    document.major_parts.last_mut().unwrap().sections.push(crate::ast::Section {
-                        title: String::from("EmptySection"),
-                        statements: Vec::new(),
-                        subsections: Vec::new(),
-                    });
-                }
-                if document.major_parts.last().unwrap().sections.last().unwrap().subsections.last().is_none() {
-                    document.major_parts.last_mut().unwrap().sections.last_mut().unwrap().subsections.push(crate::ast::SubSection {
-                        title: String::from("EmptySubSection"),
-                        statements: Vec::new(),
-                        subsubsections: Vec::new(),
-                    });
-                }
-                document.major_parts.last_mut().unwrap().sections.last_mut().unwrap().subsections.last_mut().unwrap().subsubsections.push(crate::ast::SubsubSection {
-                    title,
-                    statements: Vec::new(),
-                });
+                document.push_subsubsection(String::from_utf8_lossy(title.into()).into_owned());
             }
             buffer.clear();
         }
         if lookbehind == b'$' && b == b'.' {
             buffer.push(b);
             if is_const_decl {
-                let const_decl = crate::ast::ConstantDeclaration {
+                let const_decl = ConstantDeclaration {
                     symbol_list: db_parser::statement::constant_declaration(&buffer).unwrap().1.symbol_list.into_iter().map(|symbol| String::from_utf8_lossy(symbol.0.into()).into_owned()).collect(),
                 };
-                let stmt = crate::ast::Statement::ConstantDeclaration(const_decl);
-                if let Some(major_part) = document.major_parts.last_mut() {
-                    if let Some(section) = major_part.sections.last_mut() {
-                        if let Some(subsection) = section.subsections.last_mut() {
-                            if let Some(subsubsection) = subsection.subsubsections.last_mut() {
-                                subsubsection.statements.push(stmt);
-                            } else {
-                                subsection.statements.push(stmt);
-                            }
-                        } else {
-                            section.statements.push(stmt);
-                        }
-                    } else {
-                        major_part.statements.push(stmt);
-                    }
-                }
+                document.push_statement(Statement::ConstantDeclaration(const_decl));
             }
             if is_axiom_assert {
-                let label = axiom_label.clone();
-                let axiom_assert = crate::ast::AxiomaticAssertion {
-                    label,
+                let axiom_assert = AxiomaticAssertion {
+                    label: axiom_label.clone(),
                     symbol_list: axiom_buffer.clone(),
                 };
                 axiom_buffer.clear();
-                let stmt = if &axiom_assert.label[0..2] == "ax" {
-                    crate::ast::Statement::AxiomaticAssertion(axiom_assert)
-                } else if &axiom_assert.label[0..2] == "df" {
-                    crate::ast::Statement::DefinitionAssertion(axiom_assert)
-                } else if &axiom_assert.label[0..1] == "w" {
-                    crate::ast::Statement::WffAssertion(axiom_assert)
-                } else if &axiom_assert.label[0..1] == "c" {
-                    crate::ast::Statement::ClassAssertion(axiom_assert)
-                } else {
-                    crate::ast::Statement::OtherAssertion(axiom_assert)
-                };
-                if let Some(major_part) = document.major_parts.last_mut() {
-                    if let Some(section) = major_part.sections.last_mut() {
-                        if let Some(subsection) = section.subsections.last_mut() {
-                            if let Some(subsubsection) = subsection.subsubsections.last_mut() {
-                                subsubsection.statements.push(stmt);
-                            } else {
-                                subsection.statements.push(stmt);
-                            }
-                        } else {
-                            section.statements.push(stmt);
-                        }
-                    } else {
-                        major_part.statements.push(stmt);
-                    }
-                }
+                document.push_statement(Statement::from_axiomatic_assertion(axiom_assert));
             }
             is_const_decl = false;
             is_axiom_assert = false;
@@ -185,7 +85,97 @@ pub fn find_and_write_base_statements(file_path: &str, from: usize, to: usize) {
         }
         lookbehind = b;
     }
-    let output_file_name = format!("../{}-base_statements.txt", file_path.split('/').last().unwrap());
-    let mut output_file = File::create(output_file_name).unwrap();
-    output_file.write(&format!("{document}").as_bytes()).unwrap();
-}
\ No newline at end of file
+    document
+}
+
+/// Collects the `$c` and `$a` statements of an already parsed database,
+/// keeping its heading structure.
+pub fn base_statements_from_document(db_document: &db_parser::document::Document) -> Document {
+    let mut document = Document::default();
+    for major_part in &db_document.major_parts {
+        if !major_part.is_implicit() {
+            document.push_major_part(String::from_utf8_lossy(major_part.title.into()).into_owned());
+        }
+        push_base_statements(&mut document, &major_part.statements);
+        for section in &major_part.sections {
+            if !section.is_implicit() {
+                document.push_section(String::from_utf8_lossy(section.title.into()).into_owned());
+            }
+            push_base_statements(&mut document, &section.statements);
+            for subsection in &section.subsections {
+                if !subsection.is_implicit() {
+                    document.push_subsection(String::from_utf8_lossy(subsection.title.into()).into_owned());
+                }
+                push_base_statements(&mut document, &subsection.statements);
+                for subsubsection in &subsection.subsubsections {
+                    document.push_subsubsection(String::from_utf8_lossy(subsubsection.title.into()).into_owned());
+                    push_base_statements(&mut document, &subsubsection.statements);
+                }
+            }
+        }
+    }
+    document
+}
+
+fn push_base_statements(document: &mut Document, statements: &[DbStatement]) {
+    for statement in statements {
+        push_base_statement(document, statement);
+    }
+}
+
+fn push_base_statement(document: &mut Document, statement: &DbStatement) {
+    match statement {
+        DbStatement::ConstantDeclaration(const_decl) => {
+            let const_decl = ConstantDeclaration {
+                symbol_list: const_decl.symbol_list.iter().map(|symbol| String::from_utf8_lossy(symbol.0.into()).into_owned()).collect(),
+            };
+            document.push_statement(Statement::ConstantDeclaration(const_decl));
+        }
+        DbStatement::AxiomaticAssertion(axiom_assert) => {
+            let axiom_assert = AxiomaticAssertion {
+                label: String::from_utf8_lossy(axiom_assert.label.0.into()).into_owned(),
+                symbol_list: axiom_assert.symbol_list.iter().map(|symbol| String::from_utf8_lossy(symbol.0.into()).into_owned()).collect(),
+            };
+            document.push_statement(Statement::from_axiomatic_assertion(axiom_assert));
+        }
+        DbStatement::Block(statements) => {
+            for statement in statements {
+                push_base_statement(document, statement);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &[u8] = include_bytes!("../../fixtures/demo.mm");
+
+    #[test]
+    fn scan_agrees_with_the_parsed_document() {
+        let (_, document) = db_parser::document::document(DEMO).unwrap();
+        assert_eq!(base_statements(DEMO).to_string(), base_statements_from_document(&document).to_string());
+    }
+
+    #[test]
+    fn scan_skips_a_statement_cut_before_its_keyword() {
+        let start = DEMO.windows(4).position(|w| w == b"ax-1").unwrap() + "ax-1 ".len();
+        let document = base_statements(&DEMO[start..]).to_string();
+        assert!(!document.contains("ax-1"));
+        assert!(document.contains("AXIOM ax-2 $a |- ( ( ph -> ( ps -> ch ) ) -> ( ( ph -> ps ) -> ( ph -> ch ) ) ) $."));
+    }
+
+    #[test]
+    fn a_range_inside_a_part_prints_no_heading_for_the_part() {
+        let start = DEMO.windows(7).position(|w| w == b"$(\n#*#*").unwrap();
+        let document = base_statements(&DEMO[start..]).to_string();
+        assert!(document.starts_with("## Axioms\n    WFF wn $a wff -. ph $.\n"), "{document}");
+        assert!(document.contains("\n    ### Theorems\n"));
+
+        let start = DEMO.windows(4).position(|w| w == b"wi $").unwrap();
+        let document = base_statements(&DEMO[start..]).to_string();
+        assert!(document.starts_with("WFF wi $a wff ( ph -> ps ) $.\n"), "{document}");
+    }
+}
@@ -1,3 +1,7 @@
 pub mod ast;
 pub mod title_tree;
 pub mod base_statements;
+pub mod range;
+pub mod scan;
+pub mod stats;
+pub mod text_index;
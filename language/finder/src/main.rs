@@ -1,7 +0,0 @@
-use finder::base_statements;
-
-fn main() {
-    // finder::title_tree::find_and_write_title_tree("../set.mm");
-    // base_statements::find_and_write_base_statements("../set.mm", 12710, 1083997);
-    base_statements::find_and_write_base_statements("../set.mm", 12710, 28337423);
-}
@@ -0,0 +1,194 @@
+use std::fmt::Display;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::scan::statement_span;
+use crate::title_tree::{title_tree, TitleNode};
+
+/// One end of a database range.
+///
+/// Written as a bare byte offset, `title:<heading title>`,
+/// `outline:<number such as 1.2.3>` or `label:<statement label>`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RangeBound {
+    Offset(usize),
+    Title(String),
+    Outline(Vec<usize>),
+    Label(String),
+}
+impl From<usize> for RangeBound {
+    fn from(offset: usize) -> Self {
+        Self::Offset(offset)
+    }
+}
+impl FromStr for RangeBound {
+    type Err = RangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || RangeError::InvalidBound(s.to_string());
+        if let Some(title) = s.strip_prefix("title:") {
+            Ok(Self::Title(title.trim().to_string()))
+        } else if let Some(outline) = s.strip_prefix("outline:") {
+            outline
+                .trim_end_matches('.')
+                .split('.')
+                .map(|n| n.parse().map_err(|_| invalid()))
+                .collect::<Result<_, _>>()
+                .map(Self::Outline)
+        } else if let Some(label) = s.strip_prefix("label:") {
+            Ok(Self::Label(label.to_string()))
+        } else {
+            s.parse().map(Self::Offset).map_err(|_| invalid())
+        }
+    }
+}
+impl Display for RangeBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Offset(offset) => write!(f, "{offset}"),
+            Self::Title(title) => write!(f, "title:{title}"),
+            Self::Outline(ord) => {
+                write!(f, "outline:{}", ord.iter().map(|n| n.to_string()).collect::<Vec<_>>().join("."))
+            }
+            Self::Label(label) => write!(f, "label:{label}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RangeError {
+    InvalidBound(String),
+    UnknownTitle(String),
+    UnknownOutline(String),
+    UnknownLabel(String),
+    OutOfBounds(usize),
+    Reversed(usize, usize),
+}
+impl Display for RangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidBound(bound) => write!(
+                f,
+                "invalid range bound {bound:?}, expected a byte offset, title:, outline: or label:"
+            ),
+            Self::UnknownTitle(title) => write!(f, "no heading titled {title:?}"),
+            Self::UnknownOutline(ord) => write!(f, "no heading numbered {ord}"),
+            Self::UnknownLabel(label) => write!(f, "no statement labeled {label}"),
+            Self::OutOfBounds(offset) => write!(f, "byte offset {offset} is past the end of the database"),
+            Self::Reversed(from, to) => write!(f, "range ends at byte {to} before it starts at byte {from}"),
+        }
+    }
+}
+impl std::error::Error for RangeError {}
+
+/// Resolves `from` and `to` to a byte range of `input`.
+///
+/// Both ends are inclusive: a heading bound at `to` extends to the end of
+/// everything nested under the heading, and a label bound to the end of the
+/// statement's `$.`.
+pub fn resolve_range(input: &[u8], from: &RangeBound, to: &RangeBound) -> Result<Range<usize>, RangeError> {
+    let tree = match (from, to) {
+        (RangeBound::Title(_) | RangeBound::Outline(_), _) | (_, RangeBound::Title(_) | RangeBound::Outline(_)) => {
+            title_tree(input)
+        }
+        _ => Default::default(),
+    };
+    let headings = tree.flatten();
+
+    let start = resolve(input, &headings, from, 0)?.start;
+    let end = resolve(input, &headings, to, start)?.end;
+    if end < start {
+        return Err(RangeError::Reversed(start, end));
+    }
+    Ok(start..end)
+}
+
+/// The span a single bound covers. Titles are looked up from `search_from`
+/// on, so that a `to` title is found after the `from` bound.
+fn resolve(input: &[u8], headings: &[&TitleNode], bound: &RangeBound, search_from: usize) -> Result<Range<usize>, RangeError> {
+    match bound {
+        RangeBound::Offset(offset) if *offset > input.len() => Err(RangeError::OutOfBounds(*offset)),
+        RangeBound::Offset(offset) => Ok(*offset..*offset),
+        RangeBound::Title(title) => headings
+            .iter()
+            .find(|node| node.offset >= search_from && node.title.eq_ignore_ascii_case(title))
+            .map(|node| node.offset..node.end)
+            .ok_or_else(|| RangeError::UnknownTitle(title.clone())),
+        RangeBound::Outline(ord) => headings
+            .iter()
+            .find(|node| node.ord == *ord)
+            .map(|node| node.offset..node.end)
+            .ok_or_else(|| RangeError::UnknownOutline(bound.to_string())),
+        RangeBound::Label(label) => {
+            statement_span(input, label.as_bytes()).ok_or_else(|| RangeError::UnknownLabel(label.clone()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &[u8] = include_bytes!("../../fixtures/demo.mm");
+
+    fn find(text: &str) -> usize {
+        DEMO.windows(text.len()).position(|w| w == text.as_bytes()).unwrap()
+    }
+
+    fn range(from: &str, to: &str) -> Result<Range<usize>, RangeError> {
+        resolve_range(DEMO, &from.parse()?, &to.parse()?)
+    }
+
+    #[test]
+    fn bounds_parse_and_display() {
+        let bounds = ["42", "title:Axioms", "outline:1.1.1", "label:ax-mp"];
+        for bound in bounds {
+            assert_eq!(bound.parse::<RangeBound>().unwrap().to_string(), bound);
+        }
+        assert_eq!("outline:1.2.".parse(), Ok(RangeBound::Outline(vec![1, 2])));
+        for invalid in ["outline:1.x", "ax-mp"] {
+            assert_eq!(invalid.parse::<RangeBound>(), Err(RangeError::InvalidBound(invalid.to_string())));
+        }
+    }
+
+    #[test]
+    fn resolve_range_by_offset() {
+        assert_eq!(range("10", "20"), Ok(10..20));
+        assert_eq!(range("0", &DEMO.len().to_string()), Ok(0..DEMO.len()));
+        assert_eq!(range("0", "100000"), Err(RangeError::OutOfBounds(100000)));
+        assert_eq!(range("20", "10"), Err(RangeError::Reversed(20, 10)));
+    }
+
+    #[test]
+    fn resolve_range_by_title() {
+        let axioms = find("$(\n#*#*");
+        let theorems = find("$(\n=-=-");
+        assert_eq!(range("title:Axioms", "title:Axioms"), Ok(axioms..DEMO.len()));
+        assert_eq!(range("title:axioms", "title:Theorems"), Ok(axioms..DEMO.len()));
+        assert_eq!(range("title:Theorems", "10000"), Err(RangeError::OutOfBounds(10000)));
+        assert_eq!(range("title:Theorems", &DEMO.len().to_string()), Ok(theorems..DEMO.len()));
+        assert_eq!(range("title:Rules", "0"), Err(RangeError::UnknownTitle("Rules".to_string())));
+    }
+
+    #[test]
+    fn resolve_range_by_outline() {
+        let part = find("$(\n####");
+        let theorems = find("$(\n=-=-");
+        assert_eq!(range("outline:1", "outline:1"), Ok(part..DEMO.len()));
+        assert_eq!(range("outline:1.1.1", "outline:1.1.1"), Ok(theorems..DEMO.len()));
+        assert_eq!(range("outline:2", "0"), Err(RangeError::UnknownOutline("outline:2".to_string())));
+    }
+
+    #[test]
+    fn resolve_range_by_label() {
+        let span = |label: &str| {
+            let start = find(&format!("{label} $a"));
+            start..start + DEMO[start..].windows(2).position(|w| w == b"$.").unwrap() + 2
+        };
+        let (ax1, ax2) = (span("ax-1"), span("ax-2"));
+        assert_eq!(range("label:ax-1", "label:ax-2"), Ok(ax1.start..ax2.end));
+        assert_eq!(range("label:ax-1", "label:ax-1"), Ok(ax1.clone()));
+        assert_eq!(range("label:ax-2", "label:ax-1"), Err(RangeError::Reversed(ax2.start, ax1.end)));
+        assert_eq!(range("label:ax-3", "0"), Err(RangeError::UnknownLabel("ax-3".to_string())));
+    }
+}
@@ -0,0 +1,45 @@
+//! Token-level scanning of raw database text, for the tools that work on
+//! byte ranges which need not parse on their own.
+
+use std::ops::Range;
+
+/// Whitespace separated tokens with their byte offsets.
+pub fn tokens(input: &[u8]) -> impl Iterator<Item = (usize, &[u8])> {
+    let mut offset = 0;
+    input.split(|c| c.is_ascii_whitespace()).filter_map(move |token| {
+        let start = offset;
+        offset += token.len() + 1;
+        (!token.is_empty()).then_some((start, token))
+    })
+}
+
+/// Labeled statements outside of comments, as the offset of the label, the
+/// label and the keyword (`$a`, `$p`, `$e` or `$f`) that follows it.
+pub fn labeled_statements(input: &[u8]) -> Vec<(usize, &[u8], &[u8])> {
+    let mut is_comment = false;
+    let mut previous: Option<(usize, &[u8])> = None;
+    let mut statements = Vec::new();
+    for (offset, token) in tokens(input) {
+        match token {
+            b"$(" => is_comment = true,
+            b"$)" => is_comment = false,
+            _ if is_comment => {}
+            b"$a" | b"$p" | b"$e" | b"$f" => {
+                if let Some((label_offset, label)) = previous {
+                    statements.push((label_offset, label, token));
+                }
+            }
+            _ => {}
+        }
+        previous = Some((offset, token));
+    }
+    statements
+}
+
+/// Finds `label $x ... $.` outside of comments and returns its span.
+pub fn statement_span(input: &[u8], label: &[u8]) -> Option<Range<usize>> {
+    let (start, _, _) = labeled_statements(input).into_iter().find(|(_, l, _)| *l == label)?;
+    tokens(&input[start..])
+        .find(|(_, token)| *token == b"$.")
+        .map(|(offset, _)| start..start + offset + 2)
+}
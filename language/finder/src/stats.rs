@@ -0,0 +1,352 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::ops::AddAssign;
+
+use db_parser::document::Document;
+use db_parser::statement::{Descriptions, Statement};
+use db_parser::AsciiSequence;
+use serde::Serialize;
+
+use crate::title_tree::TitleTree;
+
+/// Number of statements of each kind.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Counts {
+    pub constants: usize,
+    pub variables: usize,
+    pub floating: usize,
+    pub essential: usize,
+    pub axioms: usize,
+    pub theorems: usize,
+}
+impl AddAssign for Counts {
+    fn add_assign(&mut self, other: Self) {
+        self.constants += other.constants;
+        self.variables += other.variables;
+        self.floating += other.floating;
+        self.essential += other.essential;
+        self.axioms += other.axioms;
+        self.theorems += other.theorems;
+    }
+}
+impl Display for Counts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>7}{:>7}{:>7}{:>7}{:>7}{:>7}",
+            self.constants, self.variables, self.floating, self.essential, self.axioms, self.theorems
+        )
+    }
+}
+
+/// The counts under a major part, section or subsection, nested headings
+/// included. Subsubsections are counted in their subsection.
+#[derive(Clone, Debug, Serialize)]
+pub struct HeadingStats {
+    pub level: usize,
+    pub ord: Vec<usize>,
+    pub title: String,
+    pub counts: Counts,
+    pub children: Vec<HeadingStats>,
+}
+impl Display for HeadingStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ord: String = self.ord.iter().map(|n| format!("{n}.")).collect();
+        writeln!(
+            f,
+            "{}  {}{ord} {}",
+            self.counts,
+            "  ".repeat(self.level - 1),
+            self.title
+        )?;
+        for child in &self.children {
+            child.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ProofSize {
+    pub label: String,
+    pub size: usize,
+}
+
+/// A `$p` statement and the number of proofs that use it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct References {
+    pub label: String,
+    pub count: usize,
+}
+
+/// A name from `(Contributed by ...)` and the number of `$p` statements it
+/// is given for.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Contributor {
+    pub name: String,
+    pub theorems: usize,
+}
+
+/// Statement counts and rankings of a database, for following it from one
+/// release to the next.
+#[derive(Clone, Debug, Serialize)]
+pub struct Stats {
+    pub total: Counts,
+    pub headings: Vec<HeadingStats>,
+    /// Sum of the `Proof::size` of every `$p` statement.
+    pub proof_size: usize,
+    pub longest_proofs: Vec<ProofSize>,
+    pub most_referenced: Vec<References>,
+    pub contributors: Vec<Contributor>,
+}
+impl Stats {
+    /// Gathers the statistics of a parsed database, keeping the `top`
+    /// entries of each ranking. Offsets are relative to `input`, the text
+    /// `document` was parsed from.
+    pub fn from_document(document: &Document, input: &[u8], top: usize) -> Self {
+        let ords: HashMap<usize, Vec<usize>> = TitleTree::from_document(document, input)
+            .flatten()
+            .into_iter()
+            .map(|node| (node.offset, node.ord.clone()))
+            .collect();
+        let mut builder = Builder {
+            input,
+            ords,
+            descriptions: Descriptions::default(),
+            proofs: Vec::new(),
+            references: HashMap::new(),
+            contributors: HashMap::new(),
+        };
+
+        let mut total = Counts::default();
+        let mut headings = Vec::new();
+        for major_part in &document.major_parts {
+            let mut counts = builder.statements(&major_part.statements);
+            let mut sections = Vec::new();
+            for section in &major_part.sections {
+                let mut section_counts = builder.statements(&section.statements);
+                let mut subsections = Vec::new();
+                for subsection in &section.subsections {
+                    let mut subsection_counts = builder.statements(&subsection.statements);
+                    for subsubsection in &subsection.subsubsections {
+                        subsection_counts += builder.statements(&subsubsection.statements);
+                    }
+                    section_counts += subsection_counts;
+                    builder.heading(
+                        3,
+                        subsection.heading,
+                        subsection.title,
+                        subsection_counts,
+                        Vec::new(),
+                        &mut subsections,
+                    );
+                }
+                counts += section_counts;
+                builder.heading(
+                    2,
+                    section.heading,
+                    section.title,
+                    section_counts,
+                    subsections,
+                    &mut sections,
+                );
+            }
+            total += counts;
+            builder.heading(1, major_part.heading, major_part.title, counts, sections, &mut headings);
+        }
+
+        let proof_size = builder.proofs.iter().map(|(_, size)| size).sum();
+        let mut longest_proofs: Vec<ProofSize> = builder
+            .proofs
+            .into_iter()
+            .map(|(label, size)| ProofSize { label, size })
+            .collect();
+        longest_proofs.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.label.cmp(&b.label)));
+        longest_proofs.truncate(top);
+        let mut most_referenced: Vec<References> = builder
+            .references
+            .into_iter()
+            .filter(|&(_, count)| count > 0)
+            .map(|(label, count)| References { label, count })
+            .collect();
+        most_referenced.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.label.cmp(&b.label)));
+        most_referenced.truncate(top);
+        let mut contributors: Vec<Contributor> = builder
+            .contributors
+            .into_iter()
+            .map(|(name, theorems)| Contributor { name, theorems })
+            .collect();
+        contributors.sort_by(|a, b| b.theorems.cmp(&a.theorems).then_with(|| a.name.cmp(&b.name)));
+        contributors.truncate(top);
+
+        Self {
+            total,
+            headings,
+            proof_size,
+            longest_proofs,
+            most_referenced,
+            contributors,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:>7}{:>7}{:>7}{:>7}{:>7}{:>7}", "$c", "$v", "$f", "$e", "$a", "$p")?;
+        writeln!(f, "{}  total", self.total)?;
+        for heading in &self.headings {
+            heading.fmt(f)?;
+        }
+        writeln!(f, "\nproof size: {} bytes", self.proof_size)?;
+        writeln!(f, "\nlongest proofs:")?;
+        for proof in &self.longest_proofs {
+            writeln!(f, "{:>9}  {}", proof.size, proof.label)?;
+        }
+        writeln!(f, "\nmost referenced:")?;
+        for references in &self.most_referenced {
+            writeln!(f, "{:>9}  {}", references.count, references.label)?;
+        }
+        writeln!(f, "\ncontributors:")?;
+        for contributor in &self.contributors {
+            writeln!(f, "{:>9}  {}", contributor.theorems, contributor.name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The name in a `(Contributed by NAME, DATE.)` note of a description.
+pub fn contributor(description: &str) -> Option<String> {
+    let (_, note) = description.split_once("(Contributed by ")?;
+    let note = &note[..note.find(')')?];
+    let name = note.rsplit_once(',').map_or(note, |(name, _)| name);
+    Some(name.split_whitespace().collect::<Vec<_>>().join(" ")).filter(|name| !name.is_empty())
+}
+
+struct Builder<'i> {
+    input: &'i [u8],
+    ords: HashMap<usize, Vec<usize>>,
+    descriptions: Descriptions<'i>,
+    proofs: Vec<(String, usize)>,
+    /// Proofs using each `$p` statement so far.
+    references: HashMap<String, usize>,
+    contributors: HashMap<String, usize>,
+}
+impl<'i> Builder<'i> {
+    /// Adds the node of an explicit heading to `siblings`. An implicit
+    /// heading, which stands in for a level the database skips, passes its
+    /// children up instead.
+    fn heading(
+        &self,
+        level: usize,
+        heading: AsciiSequence,
+        title: AsciiSequence,
+        counts: Counts,
+        children: Vec<HeadingStats>,
+        siblings: &mut Vec<HeadingStats>,
+    ) {
+        if heading.is_empty() {
+            siblings.extend(children);
+            return;
+        }
+        siblings.push(HeadingStats {
+            level,
+            ord: self
+                .ords
+                .get(&heading.offset_in(self.input))
+                .cloned()
+                .unwrap_or_default(),
+            title: String::from_utf8_lossy(title.as_bytes()).into_owned(),
+            counts,
+            children,
+        });
+    }
+
+    fn statements(&mut self, statements: &[Statement<'i>]) -> Counts {
+        let mut counts = Counts::default();
+        for statement in statements {
+            self.statement(statement, &mut counts);
+        }
+        counts
+    }
+
+    fn statement(&mut self, statement: &Statement<'i>, counts: &mut Counts) {
+        let description = self.descriptions.describe(statement);
+        match statement {
+            Statement::Comment(_) => {}
+            Statement::Block(statements) => {
+                for statement in statements {
+                    self.statement(statement, counts);
+                }
+            }
+            Statement::ConstantDeclaration(declaration) => counts.constants += declaration.symbol_list.len(),
+            Statement::VariableDeclaration(declaration) => counts.variables += declaration.symbol_list.len(),
+            Statement::DisjoinVariableDeclaration(_) => {}
+            Statement::FloatingHypothesis(_) => counts.floating += 1,
+            Statement::EssentialHypothesis(_) => counts.essential += 1,
+            Statement::AxiomaticAssertion(_) => counts.axioms += 1,
+            Statement::ProvableAssertion(assert) => {
+                counts.theorems += 1;
+                let label = assert.label.to_string();
+                self.proofs.push((label.clone(), assert.proof.size()));
+                let used: HashSet<String> = assert.proof.labels.iter().map(|label| label.to_string()).collect();
+                for used in used {
+                    if let Some(count) = self.references.get_mut(&used) {
+                        *count += 1;
+                    }
+                }
+                let description = description.map(|comment| String::from_utf8_lossy(comment.as_bytes()).into_owned());
+                if let Some(name) = description.as_deref().and_then(contributor) {
+                    *self.contributors.entry(name).or_default() += 1;
+                }
+                self.references.insert(label, 0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &[u8] = include_bytes!("../../fixtures/demo.mm");
+
+    #[test]
+    fn counts_and_rankings_of_the_demo_database() {
+        let (_, document) = db_parser::document::document(DEMO).unwrap();
+        let stats = Stats::from_document(&document, DEMO, 2);
+
+        let total = Counts {
+            constants: 6,
+            variables: 3,
+            floating: 3,
+            essential: 11,
+            axioms: 5,
+            theorems: 4,
+        };
+        assert_eq!(stats.total, total);
+        assert_eq!(stats.headings.len(), 1);
+        assert_eq!(stats.headings[0].children[0].children[0].title, "Theorems");
+        assert_eq!(stats.headings[0].children[0].children[0].counts.theorems, 4);
+        assert_eq!(stats.proof_size, 80);
+        let longest = ProofSize {
+            label: "a1i".to_string(),
+            size: "( wi ax-1 ax-mp ) ABADCABEF".len(),
+        };
+        assert_eq!(stats.longest_proofs[0], longest);
+        assert_eq!(stats.longest_proofs.len(), 2);
+        assert!(stats.most_referenced.is_empty());
+        let names: Vec<&str> = stats.contributors.iter().map(|contributor| contributor.name.as_str()).collect();
+        assert_eq!(names, ["Mario Carneiro", "NM"]);
+    }
+
+    #[test]
+    fn contributor_names() {
+        assert_eq!(contributor("Text. (Contributed by NM, 30-Sep-1992.)").as_deref(), Some("NM"));
+        let wrapped = "(Contributed by Mario\n      Carneiro, 24-Jan-2013.)";
+        assert_eq!(contributor(wrapped).as_deref(), Some("Mario Carneiro"));
+        assert_eq!(contributor("(Proof shortened by NM, 1-Jan-2000.)"), None);
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use db_parser::document::Document;
+use db_parser::statement::{Descriptions, Statement};
+use db_parser::AsciiSequence;
+use serde::{Deserialize, Serialize};
+
+use crate::title_tree::TitleTree;
+
+/// Bump when the stored layout changes, so that old index files are rebuilt.
+const FORMAT_VERSION: u32 = 1;
+/// Characters of a comment kept to show with search results.
+const SNIPPET_LENGTH: usize = 120;
+
+/// The parts of an indexed item that words can come from, with the weight of
+/// a word found there.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    Label,
+    Title,
+    Comment,
+}
+impl Field {
+    fn weight(&self) -> f64 {
+        match self {
+            Self::Label => 3.0,
+            Self::Title => 2.0,
+            Self::Comment => 1.0,
+        }
+    }
+}
+
+/// An `$a` or `$p` statement with its description, or a heading.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexedItem {
+    /// The label of a statement, or the outline number and title of a
+    /// heading.
+    pub name: String,
+    /// `$a`, `$p`, or `heading`.
+    pub kind: String,
+    pub offset: usize,
+    /// The start of the description or heading text.
+    pub snippet: String,
+}
+
+/// How often a word occurs in one field of one item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Posting {
+    pub item: usize,
+    pub field: Field,
+    pub count: usize,
+}
+
+/// Identifies the database text an index was built from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceStamp {
+    pub version: u32,
+    pub length: usize,
+    pub hash: u64,
+}
+impl SourceStamp {
+    pub fn of(input: &[u8]) -> Self {
+        // FNV-1a, which unlike `DefaultHasher` gives the same value in
+        // every build.
+        let hash = input
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, &c| (hash ^ u64::from(c)).wrapping_mul(0x0100_0000_01b3));
+        Self {
+            version: FORMAT_VERSION,
+            length: input.len(),
+            hash,
+        }
+    }
+}
+
+/// An inverted index from words to the statements and headings they occur
+/// in: in labels, heading titles, or description and heading comments.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextIndex {
+    pub source: SourceStamp,
+    pub items: Vec<IndexedItem>,
+    pub postings: BTreeMap<String, Vec<Posting>>,
+}
+
+/// Lowercased runs of letters and digits, at least two characters long.
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 1)
+        .map(str::to_lowercase)
+}
+
+/// The database text could not be parsed, so there is nothing to index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError(pub String);
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot parse database: {}", self.0)
+    }
+}
+impl std::error::Error for ParseError {}
+
+impl TextIndex {
+    /// Indexes the database text `input`. If it does not parse to the end,
+    /// the parsed part is indexed.
+    pub fn build(input: &[u8]) -> Result<Self, ParseError> {
+        let mut builder = Builder {
+            input,
+            index: TextIndex {
+                source: SourceStamp::of(input),
+                items: Vec::new(),
+                postings: BTreeMap::new(),
+            },
+            descriptions: Descriptions::default(),
+        };
+        let (_, document) = db_parser::document::document(input).map_err(|error| ParseError(format!("{error:?}")))?;
+        builder.document(&document, &TitleTree::from_document(&document, input));
+        Ok(builder.index)
+    }
+
+    /// The index file kept next to `database`, such as `set.mm.index.json`.
+    pub fn path_for(database: &Path) -> PathBuf {
+        let mut name = database.file_name().unwrap_or_default().to_os_string();
+        name.push(".index.json");
+        database.with_file_name(name)
+    }
+
+    /// Reads the index stored next to `database`, or builds and stores a new
+    /// one if there is none or it was built from different text. Failing to
+    /// store the index is not an error: the next call builds it again.
+    pub fn load_or_build(database: &Path) -> io::Result<Self> {
+        let input = std::fs::read(database)?;
+        let path = Self::path_for(database);
+        let stored = std::fs::read(&path)
+            .ok()
+            .and_then(|stored| serde_json::from_slice::<TextIndex>(&stored).ok());
+        match stored {
+            Some(index) if index.source == SourceStamp::of(&input) => Ok(index),
+            _ => {
+                let index = Self::build(&input).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+                if let Ok(stored) = serde_json::to_vec(&index) {
+                    let _ = std::fs::write(&path, stored);
+                }
+                Ok(index)
+            }
+        }
+    }
+
+    /// The items matching the words of `query`, best first.
+    ///
+    /// Query words containing punctuation, such as `ax-mp`, also match
+    /// labels as a whole. Items containing more of the query words come
+    /// first; among those, a tf-idf score ranks words in labels above words
+    /// in titles, and those above words in comments.
+    pub fn search(&self, query: &str) -> Vec<TextHit> {
+        let mut query_words: Vec<String> = words(query)
+            .chain(
+                query
+                    .split_whitespace()
+                    .filter(|token| token.contains(|c: char| !c.is_alphanumeric()))
+                    .map(str::to_lowercase),
+            )
+            .collect();
+        query_words.sort();
+        query_words.dedup();
+
+        let mut scores: HashMap<usize, (usize, f64)> = HashMap::new();
+        for word in &query_words {
+            let Some(postings) = self.postings.get(word) else {
+                continue;
+            };
+            let mut items: Vec<usize> = postings.iter().map(|posting| posting.item).collect();
+            items.dedup();
+            let idf = (1.0 + self.items.len() as f64 / items.len() as f64).ln();
+            let mut last = None;
+            for posting in postings {
+                let score = scores.entry(posting.item).or_default();
+                if last != Some(posting.item) {
+                    score.0 += 1;
+                    last = Some(posting.item);
+                }
+                let count = posting.count as f64;
+                score.1 += idf * posting.field.weight() * count / (count + 1.2);
+            }
+        }
+
+        let mut hits: Vec<TextHit> = scores
+            .into_iter()
+            .map(|(item, (matched, score))| TextHit {
+                item: self.items[item].clone(),
+                matched,
+                score,
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.matched
+                .cmp(&a.matched)
+                .then(b.score.total_cmp(&a.score))
+                .then(a.item.offset.cmp(&b.item.offset))
+        });
+        hits
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TextHit {
+    #[serde(flatten)]
+    pub item: IndexedItem,
+    /// Number of distinct query words the item contains.
+    pub matched: usize,
+    pub score: f64,
+}
+impl Display for TextHit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {:.2}", self.item.name, self.item.kind, self.score)?;
+        if !self.item.snippet.is_empty() {
+            write!(f, "\n  {}", self.item.snippet)?;
+        }
+        Ok(())
+    }
+}
+
+struct Builder<'i> {
+    input: &'i [u8],
+    index: TextIndex,
+    descriptions: Descriptions<'i>,
+}
+impl<'i> Builder<'i> {
+    fn document(&mut self, document: &Document<'i>, tree: &TitleTree) {
+        let ords: HashMap<usize, String> =
+            tree.flatten().iter().map(|node| (node.offset, node.ord_string())).collect();
+        let heading = |builder: &mut Self, heading: AsciiSequence, title: AsciiSequence, text: AsciiSequence| {
+            if heading.is_empty() {
+                return;
+            }
+            let offset = heading.offset_in(builder.input);
+            let title = String::from_utf8_lossy(title.as_bytes()).into_owned();
+            let text = String::from_utf8_lossy(text.as_bytes()).into_owned();
+            let name = format!("{} {title}", ords.get(&offset).map_or("", String::as_str));
+            builder.push(name, "heading", offset, &[(Field::Title, &title), (Field::Comment, &text)]);
+        };
+        for major_part in &document.major_parts {
+            heading(self, major_part.heading, major_part.title, major_part.heading_comments);
+            self.statements(&major_part.statements);
+            for section in &major_part.sections {
+                heading(self, section.heading, section.title, section.heading_comments);
+                self.statements(&section.statements);
+                for subsection in &section.subsections {
+                    heading(self, subsection.heading, subsection.title, subsection.heading_comments);
+                    self.statements(&subsection.statements);
+                    for subsubsection in &subsection.subsubsections {
+                        heading(self, subsubsection.heading, subsubsection.title, subsubsection.heading_comments);
+                        self.statements(&subsubsection.statements);
+                    }
+                }
+            }
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement<'i>]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement<'i>) {
+        let description = self.descriptions.describe(statement);
+        let (label, keyword) = match statement {
+            Statement::Block(statements) => {
+                for statement in statements {
+                    self.statement(statement);
+                }
+                return;
+            }
+            Statement::AxiomaticAssertion(assert) => (assert.label, "$a"),
+            Statement::ProvableAssertion(assert) => (assert.label, "$p"),
+            _ => return,
+        };
+        let comment = description
+            .map(|comment| String::from_utf8_lossy(comment.as_bytes()).into_owned())
+            .unwrap_or_default();
+        let offset = label.0.offset_in(self.input);
+        let label = label.to_string();
+        self.push(label.clone(), keyword, offset, &[(Field::Label, &label), (Field::Comment, &comment)]);
+    }
+
+    fn push(&mut self, name: String, kind: &str, offset: usize, fields: &[(Field, &str)]) {
+        let item = self.index.items.len();
+        let mut counts: BTreeMap<(String, Field), usize> = BTreeMap::new();
+        for &(field, text) in fields {
+            for word in words(text) {
+                *counts.entry((word, field)).or_default() += 1;
+            }
+            // Labels are indexed whole as well as split at punctuation, so
+            // that `ax-mp` is found both as itself and as `mp`.
+            if field == Field::Label && text.contains(|c: char| !c.is_alphanumeric()) {
+                *counts.entry((text.to_lowercase(), field)).or_default() += 1;
+            }
+        }
+        for ((word, field), count) in counts {
+            self.index.postings.entry(word).or_default().push(Posting { item, field, count });
+        }
+        let snippet_source = fields.iter().find(|(field, _)| *field == Field::Comment).map_or("", |(_, text)| *text);
+        let snippet = snippet_source.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.index.items.push(IndexedItem {
+            name,
+            kind: kind.to_string(),
+            offset,
+            snippet: snippet.chars().take(SNIPPET_LENGTH).collect(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    fn names(hits: &[TextHit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.item.name.as_str()).collect()
+    }
+
+    /// An empty directory of its own for a test.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("text-index-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn build_indexes_statements_and_headings() {
+        let index = TextIndex::build(DEMO.as_bytes()).unwrap();
+        assert_eq!(index.source, SourceStamp::of(DEMO.as_bytes()));
+        let items: Vec<(&str, &str)> = index.items.iter().map(|item| (item.name.as_str(), item.kind.as_str())).collect();
+        assert_eq!(items[0], ("1. PROPOSITIONAL CALCULUS", "heading"));
+        assert!(items.contains(&("ax-mp", "$a")));
+        assert!(items.contains(&("mp2b", "$p")));
+        assert!(!items.iter().any(|(name, _)| *name == "wph"));
+
+        let ax_mp = index.items.iter().position(|item| item.name == "ax-mp").unwrap();
+        assert_eq!(index.items[ax_mp].snippet, "Rule of Modus Ponens. (Contributed by NM, 30-Sep-1992.)");
+        let postings = &index.postings["ax-mp"];
+        assert_eq!(postings, &[Posting { item: ax_mp, field: Field::Label, count: 1 }]);
+    }
+
+    #[test]
+    fn search_ranks_labels_above_comments() {
+        let index = TextIndex::build(DEMO.as_bytes()).unwrap();
+        // mp2c mentions mp2b in its comment only.
+        assert_eq!(names(&index.search("mp2b")), ["mp2b", "mp2c"]);
+        // `ax-mp` matches as a whole and as `ax` and `mp`; the other axioms
+        // only as `ax`.
+        let hits = index.search("ax-mp");
+        assert_eq!(names(&hits), ["ax-mp", "ax-1", "ax-2"]);
+        assert_eq!(hits[0].matched, 3);
+        assert_eq!(hits[1].matched, 1);
+        // Items with more of the query words come first.
+        let hits = index.search("modus ponens theorem");
+        assert_eq!(names(&hits), ["mp1", "ax-mp", "mp2b"]);
+        assert_eq!(hits[0].matched, 3);
+        assert!(index.search("nonexistent").is_empty());
+    }
+
+    #[test]
+    fn load_or_build_stores_the_index_and_rebuilds_it_when_the_source_changes() {
+        let dir = scratch("stamp");
+        let database = dir.join("demo.mm");
+        std::fs::write(&database, DEMO).unwrap();
+        let path = TextIndex::path_for(&database);
+        assert_eq!(path, dir.join("demo.mm.index.json"));
+
+        let built = TextIndex::load_or_build(&database).unwrap();
+        let stored: TextIndex = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(stored, built);
+
+        // A stored index with the right stamp is used as it is.
+        let mut marked = built.clone();
+        marked.items[0].name = "marked".to_string();
+        std::fs::write(&path, serde_json::to_vec(&marked).unwrap()).unwrap();
+        assert_eq!(TextIndex::load_or_build(&database).unwrap(), marked);
+
+        // Changing the database changes the stamp, so the index is rebuilt.
+        let changed = DEMO.replace("Modus ponens as a theorem.", "Detachment as a theorem.");
+        std::fs::write(&database, &changed).unwrap();
+        let rebuilt = TextIndex::load_or_build(&database).unwrap();
+        assert_eq!(rebuilt.source, SourceStamp::of(changed.as_bytes()));
+        assert_eq!(names(&rebuilt.search("detachment")), ["mp1"]);
+        assert_eq!(serde_json::from_slice::<TextIndex>(&std::fs::read(&path).unwrap()).unwrap(), rebuilt);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn load_or_build_still_returns_the_index_when_it_cannot_be_stored() {
+        let dir = scratch("unwritable");
+        let database = dir.join("demo.mm");
+        std::fs::write(&database, DEMO).unwrap();
+        // A directory in the way of the index file makes writing it fail.
+        std::fs::create_dir(TextIndex::path_for(&database)).unwrap();
+        let index = TextIndex::load_or_build(&database).unwrap();
+        assert_eq!(index, TextIndex::build(DEMO.as_bytes()).unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,141 +1,432 @@
-use std::{sync::OnceLock, fs::File, io::{Read, Write as IoWrite}, fmt::Write as FmtWrite};
-use regex::Regex;
-
-pub const MAJOR_PART_PATTERN: &str = r"(?x) # Enable verbose mode (ignores whitespace in pattern)
-    ^\s*\#\#\#\#*\n                     # Match initial #### pattern line
-    \s*                                 # Optional whitespace
-    (.+?)                               # Capture the title (non-greedy)
-    \s*                                 # Optional whitespace
-    \#\#\#\#*                           # Match ending #### pattern line
-    ";
-
-pub const SECTION_PATTERN: &str = r"(?x) # Enable verbose mode (ignores whitespace in pattern)
-    ^\s*\#\*\#\*.*\n                 # Match initial #*#* pattern line
-    \s*                              # Optional whitespace
-    (.+?)                            # Capture the title (non-greedy)
-    \s*                              # Optional whitespace
-    \#\*\#\*                         # Match ending #*#* pattern line
-    ";
-
-pub const SUBSECTION_PATTERN: &str = r"(?x) # Enable verbose mode (ignores whitespace in pattern)
-    ^\s*\=\-\=\-.*\n                    # Match initial =-=- pattern line
-    \s*                                 # Optional whitespace
-    (.+?)                               # Capture the title (non-greedy)
-    \s*                                 # Optional whitespace
-    \=\-\=\-                            # Match ending =-=- pattern line
-    ";
-
-pub const SUBSUBSECTION_PATTERN: &str = r"(?x) # Enable verbose mode (ignores whitespace in pattern)
-    ^\s*\-\.\-\..*\n                       # Match initial -.-. pattern line
-    \s*                                    # Optional whitespace
-    (.+?)                                  # Capture the title (non-greedy)
-    \s*                                    # Optional whitespace
-    \-\.\-\.                               # Match ending -.-. pattern line
-    ";
-
-pub fn extract_major_part(text: &str) -> Option<String> {
-    static MAJOR_PART_MATCH: OnceLock<Regex> = OnceLock::new();
-    if MAJOR_PART_MATCH.get_or_init(|| {
-        Regex::new(MAJOR_PART_PATTERN).unwrap()
-    }).is_match(text) {
-        let re = Regex::new(MAJOR_PART_PATTERN).unwrap();
-        re.captures(text)
-            .and_then(|caps| caps.get(1))
-            .map(|m| m.as_str().trim().to_string())
-    } else { None }
+use std::{fs::File, io::{Read, Write as IoWrite}, fmt::{Display, Write as FmtWrite}};
+use db_parser::document::Document;
+use db_parser::statement::Statement;
+use db_parser::AsciiSequence;
+use serde::Serialize;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TitleTree {
+    pub nodes: Vec<TitleNode>,
+    pub warnings: Vec<TitleWarning>,
 }
+impl TitleTree {
+    /// The heading outline of a parsed database. Offsets are relative to
+    /// `input`, the text `document` was parsed from.
+    pub fn from_document(document: &Document, input: &[u8]) -> Self {
+        let mut builder = Builder {
+            input,
+            tree: TitleTree::default(),
+            counts: [0; 4],
+            last_level: 0,
+        };
+        for major_part in &document.major_parts {
+            builder.heading(1, major_part.heading, major_part.title);
+            for section in &major_part.sections {
+                builder.heading(2, section.heading, section.title);
+                for subsection in &section.subsections {
+                    builder.heading(3, subsection.heading, subsection.title);
+                    for subsubsection in &subsection.subsubsections {
+                        builder.heading(4, subsubsection.heading, subsubsection.title);
+                    }
+                }
+            }
+        }
+
+        let mut assertions = Vec::new();
+        for statement in document.statements() {
+            push_assertions(statement, input, &mut assertions);
+        }
+        let mut tree = builder.tree;
+        tree.finish(input.len(), &assertions);
+        tree
+    }
+
+    /// Every node in document order.
+    pub fn flatten(&self) -> Vec<&TitleNode> {
+        fn visit<'t>(nodes: &'t [TitleNode], flat: &mut Vec<&'t TitleNode>) {
+            for node in nodes {
+                flat.push(node);
+                visit(&node.children, flat);
+            }
+        }
+        let mut flat = Vec::new();
+        visit(&self.nodes, &mut flat);
+        flat
+    }
+
+    /// The users' mathboxes with their owners: the headings titled
+    /// `Mathbox for <owner>` directly under the last major part, when its
+    /// title speaks of mathboxes as set.mm's does. Other headings there, such
+    /// as guidelines, are not mathboxes.
+    pub fn mathboxes(&self) -> Vec<(&str, &TitleNode)> {
+        match self.nodes.last() {
+            Some(part) if part.title.to_ascii_lowercase().contains("mathbox") => part
+                .children
+                .iter()
+                .filter_map(|node| Some((node.title.strip_prefix("Mathbox for ")?, node)))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn push(&mut self, level: usize, ord: Vec<usize>, title: String, offset: usize) {
+        let mut nodes = &mut self.nodes;
+        while nodes.last().is_some_and(|node| node.level < level) {
+            nodes = &mut nodes.last_mut().unwrap().children;
+        }
+        nodes.push(TitleNode {
+            level,
+            ord,
+            title,
+            offset,
+            end: offset,
+            statement_count: 0,
+            first_label: None,
+            last_label: None,
+            children: Vec::new(),
+        });
+    }
+
+    /// Fills in where each heading ends and the assertions under it, given
+    /// the `(offset, label)` of every `$a` and `$p` in order.
+    fn finish(&mut self, len: usize, assertions: &[(usize, String)]) {
+        fn visit(nodes: &mut [TitleNode], end: usize, assertions: &[(usize, String)]) {
+            for i in 0..nodes.len() {
+                let node_end = nodes.get(i + 1).map_or(end, |next| next.offset);
+                let node = &mut nodes[i];
+                node.end = node_end;
+                let first = assertions.partition_point(|(offset, _)| *offset < node.offset);
+                let last = assertions.partition_point(|(offset, _)| *offset < node.end);
+                node.statement_count = last - first;
+                node.first_label = assertions[first..last].first().map(|(_, label)| label.clone());
+                node.last_label = assertions[first..last].last().map(|(_, label)| label.clone());
+                visit(&mut node.children, node_end, assertions);
+            }
+        }
+        visit(&mut self.nodes, len, assertions);
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// A nested Markdown list, one item per heading.
+    pub fn to_markdown(&self) -> String {
+        fn visit(nodes: &[TitleNode], depth: usize, o: &mut String) {
+            for node in nodes {
+                let _ = writeln!(o, "{}- {} {}{}", "  ".repeat(depth), node.ord_string(), node.title, node.summary());
+                visit(&node.children, depth + 1, o);
+            }
+        }
+        let mut o = String::new();
+        visit(&self.nodes, 0, &mut o);
+        o
+    }
 
-pub fn extract_section(text: &str) -> Option<String> {
-    static SECTION_MATCH: OnceLock<Regex> = OnceLock::new();
-    if SECTION_MATCH.get_or_init(|| {
-        Regex::new(SECTION_PATTERN).unwrap()
-    }).is_match(text) {
-        let re = Regex::new(SECTION_PATTERN).unwrap();
-        re.captures(text)
-            .and_then(|caps| caps.get(1))
-            .map(|m| m.as_str().trim().to_string())
-    } else { None }
+    /// A table of contents in the style of metamath's `mmtheorems.html`.
+    /// Headings link to `#mm<outline number>` anchors and labels to
+    /// `<label>.html` theorem pages.
+    pub fn to_html(&self) -> String {
+        fn visit(nodes: &[TitleNode], depth: usize, o: &mut String) {
+            if nodes.is_empty() {
+                return;
+            }
+            let indent = "  ".repeat(depth);
+            let _ = writeln!(o, "{indent}<ul>");
+            for node in nodes {
+                let _ = write!(
+                    o,
+                    "{indent}  <li><a href=\"#{}\">{} {}</a>",
+                    node.anchor(),
+                    node.ord_string(),
+                    escape_html(&node.title)
+                );
+                if let (Some(first), Some(last)) = (&node.first_label, &node.last_label) {
+                    let _ = write!(
+                        o,
+                        " <small>({} statements, <a href=\"{first}.html\">{first}</a> &ndash; <a href=\"{last}.html\">{last}</a>)</small>",
+                        node.statement_count
+                    );
+                }
+                if node.children.is_empty() {
+                    let _ = writeln!(o, "</li>");
+                } else {
+                    let _ = writeln!(o);
+                    visit(&node.children, depth + 2, o);
+                    let _ = writeln!(o, "{indent}  </li>");
+                }
+            }
+            let _ = writeln!(o, "{indent}</ul>");
+        }
+        let mut o = String::new();
+        let _ = writeln!(o, "<nav class=\"toc\">");
+        let _ = writeln!(o, "<h2>Table of Contents</h2>");
+        visit(&self.nodes, 0, &mut o);
+        let _ = writeln!(o, "</nav>");
+        o
+    }
+}
+impl Display for TitleTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for node in &self.nodes {
+            node.fmt(f)?;
+        }
+        Ok(())
+    }
 }
 
-pub fn extract_sub_section(text: &str) -> Option<String> {
-    static SUBSECTION_MATCH: OnceLock<Regex> = OnceLock::new();
-    if SUBSECTION_MATCH.get_or_init(|| {
-        Regex::new(SUBSECTION_PATTERN).unwrap()
-    }).is_match(text) {
-        let re = Regex::new(SUBSECTION_PATTERN).unwrap();
-        re.captures(text)
-            .and_then(|caps| caps.get(1))
-            .map(|m| m.as_str().trim().to_string())
-    } else { None }
+/// A heading together with the headings nested under it. `level` runs from
+/// 1 (major part) to 4 (subsubsection); a node may sit directly under a node
+/// more than one level above it when the database skips a level.
+#[derive(Clone, Debug, Serialize)]
+pub struct TitleNode {
+    pub level: usize,
+    pub ord: Vec<usize>,
+    pub title: String,
+    pub offset: usize,
+    /// Offset of the next heading at the same or a higher level.
+    pub end: usize,
+    /// Number of `$a` and `$p` statements under the heading, nested
+    /// headings included.
+    pub statement_count: usize,
+    pub first_label: Option<String>,
+    pub last_label: Option<String>,
+    pub children: Vec<TitleNode>,
 }
+impl TitleNode {
+    pub fn ord_string(&self) -> String {
+        self.ord.iter().map(|n| format!("{n}.")).collect()
+    }
 
-pub fn extract_sub_sub_section(text: &str) -> Option<String> {
-    static SUBSUBSECTION_MATCH: OnceLock<Regex> = OnceLock::new();
-    if SUBSUBSECTION_MATCH.get_or_init(|| {
-        Regex::new(SUBSUBSECTION_PATTERN).unwrap()
-    }).is_match(text) {
-        let re = Regex::new(SUBSUBSECTION_PATTERN).unwrap();
-        re.captures(text)
-            .and_then(|caps| caps.get(1))
-            .map(|m| m.as_str().trim().to_string())
-    } else { None }
+    pub fn anchor(&self) -> String {
+        format!("mm{}", self.ord.iter().map(|n| n.to_string()).collect::<Vec<_>>().join("."))
+    }
+
+    fn summary(&self) -> String {
+        match (&self.first_label, &self.last_label) {
+            (Some(first), Some(last)) => format!(" ({} statements, {first} - {last})", self.statement_count),
+            _ => String::new(),
+        }
+    }
 }
 
-pub fn find_and_write_title_tree(file_path: &str) {
-    let mut file = File::open(file_path).unwrap();
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap();
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+impl Display for TitleNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let indent = "  ".repeat(self.level - 1);
+        writeln!(f, "{indent}{} {} $ @[{}(byte)]", self.ord_string(), self.title, self.offset)?;
+        for child in &self.children {
+            child.fmt(f)?;
+        }
+        Ok(())
+    }
+}
 
-    let mut lookbehind = b' ';
-    let mut comment = Vec::new();
-    let mut is_comment = false;
-    let mut idx = 0;
-    let mut stack = vec![vec![vec![vec![()]]]];
-    let mut last_level = 0;
-
-    let mut o = String::new();
-
-    for (i, &b) in contents.as_bytes().into_iter().enumerate() {
-        if lookbehind == b'$' && b == b')' {
-            is_comment = false;
-            comment.pop();
-            let s = String::from_utf8_lossy(&comment);
-            if let Some(title) = extract_major_part(&s) {
-                let ord = format!("{}.", stack.len());
-                o.write_str(format!("{ord} {} $ @[{idx}(byte)]\n", title).as_str()).unwrap();
-                stack.push(vec![vec![vec![()]]]);
-                last_level = 1;
-            } else if let Some(title) = extract_section(&s) {
-                let ord = format!("{}.{}.", stack.len() - 1, stack.last().unwrap().len());
-                o.write_str(format!("  {ord} {} $ @[{idx}(byte)]\n", title).as_str()).unwrap();
-                stack.last_mut().unwrap().push(vec![vec![()]]);
-                last_level = 2;
-            } else if let Some(title) = extract_sub_section(&s) {
-                let ord = format!("{}.{}.{}.", stack.len() - 1, stack.last().unwrap().len() - 1, stack.last().unwrap().last().unwrap().len());
-                o.write_str(format!("    {ord} {} $ @[{idx}(byte)]\n", title).as_str()).unwrap();
-                stack.last_mut().unwrap().last_mut().unwrap().push(vec![()]);
-                if last_level == 1 { println!("{ord} has 2 level difference.") }
-                last_level = 3;
-            } else if let Some(title) = extract_sub_sub_section(&s) {
-                let ord = format!("{}.{}.{}.{}.", stack.len() - 1, stack.last().unwrap().len() - 1, stack.last().unwrap().last().unwrap().len() - 1, stack.last().unwrap().last().unwrap().last().unwrap().len());
-                o.write_str(format!("      {ord} {} $ @[{idx}(byte)]\n", title).as_str()).unwrap();
-                stack.last_mut().unwrap().last_mut().unwrap().last_mut().unwrap().push(());
-                if last_level <= 2 { println!("{ord} has 2~3 level difference.") }
-                last_level = 4;
-            }
-            comment.clear();
+/// Parses `input` and builds its heading outline. If parsing stops early the
+/// outline covers the parsed part and carries a `TitleWarning::Unparsed`.
+pub fn title_tree(input: &[u8]) -> TitleTree {
+    let Ok((rest, document)) = db_parser::document::document(input) else {
+        return TitleTree {
+            nodes: Vec::new(),
+            warnings: vec![TitleWarning::Unparsed { offset: 0 }],
+        };
+    };
+    let mut tree = TitleTree::from_document(&document, input);
+    if let Some(position) = rest.iter().position(|c| !c.is_ascii_whitespace()) {
+        tree.warnings.push(TitleWarning::Unparsed {
+            offset: input.len() - rest.len() + position,
+        });
+    }
+    tree
+}
+
+struct Builder<'i> {
+    input: &'i [u8],
+    tree: TitleTree,
+    counts: [usize; 4],
+    last_level: usize,
+}
+impl Builder<'_> {
+    /// Numbers and records an explicit heading. Implicit headings, which stand
+    /// in for a level the database skips, only leave a 0 in the numbering.
+    fn heading(&mut self, level: usize, heading: AsciiSequence, title: AsciiSequence) {
+        if heading.is_empty() {
+            return;
         }
-        if is_comment {
-            comment.push(b);
+        self.counts[level - 1] += 1;
+        self.counts[level..].fill(0);
+        let ord = self.counts[..level].to_vec();
+        if level > self.last_level + 1 {
+            self.tree.warnings.push(TitleWarning::SkippedLevel {
+                ord: ord.clone(),
+                level,
+                previous_level: self.last_level,
+            });
         }
-        if lookbehind == b'$' && b == b'(' {
-            is_comment = true;
-            idx = i - 1;
+        let title = String::from_utf8_lossy(title.as_bytes()).into_owned();
+        self.tree.push(level, ord, title, heading.offset_in(self.input));
+        self.last_level = level;
+    }
+}
+
+fn push_assertions(statement: &Statement, input: &[u8], assertions: &mut Vec<(usize, String)>) {
+    match statement {
+        Statement::AxiomaticAssertion(assert) => {
+            assertions.push((assert.label.0.offset_in(input), assert.label.to_string()));
+        }
+        Statement::ProvableAssertion(assert) => {
+            assertions.push((assert.label.0.offset_in(input), assert.label.to_string()));
+        }
+        Statement::Block(statements) => {
+            for statement in statements {
+                push_assertions(statement, input, assertions);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TitleWarning {
+    /// A heading more than one level below the heading before it.
+    SkippedLevel {
+        ord: Vec<usize>,
+        level: usize,
+        previous_level: usize,
+    },
+    /// The database does not parse past this offset, so later headings are
+    /// missing from the tree.
+    Unparsed { offset: usize },
+}
+impl Display for TitleWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SkippedLevel { ord, level, previous_level } => write!(
+                f,
+                "{} is a level {level} heading after a level {previous_level} heading",
+                ord.iter().map(|n| format!("{n}.")).collect::<String>()
+            ),
+            Self::Unparsed { offset } => write!(f, "parsing stopped at byte {offset}"),
         }
-        lookbehind = b;
     }
+}
 
-    let output_file_name = format!("../{}-title-tree.txt", file_path.split('/').last().unwrap());
+pub fn find_and_write_title_tree(file_path: &str) {
+    let mut file = File::open(file_path).unwrap();
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+
+    let tree = title_tree(contents.as_bytes());
+
+    let output_file_name = format!("../{}-title-tree.txt", file_path.split('/').next_back().unwrap());
     let mut output_file = File::create(output_file_name).unwrap();
-    output_file.write(&o.as_bytes()).unwrap();
-}
\ No newline at end of file
+    output_file.write_all(format!("{tree}").as_bytes()).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &[u8] = include_bytes!("../../fixtures/demo.mm");
+
+    #[test]
+    fn json_outline_nests_the_headings_with_their_statements() {
+        let json: serde_json::Value = serde_json::from_str(&title_tree(DEMO).to_json()).unwrap();
+        let part = &json["nodes"][0];
+        let axioms = &part["children"][0];
+        let theorems = &axioms["children"][0];
+        assert_eq!(json["nodes"].as_array().unwrap().len(), 1);
+        assert_eq!(part["title"], "PROPOSITIONAL CALCULUS");
+        assert_eq!(axioms["title"], "Axioms");
+        assert_eq!(axioms["ord"], serde_json::json!([1, 1]));
+        assert_eq!(theorems["ord"], serde_json::json!([1, 1, 1]));
+        assert_eq!(theorems["level"], 3);
+        assert_eq!(theorems["statement_count"], 4);
+        assert_eq!(theorems["first_label"], "a1i");
+        assert_eq!(theorems["last_label"], "mp1");
+        assert_eq!(part["statement_count"], 9);
+        assert_eq!(part["first_label"], "wn");
+        assert_eq!(part["last_label"], "mp1");
+        assert_eq!(theorems["children"], serde_json::json!([]));
+        assert_eq!(json["warnings"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn markdown_outline_is_a_nested_list() {
+        let expected = [
+            "- 1. PROPOSITIONAL CALCULUS (9 statements, wn - mp1)",
+            "  - 1.1. Axioms (9 statements, wn - mp1)",
+            "    - 1.1.1. Theorems (4 statements, a1i - mp1)",
+        ];
+        assert_eq!(title_tree(DEMO).to_markdown(), expected.map(|line| line.to_string() + "\n").concat());
+    }
+
+    #[test]
+    fn html_outline_links_headings_and_labels() {
+        let html = title_tree(DEMO).to_html();
+        let items: Vec<&str> = html.lines().map(str::trim_start).filter(|line| line.starts_with("<li>")).collect();
+        assert_eq!(items.len(), 3);
+        assert!(items[0].starts_with("<li><a href=\"#mm1\">1. PROPOSITIONAL CALCULUS</a> <small>(9 statements,"));
+        assert!(items[1].starts_with("<li><a href=\"#mm1.1\">1.1. Axioms</a>"));
+        let theorems = "<li><a href=\"#mm1.1.1\">1.1.1. Theorems</a> <small>(4 statements, \
+                        <a href=\"a1i.html\">a1i</a> &ndash; <a href=\"mp1.html\">mp1</a>)</small></li>";
+        assert_eq!(items[2], theorems);
+        // Each heading with children opens a nested list inside its item.
+        let depth: Vec<usize> = html
+            .lines()
+            .filter(|line| line.trim_start().starts_with("<ul>"))
+            .map(|line| line.len() - line.trim_start().len())
+            .collect();
+        assert_eq!(depth, [0, 4, 8]);
+        assert!(html.starts_with("<nav class=\"toc\">\n<h2>Table of Contents</h2>\n"));
+        assert!(html.ends_with("</ul>\n</nav>\n"));
+    }
+
+    #[test]
+    fn a_skipped_level_is_reported_and_the_heading_kept_under_its_part() {
+        let tree = title_tree(include_bytes!("../../fixtures/skipped-level.mm"));
+        let warning = TitleWarning::SkippedLevel {
+            ord: vec![1, 0, 1],
+            level: 3,
+            previous_level: 1,
+        };
+        assert_eq!(tree.warnings, [warning]);
+        assert_eq!(tree.warnings[0].to_string(), "1.0.1. is a level 3 heading after a level 1 heading");
+
+        let part = &tree.nodes[0];
+        let titles: Vec<(&str, usize, &[usize])> = part
+            .children
+            .iter()
+            .map(|node| (node.title.as_str(), node.level, node.ord.as_slice()))
+            .collect();
+        assert_eq!(
+            titles,
+            [("Subsection without a section", 3, &[1, 0, 1][..]), ("A section", 2, &[1, 1][..])]
+        );
+        assert_eq!(part.children[0].first_label.as_deref(), Some("ax-a"));
+        assert_eq!(part.children[0].end, part.children[1].offset);
+        assert_eq!(part.children[1].first_label.as_deref(), Some("ax-b"));
+    }
+
+    #[test]
+    fn mathboxes_are_the_headings_named_after_their_owner() {
+        let major = "#".repeat(79);
+        let section = "#*".repeat(39) + "#";
+        let heading = |rule: &str, title: &str| format!("$(\n{rule}\n  {title}\n{rule}\n$)\n");
+        let input = [
+            heading(&major, "PART ONE"),
+            "$c wff $.\n".to_string(),
+            heading(&major, "SUPPLEMENTARY MATERIAL (USER'S MATHBOXES)"),
+            heading(&section, "Guidelines for mathboxes"),
+            heading(&section, "Mathbox for Alice"),
+            heading(&section, "Mathbox for Bob"),
+        ]
+        .concat();
+        let tree = title_tree(input.as_bytes());
+        let owners: Vec<&str> = tree.mathboxes().into_iter().map(|(owner, _)| owner).collect();
+        assert_eq!(owners, ["Alice", "Bob"]);
+    }
+}
@@ -0,0 +1,73 @@
+$( Header comment $)
+
+$(
+#############################################################################
+                           PROPOSITIONAL CALCULUS
+#############################################################################
+  Intro text.
+$)
+
+$c ( ) -> -. wff |- $.
+$v ph ps ch $.
+wph $f wff ph $.
+wps $f wff ps $.
+wch $f wff ch $.
+
+$(
+#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#
+                     Axioms
+#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#*#
+$)
+
+  $( Negation is a wff. $)
+  wn $a wff -. ph $.
+  $( Implication is a wff. $)
+  wi $a wff ( ph -> ps ) $.
+
+  ${
+    min $e |- ph $.
+    maj $e |- ( ph -> ps ) $.
+    $( Rule of Modus Ponens. (Contributed by NM, 30-Sep-1992.) $)
+    ax-mp $a |- ps $.
+  $}
+
+  $( Axiom _Simp_. Peirce's friend. (Contributed by NM, 30-Sep-1992.) $)
+  ax-1 $a |- ( ph -> ( ps -> ph ) ) $.
+
+  $( Axiom _Frege_. (Contributed by NM, 30-Sep-1992.) $)
+  ax-2 $a |- ( ( ph -> ( ps -> ch ) ) -> ( ( ph -> ps ) -> ( ph -> ch ) ) ) $.
+
+$(
+=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=
+                     Theorems
+=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=-=
+$)
+
+  ${
+    a1i.1 $e |- ph $.
+    $( Inference introducing an antecedent. (Contributed by NM, 29-Dec-1992.) $)
+    a1i $p |- ( ps -> ph ) $= ( wi ax-1 ax-mp ) ABADCABEF $.
+  $}
+
+  ${
+    mp2b.1 $e |- ph $.
+    mp2b.2 $e |- ( ph -> ps ) $.
+    mp2b.3 $e |- ( ps -> ch ) $.
+    $( A double modus ponens inference. (Contributed by Mario Carneiro, 24-Jan-2013.) $)
+    mp2b $p |- ch $= ( ax-mp ) BCABDEGFG $.
+  $}
+
+  ${
+    mp2c.1 $e |- ph $.
+    mp2c.2 $e |- ( ph -> ps ) $.
+    mp2c.3 $e |- ( ps -> ch ) $.
+    $( A second copy of mp2b. $)
+    mp2c $p |- ch $= ( ax-mp ) BCABDEGFG $.
+  $}
+
+  ${
+    mp1.1 $e |- ph $.
+    mp1.2 $e |- ( ph -> ps ) $.
+    $( Modus ponens as a theorem. $)
+    mp1 $p |- ps $= ( ax-mp ) ABCDE $.
+  $}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use db_parser::statement::Statement;
+use db_parser::terminals::Label;
+use finder::title_tree::TitleTree;
+use verifier::database::{Database, DatabaseError, EntryKind};
+use verifier::incremental::ProofCache;
+
+use crate::line_index::LineIndex;
+use crate::parsed::Parse;
+
+/// A parse, database or proof error, by the bytes it is reported on.
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+/// A labeled statement, with what hovering over its label shows.
+#[derive(Debug, Clone)]
+pub struct Definition {
+    /// Bytes of the label where it is declared.
+    pub range: Range<usize>,
+    pub keyword: &'static str,
+    /// The math string, typecode first.
+    pub statement: String,
+    /// The `$e` hypotheses of an assertion, as `label $e ...` lines.
+    pub hypotheses: Vec<String>,
+    /// Bytes of the description comment of an assertion.
+    pub comment: Option<Range<usize>>,
+}
+
+/// What the server knows about one open file, computed from its parse each
+/// time it changes.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub lines: LineIndex,
+    pub problems: Vec<Problem>,
+    /// Labeled statements in file order.
+    pub labels: Vec<(String, Definition)>,
+    label_index: HashMap<String, usize>,
+    /// Where each `$c` and `$v` symbol is first declared.
+    pub symbols: HashMap<String, Range<usize>>,
+    pub headings: TitleTree,
+}
+impl Analysis {
+    /// Analyses the parse of `text`, checking again only the proofs that
+    /// `proofs` has no result for.
+    pub fn new(text: &str, parse: &Parse, proofs: &mut ProofCache) -> Self {
+        let input = text.as_bytes();
+        let mut analysis = Analysis {
+            lines: LineIndex::new(text),
+            problems: Vec::new(),
+            labels: Vec::new(),
+            label_index: HashMap::new(),
+            symbols: HashMap::new(),
+            headings: TitleTree::default(),
+        };
+
+        // What parses is still analysed when the rest of the file does not.
+        if let Some(start) = parse.stopped {
+            let end = input[start..].iter().position(u8::is_ascii_whitespace).map_or(input.len(), |n| start + n);
+            analysis.problems.push(Problem {
+                range: start..end,
+                message: "cannot parse the database from here".to_string(),
+            });
+        }
+
+        let document = &parse.document;
+        let mut declared = Vec::new();
+        for statement in document.statements() {
+            analysis.declarations(input, statement, &mut declared);
+        }
+        analysis.headings = TitleTree::from_document(document, input);
+        match Database::new(document) {
+            Ok(database) => analysis.database(input, &database, proofs),
+            Err(error) => {
+                let label = match &error {
+                    DatabaseError::DuplicateLabel(label) => label,
+                    DatabaseError::MalformedFloatingHypothesis(label) => label,
+                };
+                // A duplicate is reported where it is declared again.
+                let mut ranges = declared.iter().filter(|(name, _)| name == label).map(|(_, range)| range.clone());
+                let range = match error {
+                    DatabaseError::DuplicateLabel(_) => ranges.nth(1),
+                    DatabaseError::MalformedFloatingHypothesis(_) => ranges.next(),
+                };
+                analysis.problems.push(Problem {
+                    range: range.unwrap_or(0..0),
+                    message: error.to_string(),
+                });
+            }
+        }
+        analysis
+    }
+
+    /// Records the `$c` and `$v` symbols of a statement, and collects the
+    /// labels it declares.
+    fn declarations(&mut self, input: &[u8], statement: &Statement, labels: &mut Vec<(String, Range<usize>)>) {
+        let span = |sequence: db_parser::AsciiSequence| {
+            let start = sequence.offset_in(input);
+            start..start + sequence.as_bytes().len()
+        };
+        let label: &Label = match statement {
+            Statement::ConstantDeclaration(declaration) => {
+                for symbol in &declaration.symbol_list {
+                    self.symbols.entry(symbol.to_string()).or_insert_with(|| span(symbol.0));
+                }
+                return;
+            }
+            Statement::VariableDeclaration(declaration) => {
+                for symbol in &declaration.symbol_list {
+                    self.symbols.entry(symbol.to_string()).or_insert_with(|| span(symbol.0));
+                }
+                return;
+            }
+            Statement::FloatingHypothesis(hypothesis) => &hypothesis.label,
+            Statement::EssentialHypothesis(hypothesis) => &hypothesis.label,
+            Statement::AxiomaticAssertion(assertion) => &assertion.label,
+            Statement::ProvableAssertion(assertion) => &assertion.label,
+            Statement::Block(statements) => {
+                for statement in statements {
+                    self.declarations(input, statement, labels);
+                }
+                return;
+            }
+            _ => return,
+        };
+        labels.push((label.to_string(), span(label.0)));
+    }
+
+    fn database(&mut self, input: &[u8], database: &Database, proofs: &mut ProofCache) {
+        let span = |sequence: db_parser::AsciiSequence| {
+            let start = sequence.offset_in(input);
+            start..start + sequence.as_bytes().len()
+        };
+        let math = |index: usize| {
+            let symbols: Vec<String> = database.entries[index].symbols.iter().map(|s| s.to_string()).collect();
+            symbols.join(" ")
+        };
+        for (index, entry) in database.entries.iter().enumerate() {
+            let hypotheses = match entry.is_assertion() {
+                true => entry
+                    .frame
+                    .hypotheses
+                    .iter()
+                    .filter(|&&h| database.entries[h].kind == EntryKind::EssentialHypothesis)
+                    .map(|&h| format!("{} $e {}", database.entries[h].label, math(h)))
+                    .collect(),
+                false => Vec::new(),
+            };
+            let label = entry.label.to_string();
+            self.label_index.insert(label.clone(), self.labels.len());
+            self.labels.push((
+                label,
+                Definition {
+                    range: span(entry.label.0),
+                    keyword: entry.kind.keyword(),
+                    statement: math(index),
+                    hypotheses,
+                    comment: entry.comment.map(span),
+                },
+            ));
+        }
+        for error in proofs.verify_all(database) {
+            let range = self.definition(&error.label).map_or(0..0, |definition| definition.range.clone());
+            self.problems.push(Problem {
+                range,
+                message: error.kind.to_string(),
+            });
+        }
+    }
+
+    pub fn definition(&self, label: &str) -> Option<&Definition> {
+        self.label_index.get(label).map(|&i| &self.labels[i].1)
+    }
+
+    /// The whitespace-delimited token around `offset`, with its bytes. A
+    /// cursor just after a token is on it.
+    pub fn token_at<'t>(&self, text: &'t str, offset: usize) -> Option<(&'t str, Range<usize>)> {
+        let bytes = text.as_bytes();
+        let mut offset = offset.min(bytes.len());
+        if bytes.get(offset).is_none_or(u8::is_ascii_whitespace) && offset > 0 {
+            offset -= 1;
+        }
+        if bytes.get(offset).is_none_or(u8::is_ascii_whitespace) {
+            return None;
+        }
+        let start = bytes[..offset].iter().rposition(u8::is_ascii_whitespace).map_or(0, |i| i + 1);
+        let end = bytes[offset..].iter().position(u8::is_ascii_whitespace).map_or(bytes.len(), |i| offset + i);
+        let token = text.get(start..end)?;
+        Some((token, start..end))
+    }
+
+    /// Markdown for hovering over a label: the statement with its
+    /// hypotheses, then the description.
+    pub fn hover(&self, text: &str, label: &str) -> Option<String> {
+        let definition = self.definition(label)?;
+        let mut o = String::from("```metamath\n");
+        for hypothesis in &definition.hypotheses {
+            o.push_str(hypothesis);
+            o.push('\n');
+        }
+        o.push_str(&format!("{label} {} {}\n```", definition.keyword, definition.statement));
+        if let Some(comment) = definition.comment.clone().and_then(|range| text.get(range)) {
+            let paragraphs: Vec<String> = comment
+                .split("\n\n")
+                .map(|paragraph| paragraph.split_whitespace().collect::<Vec<_>>().join(" "))
+                .filter(|paragraph| !paragraph.is_empty())
+                .collect();
+            o.push_str("\n\n");
+            o.push_str(&paragraphs.join("\n\n"));
+        }
+        Some(o)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    fn analyse(text: &str) -> Analysis {
+        Analysis::new(text, &Parse::new(text.as_bytes()), &mut ProofCache::new())
+    }
+
+    fn problems(analysis: &Analysis, text: &str) -> Vec<(String, String)> {
+        let problem = |problem: &Problem| (text[problem.range.clone()].to_string(), problem.message.clone());
+        analysis.problems.iter().map(problem).collect()
+    }
+
+    #[test]
+    fn new_collects_labels_symbols_and_headings() {
+        let analysis = analyse(DEMO);
+        assert!(analysis.problems.is_empty());
+        let labels: Vec<&str> = analysis.labels.iter().map(|(label, _)| label.as_str()).take(4).collect();
+        assert_eq!(labels, ["wph", "wps", "wch", "wn"]);
+
+        let a1i = analysis.definition("a1i").unwrap();
+        assert_eq!(&DEMO[a1i.range.clone()], "a1i");
+        assert_eq!(a1i.range.start, DEMO.find("a1i $p").unwrap());
+        assert_eq!(a1i.keyword, "$p");
+        assert_eq!(a1i.statement, "|- ( ps -> ph )");
+        assert_eq!(a1i.hypotheses, ["a1i.1 $e |- ph"]);
+        assert!(analysis.definition("a1i.1").unwrap().hypotheses.is_empty());
+        assert!(analysis.definition("nonexistent").is_none());
+
+        assert_eq!(&DEMO[analysis.symbols["->"].clone()], "->");
+        assert_eq!(&DEMO[analysis.symbols["ph"].clone()], "ph");
+        assert_eq!(analysis.headings.nodes[0].title, "PROPOSITIONAL CALCULUS");
+    }
+
+    #[test]
+    fn new_reports_problems_where_they_occur() {
+        // A proof that does not verify is reported on its label.
+        let text = DEMO.replacen("( ax-mp ) ABCDE", "( ax-mp ) ABCD", 1);
+        let analysis = analyse(&text);
+        assert_eq!(problems(&analysis, &text), [("mp1".to_string(), "4 expressions left on the stack".to_string())]);
+
+        // A label declared again is reported where it is redeclared.
+        let text = DEMO.replacen("mp1 $p", "mp2b $p", 1);
+        let analysis = analyse(&text);
+        let redeclared = text.rfind("mp2b $p").unwrap();
+        assert_eq!(analysis.problems[0].range, redeclared..redeclared + 4);
+        assert_eq!(analysis.problems[0].message, "label mp2b is declared more than once");
+
+        // What parses before an error is still analysed.
+        let text = DEMO.replacen("mp1 $p |- ps $=", "mp1 $p |- ps $= $=", 1);
+        let analysis = analyse(&text);
+        assert!(analysis.problems.iter().any(|problem| problem.message == "cannot parse the database from here"));
+        assert!(analysis.definition("a1i").is_some());
+    }
+
+    #[test]
+    fn token_at_finds_the_token_around_the_cursor() {
+        let analysis = analyse(DEMO);
+        let start = DEMO.find("ax-mp $a").unwrap();
+        assert_eq!(analysis.token_at(DEMO, start), Some(("ax-mp", start..start + 5)));
+        assert_eq!(analysis.token_at(DEMO, start + 2), Some(("ax-mp", start..start + 5)));
+        // Just after the token, on the space.
+        assert_eq!(analysis.token_at(DEMO, start + 5), Some(("ax-mp", start..start + 5)));
+        // A cursor after a space is on no token.
+        let text = "a  b";
+        assert_eq!(analysis.token_at(text, 2), None);
+        assert_eq!(analysis.token_at(text, 1), Some(("a", 0..1)));
+        assert_eq!(analysis.token_at(text, 0), Some(("a", 0..1)));
+        assert_eq!(analysis.token_at(text, 10), Some(("b", 3..4)));
+        assert_eq!(analysis.token_at("", 0), None);
+    }
+
+    #[test]
+    fn hover_shows_the_statement_with_its_hypotheses_and_description() {
+        let analysis = analyse(DEMO);
+        let expected = "```metamath\na1i.1 $e |- ph\na1i $p |- ( ps -> ph )\n```\n\n\
+                        Inference introducing an antecedent. (Contributed by NM, 29-Dec-1992.)";
+        assert_eq!(analysis.hover(DEMO, "a1i").unwrap(), expected);
+        assert_eq!(analysis.hover(DEMO, "wph").unwrap(), "```metamath\nwph $f wff ph\n```");
+        assert!(analysis.hover(DEMO, "nonexistent").is_none());
+    }
+}
@@ -0,0 +1,43 @@
+use lsp_types::{Position, Range};
+
+/// Converts between byte offsets and the line and UTF-16 column positions
+/// the protocol uses.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    /// Byte offset of the start of every line.
+    starts: Vec<usize>,
+}
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { starts }
+    }
+
+    pub fn position(&self, text: &str, offset: usize) -> Position {
+        let offset = offset.min(text.len());
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.starts[line];
+        let character = text.get(start..offset).map_or(offset - start, |s| s.encode_utf16().count());
+        Position::new(line as u32, character as u32)
+    }
+
+    pub fn range(&self, text: &str, range: std::ops::Range<usize>) -> Range {
+        Range::new(self.position(text, range.start), self.position(text, range.end))
+    }
+
+    pub fn offset(&self, text: &str, position: Position) -> usize {
+        let Some(&start) = self.starts.get(position.line as usize) else {
+            return text.len();
+        };
+        let mut units = 0;
+        for (i, c) in text[start..].char_indices() {
+            if units >= position.character as usize || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        text.len()
+    }
+}
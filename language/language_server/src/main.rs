@@ -0,0 +1,331 @@
+//! A language server for Metamath databases over stdio: diagnostics from
+//! parsing and verification, go-to-definition for labels and math symbols,
+//! hover, document symbols from the headings, and workspace symbols by label.
+
+mod analysis;
+mod line_index;
+mod parsed;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use db_parser::incremental::TextEdit;
+use finder::title_tree::TitleNode;
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _, WorkspaceSymbolRequest};
+use verifier::incremental::ProofCache;
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability, Location, MarkupContent,
+    MarkupKind, OneOf, PublishDiagnosticsParams, ServerCapabilities, SymbolInformation, SymbolKind,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Uri, WorkspaceSymbolParams, WorkspaceSymbolResponse,
+};
+
+use analysis::Analysis;
+use line_index::LineIndex;
+use parsed::Parsed;
+
+/// The nom parsers recurse once per nested heading and block, which does not
+/// fit in the default main thread stack for set.mm.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+/// Most workspace symbols returned for one query.
+const WORKSPACE_SYMBOL_LIMIT: usize = 200;
+
+type ServerResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+fn main() -> ServerResult<()> {
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(serve)?
+        .join()
+        .map_err(|_| "the server thread panicked")?
+}
+
+fn serve() -> ServerResult<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::INCREMENTAL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        workspace_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+    Server {
+        connection: &connection,
+        documents: HashMap::new(),
+    }
+    .run()?;
+    // The writer thread stops once every sender is gone.
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+struct Server<'c> {
+    connection: &'c Connection,
+    documents: HashMap<Uri, OpenFile>,
+}
+
+/// An open file: its text and parse, what the server knows about it, and the
+/// results of its proofs to reuse after the next edit.
+struct OpenFile {
+    parsed: Parsed,
+    analysis: Analysis,
+    proofs: ProofCache,
+}
+impl OpenFile {
+    fn text(&self) -> &str {
+        self.parsed.text()
+    }
+}
+impl Server<'_> {
+    fn run(&mut self) -> ServerResult<()> {
+        for message in &self.connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.request(request)?;
+                }
+                Message::Notification(notification) => self.notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn request(&self, request: Request) -> ServerResult<()> {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => serde_json::to_value(self.definition(serde_json::from_value(request.params)?))?,
+            HoverRequest::METHOD => serde_json::to_value(self.hover(serde_json::from_value(request.params)?))?,
+            DocumentSymbolRequest::METHOD => {
+                serde_json::to_value(self.document_symbols(serde_json::from_value(request.params)?))?
+            }
+            WorkspaceSymbolRequest::METHOD => {
+                serde_json::to_value(self.workspace_symbols(serde_json::from_value(request.params)?))?
+            }
+            _ => return self.respond(Response::new_err(id, -32601, format!("unsupported request {}", request.method))),
+        };
+        self.respond(Response::new_ok(id, result))
+    }
+
+    fn respond(&self, response: Response) -> ServerResult<()> {
+        self.connection.sender.send(Message::Response(response))?;
+        Ok(())
+    }
+
+    fn notification(&mut self, notification: Notification) -> ServerResult<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams = serde_json::from_value(notification.params)?;
+                self.update(params.text_document.uri, Parsed::parse(params.text_document.text))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams = serde_json::from_value(notification.params)?;
+                let Some(file) = self.documents.get(&params.text_document.uri) else {
+                    return Ok(());
+                };
+                // Each change applies to the text the previous one left.
+                let mut parsed: Option<Parsed> = None;
+                for change in params.content_changes {
+                    let current = parsed.as_ref().unwrap_or(&file.parsed);
+                    let next = match change.range {
+                        Some(range) => {
+                            let lines = match parsed {
+                                Some(_) => &LineIndex::new(current.text()),
+                                None => &file.analysis.lines,
+                            };
+                            let start = lines.offset(current.text(), range.start);
+                            let end = lines.offset(current.text(), range.end);
+                            current.edit(&TextEdit {
+                                range: start..end.max(start),
+                                text: change.text.into_bytes(),
+                            })
+                        }
+                        None => Parsed::parse(change.text),
+                    };
+                    parsed = Some(next);
+                }
+                match parsed {
+                    Some(parsed) => self.update(params.text_document.uri, parsed),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams = serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                self.publish(params.text_document.uri, Vec::new())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Analyses the new text of a document and publishes its diagnostics.
+    fn update(&mut self, uri: Uri, parsed: Parsed) -> ServerResult<()> {
+        let mut proofs = self.documents.remove(&uri).map(|file| file.proofs).unwrap_or_default();
+        let analysis = Analysis::new(parsed.text(), parsed.borrow_dependent(), &mut proofs);
+        let diagnostics = analysis
+            .problems
+            .iter()
+            .map(|problem| Diagnostic {
+                range: analysis.lines.range(parsed.text(), problem.range.clone()),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("metamath".to_string()),
+                message: problem.message.clone(),
+                ..Diagnostic::default()
+            })
+            .collect();
+        self.documents.insert(
+            uri.clone(),
+            OpenFile {
+                parsed,
+                analysis,
+                proofs,
+            },
+        );
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&self, uri: Uri, diagnostics: Vec<Diagnostic>) -> ServerResult<()> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection.sender.send(Message::Notification(notification))?;
+        Ok(())
+    }
+
+    /// The declaration of the label or math symbol under the cursor.
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+        let file = self.documents.get(&uri)?;
+        let (analysis, text) = (&file.analysis, file.text());
+        let (token, _) = analysis.token_at(text, analysis.lines.offset(text, position.position))?;
+        let range = match analysis.definition(token) {
+            Some(definition) => definition.range.clone(),
+            None => analysis.symbols.get(token)?.clone(),
+        };
+        Some(GotoDefinitionResponse::Scalar(Location::new(uri, analysis.lines.range(text, range))))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params;
+        let file = self.documents.get(&position.text_document.uri)?;
+        let (analysis, text) = (&file.analysis, file.text());
+        let (token, range) = analysis.token_at(text, analysis.lines.offset(text, position.position))?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: analysis.hover(text, token)?,
+            }),
+            range: Some(analysis.lines.range(text, range)),
+        })
+    }
+
+    /// The headings of a document, each with its subheadings and the
+    /// assertions directly under it.
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let file = self.documents.get(&params.text_document.uri)?;
+        let mut assertions = file
+            .analysis
+            .labels
+            .iter()
+            .filter(|(_, definition)| matches!(definition.keyword, "$a" | "$p"))
+            .peekable();
+        let mut symbols = Vec::new();
+        for node in &file.analysis.headings.nodes {
+            while let Some(assertion) = assertions.next_if(|(_, definition)| definition.range.start < node.offset) {
+                symbols.push(assertion_symbol(file, assertion));
+            }
+            symbols.push(heading_symbol(file, node, &mut assertions));
+        }
+        symbols.extend(assertions.map(|assertion| assertion_symbol(file, assertion)));
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    /// Labels in every open document containing the query, ignoring case.
+    fn workspace_symbols(&self, params: WorkspaceSymbolParams) -> WorkspaceSymbolResponse {
+        let query = params.query.to_lowercase();
+        let mut symbols = Vec::new();
+        for (uri, file) in &self.documents {
+            for (label, definition) in &file.analysis.labels {
+                if symbols.len() == WORKSPACE_SYMBOL_LIMIT {
+                    break;
+                }
+                if !label.to_lowercase().contains(&query) {
+                    continue;
+                }
+                #[allow(deprecated)]
+                symbols.push(SymbolInformation {
+                    name: label.clone(),
+                    kind: symbol_kind(definition.keyword),
+                    tags: None,
+                    deprecated: None,
+                    location: Location::new(uri.clone(), file.analysis.lines.range(file.text(), definition.range.clone())),
+                    container_name: None,
+                });
+            }
+        }
+        WorkspaceSymbolResponse::Flat(symbols)
+    }
+}
+
+fn symbol_kind(keyword: &str) -> SymbolKind {
+    match keyword {
+        "$p" => SymbolKind::FUNCTION,
+        "$a" => SymbolKind::CONSTANT,
+        _ => SymbolKind::VARIABLE,
+    }
+}
+
+fn assertion_symbol(file: &OpenFile, (label, definition): &(String, analysis::Definition)) -> DocumentSymbol {
+    let range = file.analysis.lines.range(file.text(), definition.range.clone());
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name: label.clone(),
+        detail: Some(format!("{} {}", definition.keyword, definition.statement)),
+        kind: symbol_kind(definition.keyword),
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range: range,
+        children: None,
+    }
+}
+
+fn heading_symbol<'a>(
+    file: &OpenFile,
+    node: &TitleNode,
+    assertions: &mut std::iter::Peekable<impl Iterator<Item = &'a (String, analysis::Definition)>>,
+) -> DocumentSymbol {
+    let mut children = Vec::new();
+    for child in &node.children {
+        while let Some(assertion) = assertions.next_if(|(_, definition)| definition.range.start < child.offset) {
+            children.push(assertion_symbol(file, assertion));
+        }
+        children.push(heading_symbol(file, child, assertions));
+    }
+    while let Some(assertion) = assertions.next_if(|(_, definition)| definition.range.start < node.end) {
+        children.push(assertion_symbol(file, assertion));
+    }
+    let range = file.analysis.lines.range(file.text(), node.offset..node.end);
+    let title_end = file.text()[node.offset..node.end].find('\n').map_or(node.end, |i| node.offset + i);
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name: format!("{} {}", node.ord_string(), node.title),
+        detail: None,
+        kind: SymbolKind::NAMESPACE,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range: file.analysis.lines.range(file.text(), node.offset..title_end),
+        children: Some(children),
+    }
+}
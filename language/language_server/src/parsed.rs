@@ -0,0 +1,61 @@
+use db_parser::document::Document;
+use db_parser::incremental::{reparse, TextEdit};
+use self_cell::self_cell;
+
+/// A parsed database and where parsing stopped short of its end, if it did.
+pub struct Parse<'a> {
+    pub document: Document<'a>,
+    pub stopped: Option<usize>,
+}
+impl<'a> Parse<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        match db_parser::document::document(input) {
+            Ok((rest, document)) => Self {
+                document,
+                stopped: rest
+                    .iter()
+                    .position(|c| !c.is_ascii_whitespace())
+                    .map(|position| input.len() - rest.len() + position),
+            },
+            Err(_) => Self {
+                document: Document::new((Vec::new(), Vec::new())),
+                stopped: Some(0),
+            },
+        }
+    }
+}
+
+self_cell!(
+    /// The text of an open file together with its parse, which borrows it.
+    pub struct Parsed {
+        owner: String,
+        #[covariant]
+        dependent: Parse,
+    }
+);
+impl Parsed {
+    pub fn parse(text: String) -> Self {
+        Self::new(text, |text| Parse::new(text.as_bytes()))
+    }
+
+    pub fn text(&self) -> &str {
+        self.borrow_owner()
+    }
+
+    /// The text after `edit`, parsed again only where the edit requires.
+    pub fn edit(&self, edit: &TextEdit) -> Self {
+        let text = String::from_utf8_lossy(&edit.apply(self.text().as_bytes())).into_owned();
+        let old = self.borrow_dependent();
+        Self::new(text, |text| {
+            let input = text.as_bytes();
+            match reparse(&old.document, self.text().as_bytes(), input, edit) {
+                // The edit lies before where parsing stopped, which moves with it.
+                Some(document) => Parse {
+                    document,
+                    stopped: old.stopped.map(|stopped| stopped + edit.text.len() - edit.range.len()),
+                },
+                None => Parse::new(input),
+            }
+        })
+    }
+}
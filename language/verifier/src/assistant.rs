@@ -0,0 +1,866 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::rc::Rc;
+
+use serde::Serialize;
+
+use crate::compress::{compress, EncodedProof};
+use crate::database::{Database, EntryKind};
+use crate::discouraged::{has_tag, NEW_USAGE};
+use crate::grammar::{Grammar, SyntaxTree};
+use crate::proof::{decode, ProofStep};
+use crate::unify::{Term, Unifier};
+use crate::verify::{Stack, VerifyError, VerifyErrorKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssistantError {
+    /// No step has this number.
+    UnknownStep(usize),
+    /// The step is proved already.
+    StepAssigned(usize),
+    UnknownLabel(String),
+    /// The entry cannot be used in the proof: a later assertion or a
+    /// hypothesis out of scope.
+    NotUsable(String),
+    /// `$f` steps are not shown; they are filled in when the proof is saved.
+    FloatingHypothesis(String),
+    /// The grammar does not parse the statement of this label, or this
+    /// expression.
+    Unparsable(String),
+    /// The statement of the label does not unify with the step.
+    Mismatch(String),
+    UnknownWorkVariable(String),
+    /// Number of unknown steps left.
+    Incomplete(usize),
+    /// A work variable is still not replaced by an expression.
+    WorkVariable(String),
+    /// No `$f` hypothesis of the theorem gives the type of this variable.
+    MissingFloating(String),
+    Verify(VerifyError),
+}
+impl Display for AssistantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownStep(step) => write!(f, "there is no step {step}"),
+            Self::StepAssigned(step) => write!(f, "step {step} is proved already"),
+            Self::UnknownLabel(label) => write!(f, "unknown label {label}"),
+            Self::NotUsable(label) => write!(f, "{label} cannot be used in this proof"),
+            Self::FloatingHypothesis(label) => {
+                write!(f, "{label} is a $f hypothesis, filled in on save")
+            }
+            Self::Unparsable(what) => write!(f, "cannot parse {what}"),
+            Self::Mismatch(label) => write!(f, "{label} does not unify with the step"),
+            Self::UnknownWorkVariable(name) => {
+                write!(f, "{name} is not an unassigned work variable")
+            }
+            Self::Incomplete(1) => write!(f, "a step is still unknown"),
+            Self::Incomplete(count) => write!(f, "{count} steps are still unknown"),
+            Self::WorkVariable(name) => write!(f, "work variable {name} is still unassigned"),
+            Self::MissingFloating(name) => write!(f, "no $f hypothesis for {name} is in scope"),
+            Self::Verify(error) => write!(f, "{error}"),
+        }
+    }
+}
+impl std::error::Error for AssistantError {}
+
+/// A step of the proof as the user sees it: an essential step, numbered in
+/// the order the proof proves it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShownStep {
+    pub number: usize,
+    /// Depth in the proof tree, the conclusion being 0.
+    pub depth: usize,
+    /// The entry proving the step, `None` while it is unknown.
+    pub label: Option<String>,
+    /// The statement, typecode first, with unassigned work variables as
+    /// `$1`, `$2`, ...
+    pub formula: Vec<String>,
+}
+
+/// A step `improve_all` proved.
+#[derive(Debug, Clone, Serialize)]
+pub struct Improvement {
+    /// The statement of the step, typecode first.
+    pub formula: Vec<String>,
+    /// The entry applied to it.
+    pub label: String,
+}
+
+/// An entry applied to a step.
+#[derive(Debug, Clone)]
+struct Assigned {
+    entry: usize,
+    /// What each mandatory hypothesis of the entry stands for: for a `$f`
+    /// the term substituted for its variable, for a `$e` the step proving it.
+    hypotheses: Vec<Hypothesis>,
+}
+
+#[derive(Debug, Clone)]
+enum Hypothesis {
+    Floating(Term),
+    Essential(usize),
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    typecode: String,
+    target: Term,
+    assigned: Option<Assigned>,
+}
+
+#[derive(Debug, Clone)]
+struct State<'d> {
+    unifier: Unifier<'d>,
+    /// The variables of the theorem, which unification never binds.
+    fixed: HashMap<String, usize>,
+    /// Steps by id, the conclusion first. Steps are never removed.
+    steps: Vec<Step>,
+    /// Number of work variables made so far.
+    work: usize,
+}
+
+/// A proof built backwards from the conclusion of a `$p` statement, in the
+/// style of metamath's proof assistant.
+///
+/// The user assigns an assertion to an unknown step; its conclusion is
+/// unified with the step and its `$e` hypotheses become new steps. Variables
+/// the unification does not determine become work variables, which later
+/// assignments or `let` fill in. Syntax steps are never shown: they are
+/// built from the grammar when the proof is saved.
+pub struct Assistant<'d> {
+    database: &'d Database<'d>,
+    grammar: &'d Grammar,
+    theorem: usize,
+    state: State<'d>,
+    history: Vec<State<'d>>,
+    /// Parsed statements by entry.
+    trees: HashMap<usize, Option<Rc<SyntaxTree>>>,
+    candidates: Option<Rc<Vec<usize>>>,
+}
+impl<'d> Assistant<'d> {
+    /// Starts from the proof of the `$p` entry at `theorem`, which may be
+    /// `?` or contain `?` steps.
+    pub fn new(database: &'d Database<'d>, grammar: &'d Grammar, theorem: usize) -> Result<Self, AssistantError> {
+        let label = database.entries[theorem].label.to_string();
+        let mut assistant = Assistant {
+            database,
+            grammar,
+            theorem,
+            state: State {
+                unifier: Unifier::new(database),
+                fixed: HashMap::new(),
+                steps: Vec::new(),
+                work: 0,
+            },
+            history: Vec::new(),
+            trees: HashMap::new(),
+            candidates: None,
+        };
+        let tree = assistant
+            .tree(theorem)
+            .ok_or_else(|| AssistantError::Unparsable(label.clone()))?;
+        let target = assistant.fixed_term(&tree);
+        assistant.state.steps.push(Step {
+            typecode: database.entries[theorem].typecode().to_string(),
+            target,
+            assigned: None,
+        });
+
+        let verify = |kind| {
+            AssistantError::Verify(VerifyError {
+                label: label.clone(),
+                kind,
+            })
+        };
+        let mut stack: Vec<Loaded> = Vec::new();
+        let mut saved = Vec::new();
+        for step in decode(database, theorem).map_err(AssistantError::Verify)? {
+            match step {
+                ProofStep::Entry(index) => {
+                    let entry = &database.entries[index];
+                    let arity = if entry.is_hypothesis() { 0 } else { entry.frame.hypotheses.len() };
+                    let base = stack
+                        .len()
+                        .checked_sub(arity)
+                        .ok_or_else(|| verify(VerifyErrorKind::StackUnderflow(entry.label.to_string())))?;
+                    let hypotheses = stack.split_off(base);
+                    stack.push(Loaded::Entry(index, hypotheses));
+                }
+                ProofStep::Saved(n) => stack.push(
+                    saved
+                        .get(n)
+                        .cloned()
+                        .ok_or_else(|| verify(VerifyErrorKind::UnknownSavedStep(n)))?,
+                ),
+                ProofStep::Save => saved.push(
+                    stack
+                        .last()
+                        .cloned()
+                        .ok_or_else(|| verify(VerifyErrorKind::NothingToSave))?,
+                ),
+                ProofStep::Unknown => stack.push(Loaded::Unknown),
+            }
+        }
+        let [root] = &stack[..] else {
+            return Err(verify(VerifyErrorKind::StackNotSingleton(stack.len())));
+        };
+        assistant.replay(0, root)?;
+        Ok(assistant)
+    }
+
+    /// Assigns again what a loaded proof applies at the step `id`.
+    fn replay(&mut self, id: usize, loaded: &Loaded) -> Result<(), AssistantError> {
+        let Loaded::Entry(index, loaded_hypotheses) = loaded else {
+            return Ok(());
+        };
+        self.apply(id, *index)?;
+        let hypotheses = self.state.steps[id]
+            .assigned
+            .as_ref()
+            .map_or(Vec::new(), |a| a.hypotheses.clone());
+        for (hypothesis, loaded) in hypotheses.iter().zip(loaded_hypotheses) {
+            match hypothesis {
+                Hypothesis::Essential(step) => self.replay(*step, loaded)?,
+                // A syntax proof gives what a variable stands for, which
+                // unification alone may leave open.
+                Hypothesis::Floating(term) => {
+                    if let Some(tree) = syntax_tree(self.database, loaded) {
+                        let proved = self.fixed_term(&tree);
+                        if !self.state.unifier.unify(term, &proved) {
+                            let label = self.database.entries[*index].label.to_string();
+                            return Err(AssistantError::Mismatch(label));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn theorem(&self) -> usize {
+        self.theorem
+    }
+
+    /// The essential steps in proof order: hypotheses before the steps that
+    /// use them, the conclusion last.
+    pub fn steps(&self) -> Vec<ShownStep> {
+        self.order()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (id, depth))| {
+                let step = &self.state.steps[id];
+                ShownStep {
+                    number: i + 1,
+                    depth,
+                    label: step
+                        .assigned
+                        .as_ref()
+                        .map(|a| self.database.entries[a.entry].label.to_string()),
+                    formula: self.formula(step),
+                }
+            })
+            .collect()
+    }
+
+    /// The steps still to prove.
+    pub fn unknown(&self) -> Vec<ShownStep> {
+        self.steps().into_iter().filter(|step| step.label.is_none()).collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.unknown().is_empty()
+    }
+
+    /// Proves the step numbered `number` with the entry `label`, unifying the
+    /// statement of the entry with the step.
+    pub fn assign(&mut self, number: usize, label: &str) -> Result<(), AssistantError> {
+        let id = self.unknown_step(number)?;
+        let index = self
+            .database
+            .index_of(label)
+            .ok_or_else(|| AssistantError::UnknownLabel(label.to_string()))?;
+        self.attempt(|assistant| assistant.apply(id, index))
+    }
+
+    /// Replaces the work variable `name`, such as `$2`, with an expression.
+    pub fn let_variable(&mut self, name: &str, expression: &[String]) -> Result<(), AssistantError> {
+        let unifier = &self.state.unifier;
+        let variable = (0..unifier.names.len())
+            .find(|&v| !unifier.is_fixed(v) && unifier.names[v] == name && unifier.bindings[v].is_none())
+            .ok_or_else(|| AssistantError::UnknownWorkVariable(name.to_string()))?;
+        let tree = unifier
+            .type_of(&Term::Variable(variable))
+            .and_then(|typecode| self.grammar.parse(&typecode, expression))
+            .ok_or_else(|| AssistantError::Unparsable(expression.join(" ")))?;
+        self.attempt(|assistant| {
+            let term = assistant.fixed_term(&tree);
+            match assistant.state.unifier.unify(&Term::Variable(variable), &term) {
+                true => Ok(()),
+                false => Err(AssistantError::Mismatch(expression.join(" "))),
+            }
+        })
+    }
+
+    /// Tries to prove the step numbered `number` by a bounded search, as
+    /// metamath's `improve` does, and returns the label it was proved with.
+    ///
+    /// A step is closed by a hypothesis of the theorem, by a step of the
+    /// proof that is already proved, or, while `depth` allows, by an
+    /// assertion whose `$e` hypotheses are closed the same way with one less
+    /// depth. The search must not change the work variables of the step nor
+    /// leave new ones. Assertions tagged as discouraged for new use are
+    /// skipped.
+    pub fn improve(&mut self, number: usize, depth: usize) -> Result<Option<String>, AssistantError> {
+        let id = self.unknown_step(number)?;
+        let before = self.state.clone();
+        if !self.close(id, depth) {
+            return Ok(None);
+        }
+        self.history.push(before);
+        Ok(self.state.steps[id]
+            .assigned
+            .as_ref()
+            .map(|a| self.database.entries[a.entry].label.to_string()))
+    }
+
+    /// Tries `improve` on every unknown step, again while that proves more
+    /// of them, and returns what was proved. All of it is one change for
+    /// `undo`.
+    pub fn improve_all(&mut self, depth: usize) -> Vec<Improvement> {
+        let before = self.state.clone();
+        let mut improvements = Vec::new();
+        loop {
+            let unknown: Vec<usize> = self
+                .order()
+                .into_iter()
+                .map(|(id, _)| id)
+                .filter(|&id| self.state.steps[id].assigned.is_none())
+                .collect();
+            let proved = improvements.len();
+            for id in unknown {
+                let formula = self.formula(&self.state.steps[id]);
+                if self.close(id, depth) {
+                    let entry = self.state.steps[id].assigned.as_ref().map_or(0, |a| a.entry);
+                    improvements.push(Improvement {
+                        label: self.database.entries[entry].label.to_string(),
+                        formula,
+                    });
+                }
+            }
+            if improvements.len() == proved {
+                break;
+            }
+        }
+        if !improvements.is_empty() {
+            self.history.push(before);
+        }
+        improvements
+    }
+
+    /// Goes back to the state before the last change. Returns whether there
+    /// was one.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(state) => {
+                self.state = state;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The finished proof, checked and compressed.
+    pub fn proof(&self) -> Result<EncodedProof<'d>, AssistantError> {
+        let unknown = self.unknown().len();
+        if unknown > 0 {
+            return Err(AssistantError::Incomplete(unknown));
+        }
+        let mut steps = Vec::new();
+        self.proof_steps(0, &mut steps)?;
+
+        let entry = &self.database.entries[self.theorem];
+        let error = |kind| {
+            AssistantError::Verify(VerifyError {
+                label: entry.label.to_string(),
+                kind,
+            })
+        };
+        let mut stack = Stack::new(self.database, self.theorem);
+        for &step in &steps {
+            stack.step(step).map_err(error)?;
+        }
+        if stack.expressions.as_slice() != [entry.symbols.clone()] {
+            return Err(error(VerifyErrorKind::ConclusionMismatch));
+        }
+        compress(self.database, self.theorem, &steps).map_err(AssistantError::Verify)
+    }
+
+    /// Proves the unknown step `id` by `search`, or leaves the state as it
+    /// was.
+    fn close(&mut self, id: usize, depth: usize) -> bool {
+        let open = self.work_variables(&self.state.steps[id].target.clone());
+        let before = self.state.clone();
+        if self.search(id, depth, &open) && self.unchanged(id, &open) {
+            return true;
+        }
+        self.state = before;
+        false
+    }
+
+    fn search(&mut self, id: usize, depth: usize, open: &[usize]) -> bool {
+        let untouched = |assistant: &Self| open.iter().all(|&v| assistant.state.unifier.bindings[v].is_none());
+        for (hypothesis, proved) in self.available() {
+            let before = self.state.clone();
+            let closed = match hypothesis {
+                true => self.apply(id, proved).is_ok(),
+                false => self.reuse(id, proved),
+            };
+            if closed && untouched(self) {
+                return true;
+            }
+            self.state = before;
+        }
+        // Every assertion unifies with a bare work variable; only what is
+        // available can be tried for it.
+        if depth == 0 || self.is_bare(id) {
+            return false;
+        }
+        for &index in self.candidates().iter() {
+            if !self.may_unify(id, index) {
+                continue;
+            }
+            let before = self.state.clone();
+            if self.apply(id, index).is_ok() && untouched(self) && self.search_all(self.new_steps(id), depth - 1, open)
+            {
+                return true;
+            }
+            self.state = before;
+        }
+        false
+    }
+
+    /// Searches for proofs of all the steps `pending`. Steps that are a bare
+    /// work variable wait until proving the others has bound it.
+    fn search_all(&mut self, mut pending: Vec<usize>, depth: usize, open: &[usize]) -> bool {
+        while !pending.is_empty() {
+            let next = pending.iter().position(|&step| !self.is_bare(step)).unwrap_or(0);
+            let step = pending.remove(next);
+            if !self.search(step, depth, open) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Whether the statement of step `id` is an unbound work variable. A
+    /// variable of the theorem is fixed and can be searched for like any
+    /// other statement.
+    fn is_bare(&self, id: usize) -> bool {
+        let target = self.state.unifier.walk(&self.state.steps[id].target);
+        matches!(target, Term::Variable(v) if !self.state.unifier.is_fixed(v))
+    }
+
+    /// What can close a step without search: the `$e` hypotheses of the
+    /// theorem, as `(true, entry)`, and the proved steps of the proof, as
+    /// `(false, id)`.
+    fn available(&self) -> Vec<(bool, usize)> {
+        let hypotheses = self.essential_hypotheses().into_iter().map(|h| (true, h));
+        let proved = self
+            .order()
+            .into_iter()
+            .filter(|&(id, _)| self.state.steps[id].assigned.is_some() && self.is_proved(id))
+            .map(|(id, _)| (false, id));
+        hypotheses.chain(proved).collect()
+    }
+
+    fn is_proved(&self, id: usize) -> bool {
+        match &self.state.steps[id].assigned {
+            Some(_) => self.new_steps(id).into_iter().all(|step| self.is_proved(step)),
+            None => false,
+        }
+    }
+
+    /// Proves the unknown step `id` with a copy of the proof of the step
+    /// `proved`, if their statements unify.
+    fn reuse(&mut self, id: usize, proved: usize) -> bool {
+        let (from, to) = (self.state.steps[proved].clone(), self.state.steps[id].clone());
+        if from.typecode != to.typecode || !self.state.unifier.unify(&to.target, &from.target) {
+            return false;
+        }
+        let assigned = self.copy(proved);
+        self.state.steps[id].assigned = assigned;
+        true
+    }
+
+    fn copy(&mut self, id: usize) -> Option<Assigned> {
+        let mut assigned = self.state.steps[id].assigned.clone()?;
+        for hypothesis in &mut assigned.hypotheses {
+            if let Hypothesis::Essential(step) = hypothesis {
+                let copied = Step {
+                    assigned: self.copy(*step),
+                    ..self.state.steps[*step].clone()
+                };
+                *step = self.state.steps.len();
+                self.state.steps.push(copied);
+            }
+        }
+        Some(assigned)
+    }
+
+    /// The assertions `improve` tries, in database order.
+    fn candidates(&mut self) -> Rc<Vec<usize>> {
+        let (database, theorem) = (self.database, self.theorem);
+        self.candidates
+            .get_or_insert_with(|| {
+                let assertions = database
+                    .assertions()
+                    .map(|(index, _)| index)
+                    .take_while(|&index| index < theorem);
+                Rc::new(
+                    assertions
+                        .filter(|&index| !has_tag(database, index, NEW_USAGE))
+                        .collect(),
+                )
+            })
+            .clone()
+    }
+
+    /// Whether the conclusion of the assertion at `index` can unify with the
+    /// step `id`, judging by typecodes and the outermost syntax axiom.
+    fn may_unify(&mut self, id: usize, index: usize) -> bool {
+        let step = &self.state.steps[id];
+        if self.database.entries[index].typecode().0.as_bytes() != step.typecode.as_bytes() {
+            return false;
+        }
+        let head = match self.state.unifier.walk(&step.target) {
+            Term::Node(axiom, _) => Some(axiom),
+            Term::Variable(_) => None,
+        };
+        match (head, self.tree(index).as_deref()) {
+            (_, None) => false,
+            (Some(head), Some(SyntaxTree::Node(axiom, _))) => head == *axiom,
+            _ => true,
+        }
+    }
+
+    /// Runs `change` on the state, keeping it for `undo` if it succeeds and
+    /// dropping what it did otherwise.
+    fn attempt(&mut self, change: impl FnOnce(&mut Self) -> Result<(), AssistantError>) -> Result<(), AssistantError> {
+        let before = self.state.clone();
+        match change(self) {
+            Ok(()) => {
+                self.history.push(before);
+                Ok(())
+            }
+            Err(error) => {
+                self.state = before;
+                Err(error)
+            }
+        }
+    }
+
+    /// Applies the entry at `index` to the unknown step `id`.
+    fn apply(&mut self, id: usize, index: usize) -> Result<(), AssistantError> {
+        let entry = &self.database.entries[index];
+        let label = entry.label.to_string();
+        match entry.kind {
+            EntryKind::FloatingHypothesis => return Err(AssistantError::FloatingHypothesis(label)),
+            EntryKind::EssentialHypothesis if index > self.theorem || entry.scope_end <= self.theorem => {
+                return Err(AssistantError::NotUsable(label));
+            }
+            _ if entry.is_assertion() && index >= self.theorem => return Err(AssistantError::NotUsable(label)),
+            _ => {}
+        }
+        if entry.typecode().to_string() != self.state.steps[id].typecode {
+            return Err(AssistantError::Mismatch(label));
+        }
+        let tree = self
+            .tree(index)
+            .ok_or_else(|| AssistantError::Unparsable(label.clone()))?;
+
+        if entry.is_hypothesis() {
+            let term = self.fixed_term(&tree);
+            if !self.state.unifier.unify(&self.state.steps[id].target.clone(), &term) {
+                return Err(AssistantError::Mismatch(label));
+            }
+            self.state.steps[id].assigned = Some(Assigned {
+                entry: index,
+                hypotheses: Vec::new(),
+            });
+            return Ok(());
+        }
+
+        // Every variable of the assertion starts as a new work variable.
+        let mut ids = HashMap::new();
+        for &hypothesis in &entry.frame.hypotheses {
+            let floating = &self.database.entries[hypothesis];
+            if floating.kind == EntryKind::FloatingHypothesis {
+                self.state.work += 1;
+                let name = format!("${}", self.state.work);
+                let variable = self
+                    .state
+                    .unifier
+                    .variable(name, Some(floating.symbols[0].to_string()), false);
+                ids.insert(floating.symbols[1].to_string(), variable);
+            }
+        }
+        let conclusion = self.state.unifier.term(self.grammar, &tree, &mut ids);
+        if !self
+            .state
+            .unifier
+            .unify(&self.state.steps[id].target.clone(), &conclusion)
+        {
+            return Err(AssistantError::Mismatch(label));
+        }
+        let mut hypotheses = Vec::new();
+        for &hypothesis in &entry.frame.hypotheses {
+            let used = &self.database.entries[hypothesis];
+            if used.kind == EntryKind::FloatingHypothesis {
+                hypotheses.push(Hypothesis::Floating(Term::Variable(ids[&used.symbols[1].to_string()])));
+                continue;
+            }
+            let tree = self
+                .tree(hypothesis)
+                .ok_or_else(|| AssistantError::Unparsable(used.label.to_string()))?;
+            let target = self.state.unifier.term(self.grammar, &tree, &mut ids);
+            hypotheses.push(Hypothesis::Essential(self.state.steps.len()));
+            self.state.steps.push(Step {
+                typecode: used.typecode().to_string(),
+                target,
+                assigned: None,
+            });
+        }
+        self.state.steps[id].assigned = Some(Assigned {
+            entry: index,
+            hypotheses,
+        });
+        Ok(())
+    }
+
+    /// Appends the steps proving the step `id`, syntax steps included.
+    fn proof_steps(&self, id: usize, steps: &mut Vec<ProofStep>) -> Result<(), AssistantError> {
+        let assigned = self.state.steps[id]
+            .assigned
+            .as_ref()
+            .ok_or(AssistantError::Incomplete(1))?;
+        for hypothesis in &assigned.hypotheses {
+            match hypothesis {
+                Hypothesis::Essential(step) => self.proof_steps(*step, steps)?,
+                Hypothesis::Floating(term) => {
+                    let tree = self.state.unifier.resolve(term);
+                    tree.proof(self.database, self.theorem, steps).map_err(|name| match name.starts_with('$') {
+                        true => AssistantError::WorkVariable(name),
+                        false => AssistantError::MissingFloating(name),
+                    })?
+                }
+            }
+        }
+        steps.push(ProofStep::Entry(assigned.entry));
+        Ok(())
+    }
+
+    /// The parsed statement of the entry at `index`.
+    fn tree(&mut self, index: usize) -> Option<Rc<SyntaxTree>> {
+        let (database, grammar) = (self.database, self.grammar);
+        self.trees
+            .entry(index)
+            .or_insert_with(|| {
+                let symbols: Vec<String> = database.entries[index].symbols.iter().map(|s| s.to_string()).collect();
+                grammar.parse_statement(&symbols).map(Rc::new)
+            })
+            .clone()
+    }
+
+    /// The term of a tree over the variables of the theorem.
+    fn fixed_term(&mut self, tree: &SyntaxTree) -> Term {
+        self.state.unifier.fixed_term(self.grammar, tree, &mut self.state.fixed)
+    }
+
+    fn formula(&self, step: &Step) -> Vec<String> {
+        let mut formula = vec![step.typecode.clone()];
+        formula.extend(self.state.unifier.resolve(&step.target).symbols(self.database));
+        formula
+    }
+
+    /// The unbound work variables in a term.
+    fn work_variables(&self, term: &Term) -> Vec<usize> {
+        match self.state.unifier.walk(term) {
+            Term::Variable(v) if self.state.unifier.is_fixed(v) => Vec::new(),
+            Term::Variable(v) => vec![v],
+            Term::Node(_, children) => children.iter().flat_map(|child| self.work_variables(child)).collect(),
+        }
+    }
+
+    /// Whether the work variables `open` of the step `id` are still unbound
+    /// and nothing below the step is left with other work variables.
+    fn unchanged(&self, id: usize, open: &[usize]) -> bool {
+        if open.iter().any(|&v| self.state.unifier.bindings[v].is_some()) {
+            return false;
+        }
+        let mut terms = Vec::new();
+        let mut pending = vec![id];
+        while let Some(step) = pending.pop() {
+            let Some(assigned) = &self.state.steps[step].assigned else {
+                continue;
+            };
+            for hypothesis in &assigned.hypotheses {
+                match hypothesis {
+                    Hypothesis::Floating(term) => terms.push(term.clone()),
+                    Hypothesis::Essential(step) => pending.push(*step),
+                }
+            }
+        }
+        terms
+            .iter()
+            .flat_map(|term| self.work_variables(term))
+            .all(|v| open.contains(&v))
+    }
+
+    /// The `$e` steps the assignment of the step `id` made.
+    fn new_steps(&self, id: usize) -> Vec<usize> {
+        self.state.steps[id].assigned.as_ref().map_or(Vec::new(), |assigned| {
+            assigned
+                .hypotheses
+                .iter()
+                .filter_map(|hypothesis| match hypothesis {
+                    Hypothesis::Essential(step) => Some(*step),
+                    Hypothesis::Floating(_) => None,
+                })
+                .collect()
+        })
+    }
+
+    fn essential_hypotheses(&self) -> Vec<usize> {
+        self.database.entries[self.theorem]
+            .frame
+            .hypotheses
+            .iter()
+            .copied()
+            .filter(|&h| self.database.entries[h].kind == EntryKind::EssentialHypothesis)
+            .collect()
+    }
+
+    /// The id of the unknown step numbered `number`.
+    fn unknown_step(&self, number: usize) -> Result<usize, AssistantError> {
+        let order = self.order();
+        let &(id, _) = number
+            .checked_sub(1)
+            .and_then(|i| order.get(i))
+            .ok_or(AssistantError::UnknownStep(number))?;
+        match self.state.steps[id].assigned {
+            Some(_) => Err(AssistantError::StepAssigned(number)),
+            None => Ok(id),
+        }
+    }
+
+    /// Ids and depths of the steps in the proof, hypotheses first.
+    fn order(&self) -> Vec<(usize, usize)> {
+        fn visit(steps: &[Step], id: usize, depth: usize, order: &mut Vec<(usize, usize)>) {
+            if let Some(assigned) = &steps[id].assigned {
+                for hypothesis in &assigned.hypotheses {
+                    if let Hypothesis::Essential(step) = hypothesis {
+                        visit(steps, *step, depth + 1, order);
+                    }
+                }
+            }
+            order.push((id, depth));
+        }
+        let mut order = Vec::new();
+        visit(&self.state.steps, 0, 0, &mut order);
+        order
+    }
+}
+
+/// A subproof of a loaded proof.
+#[derive(Debug, Clone)]
+enum Loaded {
+    Unknown,
+    Entry(usize, Vec<Loaded>),
+}
+
+/// The expression a syntax subproof builds, if it has no unknown steps.
+fn syntax_tree(database: &Database, loaded: &Loaded) -> Option<SyntaxTree> {
+    let Loaded::Entry(index, hypotheses) = loaded else {
+        return None;
+    };
+    match database.entries[*index].kind {
+        EntryKind::FloatingHypothesis => Some(SyntaxTree::Variable(database.floating_variable(*index).to_string())),
+        _ => Some(SyntaxTree::Node(
+            *index,
+            hypotheses
+                .iter()
+                .map(|h| syntax_tree(database, h))
+                .collect::<Option<_>>()?,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify::verify;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    /// The demo database with the proof `proof` of `label` taken out.
+    fn unproved(label: &str, proof: &str) -> String {
+        let proved = format!("{label} $p");
+        let start = DEMO.find(&proved).unwrap();
+        let proof = &DEMO[start..].replacen(&format!("$= {proof} $."), "$= ? $.", 1);
+        format!("{}{proof}", &DEMO[..start])
+    }
+
+    #[test]
+    fn improve_proves_a_variable_of_the_theorem() {
+        let text = unproved("mp1", "( ax-mp ) ABCDE");
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let grammar = Grammar::new(&database);
+        let mut assistant = Assistant::new(&database, &grammar, database.index_of("mp1").unwrap()).unwrap();
+
+        let number = assistant.unknown()[0].number;
+        assert_eq!(assistant.improve(number, 1).unwrap().as_deref(), Some("ax-mp"));
+        assert!(assistant.is_complete());
+        assert_eq!(assistant.proof().unwrap().to_string(), "( ax-mp ) ABCDE");
+    }
+
+    #[test]
+    fn improve_all_searches_as_deep_as_asked() {
+        let text = unproved("mp2b", "( ax-mp ) BCABDEGFG");
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let grammar = Grammar::new(&database);
+        let theorem = database.index_of("mp2b").unwrap();
+
+        let mut assistant = Assistant::new(&database, &grammar, theorem).unwrap();
+        assert!(assistant.improve_all(1).is_empty());
+        assert!(!assistant.is_complete());
+        assert!(matches!(assistant.proof(), Err(AssistantError::Incomplete(1))));
+
+        let mut assistant = Assistant::new(&database, &grammar, theorem).unwrap();
+        assert!(!assistant.improve_all(2).is_empty());
+        let proof = assistant.proof().unwrap();
+        let saved = crate::compress::replace_proof(text.as_bytes(), &database, theorem, &proof).unwrap();
+        let (_, document) = db_parser::document::document(&saved).unwrap();
+        let database = Database::new(&document).unwrap();
+        assert_eq!(verify(&database, theorem), Ok(()));
+    }
+
+    #[test]
+    fn assign_and_undo() {
+        let text = unproved("mp1", "( ax-mp ) ABCDE");
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let grammar = Grammar::new(&database);
+        let mut assistant = Assistant::new(&database, &grammar, database.index_of("mp1").unwrap()).unwrap();
+
+        let number = assistant.unknown()[0].number;
+        assistant.assign(number, "ax-mp").unwrap();
+        assert_eq!(assistant.unknown().len(), 2);
+        let assigned = assistant.steps().into_iter().find(|step| step.label.as_deref() == Some("ax-mp")).unwrap();
+        assert!(matches!(assistant.assign(assigned.number, "ax-mp"), Err(AssistantError::StepAssigned(_))));
+        assert!(assistant.undo());
+        assert_eq!(assistant.unknown().len(), 1);
+        assert!(!assistant.undo());
+    }
+}
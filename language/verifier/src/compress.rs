@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use db_parser::terminals::Label;
+
+use crate::database::Database;
+use crate::proof::{proof_graph, Node, ProofStep};
+use crate::verify::{VerifyError, VerifyErrorKind};
+
+/// Lines of proof text are wrapped to this width, as in set.mm.
+pub const LINE_WIDTH: usize = 79;
+
+/// A compressed proof: the parenthesized label list and the letter string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedProof<'a> {
+    pub labels: Vec<Label<'a>>,
+    pub letters: String,
+}
+impl<'a> EncodedProof<'a> {
+    /// The proof laid out the way metamath writes it: wrapped to
+    /// `LINE_WIDTH`, every line indented by `indent`, ending with `$.`.
+    pub fn layout(&self, indent: usize) -> String {
+        let mut layout = Layout {
+            width: LINE_WIDTH.max(indent + 3),
+            indent,
+            lines: Vec::new(),
+            line: " ".repeat(indent),
+        };
+        layout.word("(");
+        for label in &self.labels {
+            layout.word(&label.to_string());
+        }
+        layout.word(")");
+        let mut letters = self.letters.as_str();
+        while !letters.is_empty() {
+            let fresh = layout.line.len() == indent;
+            let room = layout.width.saturating_sub(layout.line.len() + usize::from(!fresh));
+            if room == 0 {
+                layout.break_line();
+                continue;
+            }
+            if !fresh {
+                layout.line.push(' ');
+            }
+            let (head, tail) = letters.split_at(room.min(letters.len()));
+            layout.line.push_str(head);
+            letters = tail;
+        }
+        layout.word("$.");
+        layout.break_line();
+        layout.lines.join("\n")
+    }
+}
+
+impl<'a> Display for EncodedProof<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(")?;
+        for label in &self.labels {
+            write!(f, " {label}")?;
+        }
+        write!(f, " ) {}", self.letters)
+    }
+}
+
+struct Layout {
+    width: usize,
+    indent: usize,
+    lines: Vec<String>,
+    line: String,
+}
+impl Layout {
+    fn word(&mut self, word: &str) {
+        if self.line.len() > self.indent && self.line.len() + 1 + word.len() > self.width {
+            self.break_line();
+        }
+        if self.line.len() > self.indent {
+            self.line.push(' ');
+        }
+        self.line.push_str(word);
+    }
+
+    fn break_line(&mut self) {
+        let next = " ".repeat(self.indent);
+        self.lines.push(std::mem::replace(&mut self.line, next));
+    }
+}
+
+/// Writes `number` in the compressed proof alphabet: a final letter `A`-`T`
+/// preceded by base-5 digits `U`-`Y`.
+pub fn encode_number(number: usize) -> String {
+    let mut letters = vec![b'A' + ((number - 1) % 20) as u8];
+    let mut rest = (number - 1) / 20;
+    while rest > 0 {
+        letters.push(b'U' + ((rest - 1) % 5) as u8);
+        rest = (rest - 1) / 5;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Item {
+    Entry(usize),
+    Saved(usize),
+    Save,
+}
+
+/// Compresses a proof of the `$p` entry at `theorem`.
+///
+/// `steps` is any valid step list, for example a plain RPN list of
+/// `ProofStep::Entry`. Identical subproofs are shared: every subproof used
+/// more than once is written out the first time, marked with `Z`, and
+/// referred back to afterwards. Labels that occur most often get the shortest
+/// numbers; labels whose numbers have the same length keep the order in which
+/// they first occur.
+pub fn compress<'a>(database: &Database<'a>, theorem: usize, steps: &[ProofStep]) -> Result<EncodedProof<'a>, VerifyError> {
+    let entry = &database.entries[theorem];
+    let error = |kind| VerifyError {
+        label: entry.label.to_string(),
+        kind,
+    };
+    let (nodes, root) = proof_graph(database, steps).map_err(error)?;
+
+    let mut references = vec![0usize; nodes.len()];
+    references[root] += 1;
+    for (_, children) in &nodes {
+        for &child in children {
+            references[child] += 1;
+        }
+    }
+    let mut items = Vec::new();
+    let mut saved = vec![None; nodes.len()];
+    let mut saves = 0;
+    emit(&nodes, &references, root, &mut saved, &mut saves, &mut items);
+
+    let mandatory = &entry.frame.hypotheses;
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    let mut first_use = Vec::new();
+    for item in &items {
+        if let Item::Entry(index) = item {
+            if !mandatory.contains(index) {
+                let count = counts.entry(*index).or_default();
+                if *count == 0 {
+                    first_use.push(*index);
+                }
+                *count += 1;
+            }
+        }
+    }
+    let mut labels = first_use.clone();
+    labels.sort_by_key(|index| std::cmp::Reverse(counts[index]));
+    let letter_count = |position: usize| encode_number(mandatory.len() + position + 1).len();
+    let mut start = 0;
+    while start < labels.len() {
+        let end = (start..labels.len()).find(|&i| letter_count(i) != letter_count(start)).unwrap_or(labels.len());
+        labels[start..end].sort_by_key(|index| first_use.iter().position(|used| used == index));
+        start = end;
+    }
+
+    let numbers: HashMap<usize, usize> = mandatory
+        .iter()
+        .chain(&labels)
+        .enumerate()
+        .map(|(i, &index)| (index, i + 1))
+        .collect();
+    let mut letters = String::new();
+    for item in items {
+        match item {
+            Item::Entry(index) => letters.push_str(&encode_number(numbers[&index])),
+            Item::Saved(n) => letters.push_str(&encode_number(mandatory.len() + labels.len() + n + 1)),
+            Item::Save => letters.push('Z'),
+        }
+    }
+    Ok(EncodedProof {
+        labels: labels.iter().map(|&index| database.entries[index].label).collect(),
+        letters,
+    })
+}
+
+fn emit(
+    nodes: &[Node],
+    references: &[usize],
+    id: usize,
+    saved: &mut [Option<usize>],
+    saves: &mut usize,
+    items: &mut Vec<Item>,
+) {
+    if let Some(n) = saved[id] {
+        items.push(Item::Saved(n));
+        return;
+    }
+    let (index, children) = &nodes[id];
+    for &child in children {
+        emit(nodes, references, child, saved, saves, items);
+    }
+    items.push(Item::Entry(*index));
+    if references[id] > 1 && !children.is_empty() {
+        items.push(Item::Save);
+        saved[id] = Some(*saves);
+        *saves += 1;
+    }
+}
+
+/// Replaces the proof of the `$p` entry at `theorem` in `input`, the text
+/// `database` was built from, with `proof`. Fails with `MissingProof` when
+/// the statement's label is not in `input` or the statement has no
+/// `$= ... $.` there.
+pub fn replace_proof(
+    input: &[u8],
+    database: &Database,
+    theorem: usize,
+    proof: &EncodedProof,
+) -> Result<Vec<u8>, VerifyError> {
+    replace_proofs(input, database, &[(theorem, proof.clone())])
+}
+
+/// Replaces the proofs of several `$p` entries at once, as `replace_proof`
+/// does for one.
+pub fn replace_proofs(
+    input: &[u8],
+    database: &Database,
+    proofs: &[(usize, EncodedProof)],
+) -> Result<Vec<u8>, VerifyError> {
+    let mut proofs: Vec<&(usize, EncodedProof)> = proofs.iter().collect();
+    proofs.sort_by_key(|(theorem, _)| *theorem);
+    let mut output = Vec::new();
+    let mut copied = 0;
+    for (theorem, proof) in proofs {
+        let label = &database.entries[*theorem].label;
+        let missing = || VerifyError {
+            label: label.to_string(),
+            kind: VerifyErrorKind::MissingProof,
+        };
+        let start = label.0.offset_within(input).filter(|&start| start >= copied).ok_or_else(missing)?;
+        let line_start = input[..start].iter().rposition(|&c| c == b'\n').map_or(0, |i| i + 1);
+        let indent = input[line_start..start].iter().take_while(|c| **c == b' ').count() + 2;
+
+        let proof_end = start + input[start..].windows(2).position(|w| w == b"$.").ok_or_else(missing)? + 2;
+        let proof_start = start + input[start..proof_end].windows(2).position(|w| w == b"$=").ok_or_else(missing)? + 2;
+
+        output.extend_from_slice(&input[copied..proof_start]);
+        output.push(b'\n');
+        output.extend_from_slice(proof.layout(indent).as_bytes());
+        copied = proof_end;
+    }
+    output.extend_from_slice(&input[copied..]);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::decode;
+    use crate::verify::verify;
+
+    const DEMO: &[u8] = include_bytes!("../../fixtures/demo.mm");
+
+    #[test]
+    fn compress_round_trips_the_proofs_of_the_demo_database() {
+        let (_, document) = db_parser::document::document(DEMO).unwrap();
+        let database = Database::new(&document).unwrap();
+        for label in ["a1i", "mp2b", "mp2c", "mp1"] {
+            let theorem = database.index_of(label).unwrap();
+            let steps = decode(&database, theorem).unwrap();
+            let proof = compress(&database, theorem, &steps).unwrap();
+
+            let saved = replace_proof(DEMO, &database, theorem, &proof).unwrap();
+            let (_, document) = db_parser::document::document(&saved).unwrap();
+            let database = Database::new(&document).unwrap();
+            assert_eq!(decode(&database, theorem), Ok(steps), "{label}");
+            assert_eq!(verify(&database, theorem), Ok(()), "{label}");
+        }
+    }
+
+    #[test]
+    fn numbers_are_encoded_as_in_the_metamath_book() {
+        let numbers: Vec<String> = [1, 20, 21, 120, 121].into_iter().map(encode_number).collect();
+        assert_eq!(numbers, ["A", "T", "UA", "YT", "UUA"]);
+    }
+
+    #[test]
+    fn replace_proof_fails_without_the_proof_in_the_text() {
+        let (_, document) = db_parser::document::document(DEMO).unwrap();
+        let database = Database::new(&document).unwrap();
+        let theorem = database.index_of("mp1").unwrap();
+        let steps = decode(&database, theorem).unwrap();
+        let proof = compress(&database, theorem, &steps).unwrap();
+
+        let start = database.entries[theorem].label.0.offset_in(DEMO);
+        let cut = &DEMO[..start + DEMO[start..].iter().position(|&c| c == b'$').unwrap()];
+        let error = replace_proof(cut, &database, theorem, &proof).unwrap_err();
+        assert_eq!(error.kind, VerifyErrorKind::MissingProof);
+        assert_eq!(error.label, "mp1");
+
+        // The same text, but not the one the database borrows from.
+        let copy = DEMO.to_vec();
+        let error = replace_proof(&copy, &database, theorem, &proof).unwrap_err();
+        assert_eq!(error.kind, VerifyErrorKind::MissingProof);
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use db_parser::document::Document;
+use db_parser::statement::{Descriptions, Proof, Statement};
+use db_parser::terminals::{Label, Symbol};
+use db_parser::AsciiSequence;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryKind {
+    FloatingHypothesis,
+    EssentialHypothesis,
+    AxiomaticAssertion,
+    ProvableAssertion,
+}
+impl EntryKind {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Self::FloatingHypothesis => "$f",
+            Self::EssentialHypothesis => "$e",
+            Self::AxiomaticAssertion => "$a",
+            Self::ProvableAssertion => "$p",
+        }
+    }
+}
+
+/// A labeled statement together with the context needed to use it.
+#[derive(Debug, Clone)]
+pub struct Entry<'a> {
+    pub label: Label<'a>,
+    pub kind: EntryKind,
+    /// The math string, typecode first.
+    pub symbols: Vec<Symbol<'a>>,
+    /// Mandatory hypotheses and `$d` pairs. Empty for hypotheses.
+    pub frame: Frame<'a>,
+    pub proof: Option<Proof<'a>>,
+    /// The description comment of an assertion: the last comment before it
+    /// that no earlier assertion took.
+    pub comment: Option<AsciiSequence<'a>>,
+    /// Index of the first entry outside the block a hypothesis is declared in.
+    pub scope_end: usize,
+}
+impl<'a> Entry<'a> {
+    pub fn is_hypothesis(&self) -> bool {
+        matches!(self.kind, EntryKind::FloatingHypothesis | EntryKind::EssentialHypothesis)
+    }
+
+    pub fn is_assertion(&self) -> bool {
+        !self.is_hypothesis()
+    }
+
+    pub fn typecode(&self) -> Symbol<'a> {
+        self.symbols[0]
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Frame<'a> {
+    /// Entry indices of the mandatory hypotheses, in database order.
+    pub hypotheses: Vec<usize>,
+    /// Every `$d` pair active at the assertion, including those on dummy
+    /// variables. Pairs on variables outside the substitution are ignored
+    /// when the assertion is applied.
+    pub disjoint: Vec<(Symbol<'a>, Symbol<'a>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatabaseError {
+    DuplicateLabel(String),
+    MalformedFloatingHypothesis(String),
+}
+impl Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateLabel(label) => write!(f, "label {label} is declared more than once"),
+            Self::MalformedFloatingHypothesis(label) => {
+                write!(f, "{label} must be a typecode followed by a single variable")
+            }
+        }
+    }
+}
+impl std::error::Error for DatabaseError {}
+
+/// The labeled statements of a parsed database, with their frames.
+#[derive(Debug, Clone, Default)]
+pub struct Database<'a> {
+    pub entries: Vec<Entry<'a>>,
+    pub labels: HashMap<Label<'a>, usize>,
+    pub constants: HashSet<Symbol<'a>>,
+    pub variables: HashSet<Symbol<'a>>,
+}
+impl<'a> Database<'a> {
+    pub fn new(document: &Document<'a>) -> Result<Self, DatabaseError> {
+        let mut builder = Builder::default();
+        for statement in document.statements() {
+            builder.statement(statement)?;
+        }
+        Ok(builder.database)
+    }
+
+    pub fn get(&self, label: &Label<'a>) -> Option<&Entry<'a>> {
+        self.labels.get(label).map(|&index| &self.entries[index])
+    }
+
+    pub fn index_of(&self, label: &str) -> Option<usize> {
+        self.labels.get(&Label(label.as_bytes().into())).copied()
+    }
+
+    pub fn is_variable(&self, symbol: &Symbol<'a>) -> bool {
+        self.variables.contains(symbol)
+    }
+
+    /// The variable a `$f` entry declares the type of.
+    pub fn floating_variable(&self, index: usize) -> Symbol<'a> {
+        self.entries[index].symbols[1]
+    }
+
+    pub fn assertions(&self) -> impl Iterator<Item = (usize, &Entry<'a>)> {
+        self.entries.iter().enumerate().filter(|(_, entry)| entry.is_assertion())
+    }
+}
+
+#[derive(Default)]
+struct Builder<'a> {
+    database: Database<'a>,
+    hypotheses: Vec<usize>,
+    disjoint: Vec<(Symbol<'a>, Symbol<'a>)>,
+    descriptions: Descriptions<'a>,
+}
+impl<'a> Builder<'a> {
+    fn statement(&mut self, statement: &Statement<'a>) -> Result<(), DatabaseError> {
+        let description = self.descriptions.describe(statement);
+        match statement {
+            Statement::ConstantDeclaration(decl) => {
+                self.database.constants.extend(decl.symbol_list.iter().copied());
+            }
+            Statement::VariableDeclaration(decl) => {
+                self.database.variables.extend(decl.symbol_list.iter().copied());
+            }
+            Statement::DisjoinVariableDeclaration(decl) => {
+                for (i, &x) in decl.symbol_list.iter().enumerate() {
+                    for &y in &decl.symbol_list[i + 1..] {
+                        self.disjoint.push((x, y));
+                    }
+                }
+            }
+            Statement::FloatingHypothesis(hyp) => {
+                if hyp.symbol_list.len() != 2 {
+                    return Err(DatabaseError::MalformedFloatingHypothesis(hyp.label.to_string()));
+                }
+                let index = self.push(hyp.label, EntryKind::FloatingHypothesis, &hyp.symbol_list, None)?;
+                self.hypotheses.push(index);
+            }
+            Statement::EssentialHypothesis(hyp) => {
+                let index = self.push(hyp.label, EntryKind::EssentialHypothesis, &hyp.symbol_list, None)?;
+                self.hypotheses.push(index);
+            }
+            Statement::AxiomaticAssertion(assert) => {
+                let index = self.push(assert.label, EntryKind::AxiomaticAssertion, &assert.symbol_list, None)?;
+                self.database.entries[index].frame = self.frame(&assert.symbol_list);
+                self.database.entries[index].comment = description;
+            }
+            Statement::ProvableAssertion(assert) => {
+                let proof = Some(assert.proof.clone());
+                let index = self.push(assert.label, EntryKind::ProvableAssertion, &assert.symbol_list, proof)?;
+                self.database.entries[index].frame = self.frame(&assert.symbol_list);
+                self.database.entries[index].comment = description;
+            }
+            Statement::Block(statements) => {
+                let (hypotheses, disjoint) = (self.hypotheses.len(), self.disjoint.len());
+                for statement in statements {
+                    self.statement(statement)?;
+                }
+                let scope_end = self.database.entries.len();
+                for &index in &self.hypotheses[hypotheses..] {
+                    self.database.entries[index].scope_end = scope_end;
+                }
+                self.hypotheses.truncate(hypotheses);
+                self.disjoint.truncate(disjoint);
+            }
+            Statement::Comment(_) => {}
+        }
+        Ok(())
+    }
+
+    fn push(
+        &mut self,
+        label: Label<'a>,
+        kind: EntryKind,
+        symbols: &[Symbol<'a>],
+        proof: Option<Proof<'a>>,
+    ) -> Result<usize, DatabaseError> {
+        let index = self.database.entries.len();
+        if self.database.labels.insert(label, index).is_some() {
+            return Err(DatabaseError::DuplicateLabel(label.to_string()));
+        }
+        self.database.entries.push(Entry {
+            label,
+            kind,
+            symbols: symbols.to_vec(),
+            frame: Frame::default(),
+            proof,
+            comment: None,
+            scope_end: usize::MAX,
+        });
+        Ok(index)
+    }
+
+    /// The mandatory hypotheses are every active `$e` plus the active `$f`
+    /// whose variable occurs in the assertion or in one of those `$e`.
+    fn frame(&self, symbols: &[Symbol<'a>]) -> Frame<'a> {
+        let entries = &self.database.entries;
+        let mut variables: HashSet<Symbol<'a>> = symbols
+            .iter()
+            .filter(|symbol| self.database.variables.contains(symbol))
+            .copied()
+            .collect();
+        for &index in &self.hypotheses {
+            if entries[index].kind == EntryKind::EssentialHypothesis {
+                variables.extend(
+                    entries[index].symbols.iter().filter(|symbol| self.database.variables.contains(symbol)),
+                );
+            }
+        }
+        let hypotheses = self
+            .hypotheses
+            .iter()
+            .copied()
+            .filter(|&index| match entries[index].kind {
+                EntryKind::FloatingHypothesis => variables.contains(&entries[index].symbols[1]),
+                _ => true,
+            })
+            .collect();
+        Frame {
+            hypotheses,
+            disjoint: self.disjoint.clone(),
+        }
+    }
+}
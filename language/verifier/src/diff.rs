@@ -0,0 +1,497 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::Display;
+
+use db_parser::statement::Proof;
+use serde::Serialize;
+
+use crate::database::{Database, Entry, EntryKind};
+use crate::proof::{decode, proof_graph};
+
+/// A heading of the outline of a database, as the title tree numbers it.
+#[derive(Debug, Clone)]
+pub struct Heading {
+    pub title: String,
+    pub ord: Vec<usize>,
+    /// Title of the heading it is nested in.
+    pub parent: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LabeledStatement {
+    pub label: String,
+    pub keyword: &'static str,
+}
+
+/// A statement removed under one label and added under another with the
+/// same kind, math and hypotheses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Renamed {
+    pub from: String,
+    pub to: String,
+}
+
+/// A statement whose keyword or math changed, both written as
+/// `$p |- ...`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedStatement {
+    pub label: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// A proof that proves its statement differently, with the sizes of the
+/// compressed proof written on one line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedProof {
+    pub label: String,
+    pub before: usize,
+    pub after: usize,
+}
+
+/// The `$d` pairs that came and went at an assertion, each written `x y`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedDisjoint {
+    pub label: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// A heading found under another heading, or out of order among the
+/// headings both databases have. Headings that moved along with the heading
+/// they are under are not listed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MovedSection {
+    pub title: String,
+    pub from: Vec<usize>,
+    pub to: Vec<usize>,
+}
+
+/// The differences between two versions of a database that change what it
+/// states or proves. Entries are listed in the order of the database they
+/// are found in, the new one unless they were removed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Diff {
+    pub added: Vec<LabeledStatement>,
+    pub removed: Vec<LabeledStatement>,
+    pub renamed: Vec<Renamed>,
+    pub changed_statements: Vec<ChangedStatement>,
+    pub changed_proofs: Vec<ChangedProof>,
+    pub changed_disjoint: Vec<ChangedDisjoint>,
+    pub moved_sections: Vec<MovedSection>,
+}
+impl Diff {
+    /// Compares the entries and outlines of `old` and `new`. Whitespace,
+    /// proof layout, the order of the labels of a compressed proof and the
+    /// places it saves subproofs at make no difference.
+    pub fn new(old: &Database, old_headings: &[Heading], new: &Database, new_headings: &[Heading]) -> Self {
+        let mut diff = Diff::default();
+        let removed: Vec<usize> = (0..old.entries.len())
+            .filter(|&index| new.get(&old.entries[index].label).is_none())
+            .collect();
+        let added: Vec<usize> = (0..new.entries.len())
+            .filter(|&index| old.get(&new.entries[index].label).is_none())
+            .collect();
+
+        // A removed statement is taken as renamed to an added one that
+        // states the same, the first with the same proof if there is one.
+        let mut candidates: HashMap<Vec<String>, VecDeque<usize>> = HashMap::new();
+        for &index in &added {
+            candidates.entry(signature(new, index)).or_default().push_back(index);
+        }
+        let mut renames: HashMap<String, String> = HashMap::new();
+        let mut renamed_from: HashMap<usize, usize> = HashMap::new();
+        for &index in &removed {
+            let renamed = candidates.get_mut(&signature(old, index)).and_then(|candidates| {
+                let shape = proof_shape(old, index, &renames);
+                let same = candidates
+                    .iter()
+                    .position(|&to| proof_shape(new, to, &HashMap::new()) == shape);
+                candidates.remove(same.unwrap_or(0))
+            });
+            match renamed {
+                Some(to) => {
+                    renamed_from.insert(to, index);
+                    renames.insert(old.entries[index].label.to_string(), new.entries[to].label.to_string());
+                }
+                None => diff.removed.push(labeled(&old.entries[index])),
+            }
+        }
+        for &index in &added {
+            if !renamed_from.contains_key(&index) {
+                diff.added.push(labeled(&new.entries[index]));
+            }
+        }
+
+        for (index, entry) in new.entries.iter().enumerate() {
+            let label = entry.label.to_string();
+            let old_index = match (old.index_of(&label), renamed_from.get(&index)) {
+                (Some(old_index), _) => old_index,
+                (None, Some(&old_index)) => {
+                    diff.renamed.push(Renamed {
+                        from: old.entries[old_index].label.to_string(),
+                        to: label.clone(),
+                    });
+                    old_index
+                }
+                (None, None) => continue,
+            };
+            let old_entry = &old.entries[old_index];
+            let (before, after) = (statement(old_entry), statement(entry));
+            if before != after {
+                diff.changed_statements.push(ChangedStatement {
+                    label: label.clone(),
+                    before,
+                    after,
+                });
+            }
+            if entry.kind == EntryKind::ProvableAssertion
+                && old_entry.kind == EntryKind::ProvableAssertion
+                && proof_shape(old, old_index, &renames) != proof_shape(new, index, &HashMap::new())
+            {
+                diff.changed_proofs.push(ChangedProof {
+                    label: label.clone(),
+                    before: old_entry.proof.as_ref().map_or(0, Proof::size),
+                    after: entry.proof.as_ref().map_or(0, Proof::size),
+                });
+            }
+            if entry.is_assertion() && old_entry.is_assertion() {
+                let (before, after) = (disjoint(old_entry), disjoint(entry));
+                if before != after {
+                    diff.changed_disjoint.push(ChangedDisjoint {
+                        label,
+                        added: after.difference(&before).cloned().collect(),
+                        removed: before.difference(&after).cloned().collect(),
+                    });
+                }
+            }
+        }
+        diff.moved_sections = moved_sections(old_headings, new_headings);
+        diff
+    }
+}
+impl Display for Diff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for statement in &self.added {
+            writeln!(f, "added {} {}", statement.keyword, statement.label)?;
+        }
+        for statement in &self.removed {
+            writeln!(f, "removed {} {}", statement.keyword, statement.label)?;
+        }
+        for renamed in &self.renamed {
+            writeln!(f, "renamed {} to {}", renamed.from, renamed.to)?;
+        }
+        for changed in &self.changed_statements {
+            writeln!(f, "changed {}: {}", changed.label, changed.before)?;
+            writeln!(
+                f,
+                "{:>width$}: {}",
+                "to",
+                changed.after,
+                width = changed.label.len() + 8
+            )?;
+        }
+        for changed in &self.changed_proofs {
+            let change = changed.after as isize - changed.before as isize;
+            writeln!(
+                f,
+                "proof of {}: {} -> {} bytes ({change:+})",
+                changed.label, changed.before, changed.after
+            )?;
+        }
+        for changed in &self.changed_disjoint {
+            write!(f, "$d of {}:", changed.label)?;
+            for pair in &changed.added {
+                write!(f, " +{{{pair}}}")?;
+            }
+            for pair in &changed.removed {
+                write!(f, " -{{{pair}}}")?;
+            }
+            writeln!(f)?;
+        }
+        for moved in &self.moved_sections {
+            let ord = |ord: &[usize]| ord.iter().map(|n| format!("{n}.")).collect::<String>();
+            writeln!(f, "moved {} {} to {}", ord(&moved.from), moved.title, ord(&moved.to))?;
+        }
+        Ok(())
+    }
+}
+
+fn labeled(entry: &Entry) -> LabeledStatement {
+    LabeledStatement {
+        label: entry.label.to_string(),
+        keyword: entry.kind.keyword(),
+    }
+}
+
+fn math(entry: &Entry) -> String {
+    entry
+        .symbols
+        .iter()
+        .map(|symbol| symbol.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn statement(entry: &Entry) -> String {
+    format!("{} {}", entry.kind.keyword(), math(entry))
+}
+
+/// What an entry states: its keyword and math, and for an assertion the
+/// math of its essential hypotheses.
+fn signature(database: &Database, index: usize) -> Vec<String> {
+    let entry = &database.entries[index];
+    let mut signature = vec![statement(entry)];
+    signature.extend(
+        entry
+            .frame
+            .hypotheses
+            .iter()
+            .map(|&hypothesis| &database.entries[hypothesis])
+            .filter(|hypothesis| hypothesis.kind == EntryKind::EssentialHypothesis)
+            .map(math),
+    );
+    signature
+}
+
+fn disjoint(entry: &Entry) -> BTreeSet<String> {
+    entry
+        .frame
+        .disjoint
+        .iter()
+        .map(|(x, y)| {
+            let (x, y) = (x.to_string(), y.to_string());
+            if x < y {
+                format!("{x} {y}")
+            } else {
+                format!("{y} {x}")
+            }
+        })
+        .collect()
+}
+
+/// The subproofs of a proof, each as its label and the numbers of the
+/// subproofs of its hypotheses, numbered in the order a depth-first walk
+/// from the conclusion first reaches them. Labels are read through
+/// `renames`. A proof that does not decode is compared by its text.
+fn proof_shape(database: &Database, index: usize, renames: &HashMap<String, String>) -> Vec<String> {
+    let graph = decode(database, index)
+        .ok()
+        .and_then(|steps| proof_graph(database, &steps).ok());
+    let Some((nodes, root)) = graph else {
+        let proof = database.entries[index].proof.as_ref();
+        let labels = proof
+            .into_iter()
+            .flat_map(|proof| proof.labels.iter().map(|label| label.to_string()));
+        let letters = proof
+            .into_iter()
+            .flat_map(|proof| proof.compressed_proofs.iter().map(|c| c.0.to_string()));
+        return labels.chain(letters).collect();
+    };
+    fn visit(
+        node: usize,
+        nodes: &[(usize, Vec<usize>)],
+        label: &dyn Fn(usize) -> String,
+        numbers: &mut HashMap<usize, usize>,
+        shape: &mut Vec<String>,
+    ) -> usize {
+        if let Some(&number) = numbers.get(&node) {
+            return number;
+        }
+        let (entry, children) = &nodes[node];
+        let children: Vec<String> = children
+            .iter()
+            .map(|&child| visit(child, nodes, label, numbers, shape).to_string())
+            .collect();
+        shape.push(format!("{} {}", label(*entry), children.join(" ")));
+        numbers.insert(node, shape.len() - 1);
+        shape.len() - 1
+    }
+    let label = |entry: usize| {
+        let label = database.entries[entry].label.to_string();
+        renames.get(&label).cloned().unwrap_or(label)
+    };
+    let mut shape = Vec::new();
+    visit(root, &nodes, &label, &mut HashMap::new(), &mut shape);
+    shape
+}
+
+/// Headings whose title both outlines have once, and that sit under another
+/// heading or fall outside the longest run of such headings kept in order.
+fn moved_sections(old: &[Heading], new: &[Heading]) -> Vec<MovedSection> {
+    fn unique(headings: &[Heading]) -> HashSet<&str> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for heading in headings {
+            *counts.entry(heading.title.as_str()).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .filter(|&(_, count)| count == 1)
+            .map(|(title, _)| title)
+            .collect()
+    }
+    let (old_unique, new_unique) = (unique(old), unique(new));
+    let old_positions: HashMap<&str, usize> = old
+        .iter()
+        .enumerate()
+        .filter(|(_, heading)| old_unique.contains(heading.title.as_str()))
+        .map(|(position, heading)| (heading.title.as_str(), position))
+        .collect();
+    let common: Vec<(&Heading, &Heading)> = new
+        .iter()
+        .filter(|heading| new_unique.contains(heading.title.as_str()))
+        .filter_map(|heading| Some((&old[*old_positions.get(heading.title.as_str())?], heading)))
+        .collect();
+    let titles: HashSet<&str> = common.iter().map(|(_, new)| new.title.as_str()).collect();
+    // A parent only one outline has, such as a renamed heading, stands for
+    // any other such parent.
+    let same_parent = |old: &Heading, new: &Heading| match (old.parent.as_deref(), new.parent.as_deref()) {
+        (Some(a), Some(b)) if titles.contains(a) || titles.contains(b) => a == b,
+        (a, b) => a.is_some() == b.is_some(),
+    };
+
+    let in_order = longest_increasing(&common.iter().map(|(old, _)| old.ord.clone()).collect::<Vec<_>>());
+    let mut moved_titles = HashSet::new();
+    let mut moved = Vec::new();
+    for (i, (old, new)) in common.iter().enumerate() {
+        let same_parent = same_parent(old, new);
+        if in_order.contains(&i) && same_parent {
+            continue;
+        }
+        moved_titles.insert(new.title.as_str());
+        if same_parent
+            && new
+                .parent
+                .as_deref()
+                .is_some_and(|parent| moved_titles.contains(parent))
+        {
+            continue;
+        }
+        moved.push(MovedSection {
+            title: new.title.clone(),
+            from: old.ord.clone(),
+            to: new.ord.clone(),
+        });
+    }
+    moved
+}
+
+/// Positions of a longest strictly increasing subsequence of `values`.
+fn longest_increasing<T: Ord>(values: &[T]) -> HashSet<usize> {
+    // `tails[k]` is the position of the smallest last value of an
+    // increasing run of length k + 1 found so far.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; values.len()];
+    for i in 0..values.len() {
+        let k = tails.partition_point(|&tail| values[tail] < values[i]);
+        previous[i] = k.checked_sub(1).map(|k| tails[k]);
+        match tails.get_mut(k) {
+            Some(tail) => *tail = i,
+            None => tails.push(i),
+        }
+    }
+    let mut positions = HashSet::new();
+    let mut position = tails.last().copied();
+    while let Some(i) = position {
+        positions.insert(i);
+        position = previous[i];
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    fn heading(title: &str, ord: &[usize], parent: Option<&str>) -> Heading {
+        Heading {
+            title: title.to_string(),
+            ord: ord.to_vec(),
+            parent: parent.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn diff_ignores_layout_and_reports_changes() {
+        let new = DEMO
+            .replace("( wi ax-1 ax-mp ) ABADCABEF", "( ax-1 wi ax-mp )\n      ABAECABDF")
+            .replace("ax-2 $a |- ( ( ph", "ax-2 $a |- ( ( -. ph")
+            .replace(
+                "mp2c $p |- ch $= ( ax-mp ) BCABDEGFG $.",
+                "mp2c $p |- ch $= ( mp2b ) ABCDEFG $.",
+            )
+            .replace("    mp1.1 $e", "    $d ph ps $.\n    mp1.1 $e")
+            .replace("mp2c", "mp2d");
+        let (_, old) = db_parser::document::document(DEMO.as_bytes()).unwrap();
+        let old = Database::new(&old).unwrap();
+        let (_, new) = db_parser::document::document(new.as_bytes()).unwrap();
+        let new = Database::new(&new).unwrap();
+
+        let diff = Diff::new(&old, &[], &new, &[]);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        let renamed: Vec<(&str, &str)> = diff.renamed.iter().map(|r| (r.from.as_str(), r.to.as_str())).collect();
+        assert_eq!(
+            renamed,
+            [
+                ("mp2c.1", "mp2d.1"),
+                ("mp2c.2", "mp2d.2"),
+                ("mp2c.3", "mp2d.3"),
+                ("mp2c", "mp2d")
+            ]
+        );
+        let changed: Vec<&str> = diff.changed_statements.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(changed, ["ax-2"]);
+        let proof = ChangedProof {
+            label: "mp2d".to_string(),
+            before: "( ax-mp ) BCABDEGFG".len(),
+            after: "( mp2b ) ABCDEFG".len(),
+        };
+        assert_eq!(diff.changed_proofs, [proof]);
+        assert_eq!(diff.changed_disjoint.len(), 1);
+        assert_eq!(diff.changed_disjoint[0].added, ["ph ps"]);
+    }
+
+    #[test]
+    fn a_moved_section_is_reported_once() {
+        let old = [
+            heading("A", &[1], None),
+            heading("A.1", &[1, 1], Some("A")),
+            heading("A.2", &[1, 2], Some("A")),
+            heading("B", &[2], None),
+            heading("B.1", &[2, 1], Some("B")),
+            heading("B.1.1", &[2, 1, 1], Some("B.1")),
+            heading("B.2", &[2, 2], Some("B")),
+        ];
+        let new = [
+            heading("A", &[1], None),
+            heading("A.1", &[1, 1], Some("A")),
+            heading("B.1", &[1, 2], Some("A")),
+            heading("B.1.1", &[1, 2, 1], Some("B.1")),
+            heading("A.2", &[1, 3], Some("A")),
+            heading("B", &[2], None),
+            heading("B.2", &[2, 1], Some("B")),
+        ];
+        let moved = moved_sections(&old, &new);
+        assert_eq!(moved.len(), 1);
+        assert_eq!(
+            (moved[0].title.as_str(), &moved[0].from[..], &moved[0].to[..]),
+            ("B.1", &[2, 1][..], &[1, 2][..])
+        );
+    }
+
+    #[test]
+    fn a_renamed_heading_moves_nothing() {
+        let old = [
+            heading("A", &[1], None),
+            heading("A.1", &[1, 1], Some("A")),
+            heading("A.2", &[1, 2], Some("A")),
+        ];
+        let new = [
+            heading("Z", &[1], None),
+            heading("A.1", &[1, 1], Some("Z")),
+            heading("A.2", &[1, 2], Some("Z")),
+        ];
+        assert!(moved_sections(&old, &new).is_empty());
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt::Display;
+
+use db_parser::statement::Proof;
+
+use crate::database::Database;
+use crate::proof::{decode, proof_graph};
+use crate::usage::UsageIndex;
+
+/// Marks an assertion that new proofs should not use.
+pub const NEW_USAGE: &str = "(New usage is discouraged.)";
+/// Marks a theorem whose proof should be left as it is.
+pub const PROOF_MODIFICATION: &str = "(Proof modification is discouraged.)";
+
+/// Whether the description comment of the entry at `index` contains `tag`.
+/// Line breaks inside the tag are allowed.
+pub fn has_tag(database: &Database, index: usize, tag: &str) -> bool {
+    database.entries[index].comment.is_some_and(|comment| {
+        let words: Vec<&[u8]> = comment.as_bytes().split(u8::is_ascii_whitespace).filter(|w| !w.is_empty()).collect();
+        let tag: Vec<&[u8]> = tag.as_bytes().split(u8::is_ascii_whitespace).collect();
+        words.windows(tag.len()).any(|window| window == tag)
+    })
+}
+
+/// A breach of the discouraged tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The proof of `user` uses `used`, whose new usage is discouraged.
+    NewUsage { user: String, used: String },
+    /// The proof of a theorem whose modification is discouraged differs from
+    /// the baseline.
+    ProofModified(String),
+}
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NewUsage { user, used } => write!(f, "{user} uses {used}, whose new usage is discouraged"),
+            Self::ProofModified(label) => {
+                write!(f, "the proof of {label} was modified, but proof modification is discouraged")
+            }
+        }
+    }
+}
+
+/// Finds the proofs that use an assertion tagged `NEW_USAGE`.
+///
+/// Theorems that are themselves tagged `NEW_USAGE`, and those `allowed`
+/// accepts by entry index, may use discouraged assertions.
+pub fn new_usage_violations(database: &Database, usage: &UsageIndex, allowed: impl Fn(usize) -> bool) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (used, _) in database.assertions().filter(|(index, _)| has_tag(database, *index, NEW_USAGE)) {
+        for &user in usage.direct(used) {
+            if !has_tag(database, user, NEW_USAGE) && !allowed(user) {
+                violations.push(Violation::NewUsage {
+                    user: database.entries[user].label.to_string(),
+                    used: database.entries[used].label.to_string(),
+                });
+            }
+        }
+    }
+    violations
+}
+
+/// Finds the theorems tagged `PROOF_MODIFICATION`, in either database, whose
+/// proof differs from the one in `baseline`. Theorems missing from the
+/// baseline are new and not reported.
+pub fn modified_proofs(database: &Database, baseline: &Database) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (index, entry) in database.assertions() {
+        let Some(proof) = &entry.proof else {
+            continue;
+        };
+        let Some(old) = baseline.labels.get(&entry.label).copied() else {
+            continue;
+        };
+        if !has_tag(database, index, PROOF_MODIFICATION) && !has_tag(baseline, old, PROOF_MODIFICATION) {
+            continue;
+        }
+        if baseline.entries[old].proof.as_ref().map(proof_text) != Some(proof_text(proof)) {
+            violations.push(Violation::ProofModified(entry.label.to_string()));
+        }
+    }
+    violations
+}
+
+/// The proof with whitespace normalized, for comparing two versions.
+fn proof_text(proof: &Proof) -> String {
+    let labels: Vec<String> = proof.labels.iter().map(|label| label.to_string()).collect();
+    let letters: String = proof.compressed_proofs.iter().map(|letters| letters.0.to_string()).collect();
+    format!("( {} ) {letters}", labels.join(" "))
+}
+
+/// The number of steps of the proof at `index` in normal form, where each
+/// reuse of a saved subproof is written out again, as metamath.exe counts
+/// them. `None` if the proof cannot be decoded.
+fn normal_steps(database: &Database, index: usize) -> Option<usize> {
+    let steps = decode(database, index).ok()?;
+    let (nodes, root) = proof_graph(database, &steps).ok()?;
+    // Children always come before their parents in `nodes`.
+    let mut sizes: Vec<usize> = Vec::with_capacity(nodes.len());
+    for (_, children) in &nodes {
+        let size = 1 + children.iter().map(|&child| sizes[child]).sum::<usize>();
+        sizes.push(size);
+    }
+    Some(sizes[root])
+}
+
+/// The listing set.mm keeps in its `discouraged` file, one line per tagged
+/// assertion and per use of a `NEW_USAGE` assertion, sorted:
+///
+/// ```text
+/// New usage of "ax-10OLD" is discouraged (1 uses).
+/// Proof modification of "19.21t-1OLD" is discouraged (19 steps).
+/// "ax-10OLD" is used by "hbn1OLD".
+/// ```
+pub fn discouraged_file(database: &Database, usage: &UsageIndex) -> String {
+    let mut lines = Vec::new();
+    for (index, entry) in database.assertions() {
+        let label = entry.label.to_string();
+        if has_tag(database, index, NEW_USAGE) {
+            lines.push(format!("New usage of \"{label}\" is discouraged ({} uses).", usage.count(index)));
+            for &user in usage.direct(index) {
+                lines.push(format!("\"{label}\" is used by \"{}\".", database.entries[user].label));
+            }
+        }
+        if has_tag(database, index, PROOF_MODIFICATION) {
+            let steps = normal_steps(database, index).unwrap_or(0);
+            lines.push(format!("Proof modification of \"{label}\" is discouraged ({steps} steps)."));
+        }
+    }
+    lines.sort();
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify::verify_all;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+    const MP2B: &str = "A double modus ponens inference.";
+
+    fn database(text: &str) -> Database<'_> {
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        Database::new(&document).unwrap()
+    }
+
+    /// The demo with mp2b tagged `tag`.
+    fn tagged_mp2b(tag: &str) -> String {
+        DEMO.replacen(MP2B, &format!("{MP2B} {tag}"), 1)
+    }
+
+    #[test]
+    fn tags_are_found_across_line_breaks() {
+        let text = DEMO.replacen(MP2B, &format!("{MP2B} (New usage\n      is discouraged.)"), 1);
+        let database = database(&text);
+        let mp2b = database.index_of("mp2b").unwrap();
+        assert!(has_tag(&database, mp2b, NEW_USAGE));
+        assert!(!has_tag(&database, mp2b, PROOF_MODIFICATION));
+        assert!(!has_tag(&database, database.index_of("mp2c").unwrap(), NEW_USAGE));
+        // Entries without a description comment have no tags.
+        assert!(!has_tag(&database, database.index_of("mp2b.1").unwrap(), NEW_USAGE));
+    }
+
+    #[test]
+    fn using_a_discouraged_assertion_is_a_violation() {
+        let mp2c = "mp2c $p |- ch $= ( ax-mp ) BCABDEGFG $.";
+        let text = tagged_mp2b(NEW_USAGE).replacen(mp2c, "mp2c $p |- ch $= ( mp2b ) ABCDEFG $.", 1);
+        let database = database(&text);
+        let usage = UsageIndex::new(&database);
+        let mp2c = database.index_of("mp2c").unwrap();
+
+        let violation = Violation::NewUsage { user: "mp2c".to_string(), used: "mp2b".to_string() };
+        assert_eq!(new_usage_violations(&database, &usage, |_| false), [violation]);
+        assert!(new_usage_violations(&database, &usage, |user| user == mp2c).is_empty());
+
+        // A user that is itself discouraged may use mp2b.
+        let text = text.replacen("A second copy of mp2b.", &format!("A second copy of mp2b. {NEW_USAGE}"), 1);
+        let database = self::database(&text);
+        assert!(new_usage_violations(&database, &UsageIndex::new(&database), |_| false).is_empty());
+    }
+
+    #[test]
+    fn changed_proofs_of_tagged_theorems_are_reported() {
+        let tagged = tagged_mp2b(PROOF_MODIFICATION);
+        let baseline = database(&tagged);
+        let mp2b = "mp2b $p |- ch $= ( ax-mp ) BCABDEGFG $.";
+
+        // Reflowing the proof is not a change.
+        let reflowed = tagged.replacen(mp2b, "mp2b $p |- ch $=\n ( ax-mp )\n BCABD EGFG $.", 1);
+        assert!(modified_proofs(&database(&reflowed), &baseline).is_empty());
+
+        let changed = DEMO.replacen(mp2b, "mp2b $p |- ch $= ( ax-mp ax-mp ) BCABDEGFH $.", 1);
+        let changed = database(&changed);
+        let modified = [Violation::ProofModified("mp2b".to_string())];
+        // The tag of either version is enough.
+        assert_eq!(modified_proofs(&changed, &baseline), modified);
+        assert_eq!(modified_proofs(&baseline, &changed), modified);
+        // Untagged theorems may change.
+        assert!(modified_proofs(&changed, &database(DEMO)).is_empty());
+    }
+
+    #[test]
+    fn discouraged_file_lists_uses_and_normal_step_counts() {
+        // The proof reuses the saved `( ph -> ps )`: 6 compressed items, but
+        // 7 steps in normal form.
+        let twice = "$( ax-1 applied to itself. (Proof modification is discouraged.) $)
+  ax1twice $p |- ( ( ph -> ps ) -> ( ( ph -> ps ) -> ( ph -> ps ) ) ) $= ( wi ax-1 ) ABCZED $.
+";
+        let mp2c = "mp2c $p |- ch $= ( ax-mp ) BCABDEGFG $.";
+        let text = tagged_mp2b(NEW_USAGE).replacen(mp2c, "mp2c $p |- ch $= ( mp2b ) ABCDEFG $.", 1) + twice;
+        let database = database(&text);
+        assert!(verify_all(&database).is_empty());
+
+        let expected = concat!(
+            "\"mp2b\" is used by \"mp2c\".\n",
+            "New usage of \"mp2b\" is discouraged (1 uses).\n",
+            "Proof modification of \"ax1twice\" is discouraged (7 steps).\n",
+        );
+        assert_eq!(discouraged_file(&database, &UsageIndex::new(&database)), expected);
+    }
+}
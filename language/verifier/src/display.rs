@@ -0,0 +1,218 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::database::{Database, EntryKind};
+use crate::proof::{decode, ProofStep};
+use crate::verify::{substitute, substitution, Expression, VerifyError, VerifyErrorKind};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShowOptions {
+    /// Also list the steps that only build the syntax of a formula.
+    pub syntax: bool,
+}
+
+/// A `show proof` style listing of a theorem's proof.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProofListing {
+    pub label: String,
+    pub steps: Vec<ListedStep>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListedStep {
+    pub number: usize,
+    /// Distance from the last step, which proves the theorem.
+    pub depth: usize,
+    /// Numbers of the listed steps matched to the hypotheses.
+    pub hypotheses: Vec<usize>,
+    pub label: String,
+    pub keyword: &'static str,
+    /// What the step proves, typecode first.
+    pub formula: String,
+    /// Whether the step lies on the logical path rather than proving the
+    /// type of a variable substitution.
+    pub essential: bool,
+}
+
+struct Step<'a> {
+    entry: usize,
+    hypotheses: Vec<usize>,
+    formula: Expression<'a>,
+}
+
+/// Lists the proof of the `$p` entry at `theorem`, computing the formula of
+/// every step. Subproofs reused through `Z` are listed once and referred to by
+/// number.
+pub fn show_proof(database: &Database, theorem: usize, options: ShowOptions) -> Result<ProofListing, VerifyError> {
+    let entry = &database.entries[theorem];
+    let error = |kind| VerifyError {
+        label: entry.label.to_string(),
+        kind,
+    };
+
+    let mut steps: Vec<Step> = Vec::new();
+    let mut stack: Vec<usize> = Vec::new();
+    let mut saved = Vec::new();
+    for step in decode(database, theorem)? {
+        match step {
+            ProofStep::Entry(index) => {
+                let used = &database.entries[index];
+                let arity = if used.is_hypothesis() { 0 } else { used.frame.hypotheses.len() };
+                let base = stack
+                    .len()
+                    .checked_sub(arity)
+                    .ok_or_else(|| error(VerifyErrorKind::StackUnderflow(used.label.to_string())))?;
+                let hypotheses = stack.split_off(base);
+                let formula = if used.is_hypothesis() {
+                    used.symbols.clone()
+                } else {
+                    let expressions: Vec<_> = hypotheses.iter().map(|&i| steps[i].formula.clone()).collect();
+                    let substitution = substitution(database, &used.frame.hypotheses, &expressions).map_err(error)?;
+                    substitute(database, &used.symbols, &substitution)
+                };
+                stack.push(steps.len());
+                steps.push(Step {
+                    entry: index,
+                    hypotheses,
+                    formula,
+                });
+            }
+            ProofStep::Saved(n) => {
+                stack.push(*saved.get(n).ok_or_else(|| error(VerifyErrorKind::UnknownSavedStep(n)))?);
+            }
+            ProofStep::Save => saved.push(*stack.last().ok_or_else(|| error(VerifyErrorKind::NothingToSave))?),
+            ProofStep::Unknown => return Err(error(VerifyErrorKind::IncompleteProof)),
+        }
+    }
+    let [root] = stack[..] else {
+        return Err(error(VerifyErrorKind::StackNotSingleton(stack.len())));
+    };
+
+    let mut depth = vec![None; steps.len()];
+    let mut essential = vec![false; steps.len()];
+    let mut pending = vec![(root, 0, true)];
+    while let Some((i, d, is_essential)) = pending.pop() {
+        if depth[i].is_none() {
+            depth[i] = Some(d);
+        }
+        essential[i] |= is_essential;
+        let used = &database.entries[steps[i].entry];
+        for (&hypothesis, &child) in used.frame.hypotheses.iter().zip(&steps[i].hypotheses) {
+            let child_essential = is_essential && database.entries[hypothesis].kind == EntryKind::EssentialHypothesis;
+            if depth[child].is_none() || (child_essential && !essential[child]) {
+                pending.push((child, d + 1, child_essential));
+            }
+        }
+    }
+
+    let mut numbers = vec![0; steps.len()];
+    let mut listed = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        if !options.syntax && !essential[i] {
+            continue;
+        }
+        numbers[i] = listed.len() + 1;
+        let used = &database.entries[step.entry];
+        listed.push(ListedStep {
+            number: numbers[i],
+            depth: depth[i].unwrap_or(0),
+            hypotheses: step
+                .hypotheses
+                .iter()
+                .filter(|&&child| options.syntax || essential[child])
+                .map(|&child| numbers[child])
+                .collect(),
+            label: used.label.to_string(),
+            keyword: used.kind.keyword(),
+            formula: step.formula.iter().map(|symbol| symbol.to_string()).collect::<Vec<_>>().join(" "),
+            essential: essential[i],
+        });
+    }
+    Ok(ProofListing {
+        label: entry.label.to_string(),
+        steps: listed,
+    })
+}
+
+impl ProofListing {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+impl Display for ProofListing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hypotheses: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| step.hypotheses.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(","))
+            .collect();
+        let number_width = self.steps.len().to_string().len();
+        let hypotheses_width = hypotheses.iter().map(String::len).max().unwrap_or(0);
+        for (step, hypotheses) in self.steps.iter().zip(&hypotheses) {
+            writeln!(
+                f,
+                "{:>number_width$} {:<hypotheses_width$} {}{} {} {}",
+                step.number,
+                hypotheses,
+                "  ".repeat(step.depth),
+                step.label,
+                step.keyword,
+                step.formula,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &[u8] = include_bytes!("../../fixtures/demo.mm");
+
+    #[test]
+    fn show_proof_computes_the_formula_of_each_step() {
+        let (_, document) = db_parser::document::document(DEMO).unwrap();
+        let database = Database::new(&document).unwrap();
+        let listing = show_proof(&database, database.index_of("mp2b").unwrap(), ShowOptions::default()).unwrap();
+
+        let steps: Vec<(usize, &[usize], &str, &str, usize)> = listing
+            .steps
+            .iter()
+            .map(|step| {
+                let hypotheses = step.hypotheses.as_slice();
+                (step.number, hypotheses, step.label.as_str(), step.formula.as_str(), step.depth)
+            })
+            .collect();
+        let expected: [(usize, &[usize], &str, &str, usize); 5] = [
+            (1, &[], "mp2b.1", "|- ph", 2),
+            (2, &[], "mp2b.2", "|- ( ph -> ps )", 2),
+            (3, &[1, 2], "ax-mp", "|- ps", 1),
+            (4, &[], "mp2b.3", "|- ( ps -> ch )", 1),
+            (5, &[3, 4], "ax-mp", "|- ch", 0),
+        ];
+        assert_eq!(steps, expected);
+        let text = listing.to_string();
+        assert_eq!(text.lines().nth(2), Some("3 1,2   ax-mp $a |- ps"));
+        assert_eq!(text.lines().last(), Some("5 3,4 ax-mp $a |- ch"));
+    }
+
+    #[test]
+    fn show_proof_lists_syntax_steps_on_request() {
+        let (_, document) = db_parser::document::document(DEMO).unwrap();
+        let database = Database::new(&document).unwrap();
+        let a1i = database.index_of("a1i").unwrap();
+
+        let listing = show_proof(&database, a1i, ShowOptions { syntax: true }).unwrap();
+        assert_eq!(listing.steps.len(), 9);
+        assert_eq!(listing.steps[3].formula, "wff ( ps -> ph )");
+        assert!(!listing.steps[3].essential);
+        assert_eq!(listing.steps[8].hypotheses, [1, 4, 5, 8]);
+
+        let listing = show_proof(&database, a1i, ShowOptions::default()).unwrap();
+        let labels: Vec<&str> = listing.steps.iter().map(|step| step.label.as_str()).collect();
+        assert_eq!(labels, ["a1i.1", "ax-1", "ax-mp"]);
+        assert_eq!(listing.steps[2].hypotheses, [1, 2]);
+    }
+}
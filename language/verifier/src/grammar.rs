@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use crate::database::{Database, EntryKind};
+use crate::proof::ProofStep;
+
+/// A parsed math string: a variable, or a syntax axiom applied to the trees
+/// substituted for its variables, in the order of its `$f` hypotheses.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SyntaxTree {
+    Variable(String),
+    Node(usize, Vec<SyntaxTree>),
+}
+impl SyntaxTree {
+    /// Number of variables and syntax axioms in the tree.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Variable(_) => 1,
+            Self::Node(_, children) => 1 + children.iter().map(Self::size).sum::<usize>(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum RuleSymbol {
+    Constant(String),
+    /// A variable of the rule, by the typecode it stands for.
+    Variable(String),
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    axiom: usize,
+    symbols: Vec<RuleSymbol>,
+    /// For each `$f` hypothesis of the axiom, the index of the first
+    /// occurrence of its variable among the variables of `symbols`.
+    order: Vec<usize>,
+}
+
+/// The syntax axioms of a database: the `$a` statements whose typecode is the
+/// type of some variable, such as `wff` or `class`.
+#[derive(Debug, Clone, Default)]
+pub struct Grammar {
+    rules: HashMap<String, Vec<Rule>>,
+    /// The typecode of every variable with a `$f` statement.
+    types: HashMap<String, String>,
+    /// For each typecode of provable statements, such as `|-`, the type its
+    /// expressions are parsed as, such as `wff`.
+    statement_types: HashMap<String, String>,
+}
+impl Grammar {
+    pub fn new(database: &Database) -> Self {
+        let mut grammar = Self::default();
+        for entry in database.entries.iter().filter(|entry| entry.kind == EntryKind::FloatingHypothesis) {
+            grammar.types.insert(entry.symbols[1].to_string(), entry.symbols[0].to_string());
+        }
+        for (index, entry) in database.assertions() {
+            let typecode = entry.typecode().to_string();
+            if entry.kind != EntryKind::AxiomaticAssertion || !grammar.types.values().any(|t| *t == typecode) {
+                continue;
+            }
+            let variables: Vec<String> = entry.symbols[1..]
+                .iter()
+                .map(|symbol| symbol.to_string())
+                .filter(|symbol| grammar.types.contains_key(symbol))
+                .collect();
+            let order = entry
+                .frame
+                .hypotheses
+                .iter()
+                .filter_map(|&hypothesis| {
+                    let variable = database.floating_variable(hypothesis).to_string();
+                    variables.iter().position(|v| *v == variable)
+                })
+                .collect();
+            let symbols = entry.symbols[1..]
+                .iter()
+                .map(|symbol| {
+                    let symbol = symbol.to_string();
+                    match grammar.types.get(&symbol) {
+                        Some(typecode) => RuleSymbol::Variable(typecode.clone()),
+                        None => RuleSymbol::Constant(symbol),
+                    }
+                })
+                .collect();
+            grammar.rules.entry(typecode).or_default().push(Rule {
+                axiom: index,
+                symbols,
+                order,
+            });
+        }
+        // A provable typecode takes the first syntax type that one of its
+        // axioms parses as: `|- ps` makes `|-` a `wff`.
+        let mut syntax_types: Vec<String> = grammar.rules.keys().cloned().collect();
+        syntax_types.sort_unstable();
+        for (_, entry) in database.assertions().filter(|(_, entry)| entry.kind == EntryKind::AxiomaticAssertion) {
+            let typecode = entry.typecode().to_string();
+            if grammar.types.values().any(|t| *t == typecode) || grammar.statement_types.contains_key(&typecode) {
+                continue;
+            }
+            let expression: Vec<String> = entry.symbols[1..].iter().map(|symbol| symbol.to_string()).collect();
+            if let Some(syntax_type) = syntax_types.iter().find(|t| grammar.parse(t, &expression).is_some()) {
+                grammar.statement_types.insert(typecode, syntax_type.clone());
+            }
+        }
+        grammar
+    }
+
+    /// The typecode of a variable, if it has a `$f` statement.
+    pub fn variable_type(&self, variable: &str) -> Option<&str> {
+        self.types.get(variable).map(String::as_str)
+    }
+
+    /// The typecode of the expression a tree stands for.
+    pub fn tree_type(&self, database: &Database, tree: &SyntaxTree) -> Option<String> {
+        match tree {
+            SyntaxTree::Variable(variable) => self.types.get(variable).cloned(),
+            SyntaxTree::Node(axiom, _) => Some(database.entries[*axiom].typecode().to_string()),
+        }
+    }
+
+    /// Parses a statement, typecode first. Statements whose typecode is not
+    /// a variable type, such as `|-`, are parsed as the type the axioms with
+    /// that typecode parse as. Fails for such typecodes that no axiom has.
+    pub fn parse_statement(&self, symbols: &[String]) -> Option<SyntaxTree> {
+        let (typecode, expression) = symbols.split_first()?;
+        let typecode = match self.types.values().any(|t| t == typecode) {
+            true => typecode,
+            false => self.statement_types.get(typecode)?,
+        };
+        self.parse(typecode, expression)
+    }
+
+    /// Parses `symbols` as an expression of type `typecode`.
+    pub fn parse(&self, typecode: &str, symbols: &[String]) -> Option<SyntaxTree> {
+        let typecode = self.types.values().find(|t| *t == typecode)?;
+        let mut parser = Parser {
+            grammar: self,
+            symbols,
+            memo: HashMap::new(),
+        };
+        parser
+            .parse(typecode, 0)
+            .into_iter()
+            .find(|(end, _)| *end == symbols.len())
+            .map(|(_, tree)| tree)
+    }
+}
+
+/// Parses of one type from one start position, with the position each ends at.
+type Parses = Vec<(usize, SyntaxTree)>;
+
+/// A memoizing top-down parser that finds, for a type and a start position,
+/// one tree for every position a parse can end at.
+struct Parser<'g, 's> {
+    grammar: &'g Grammar,
+    symbols: &'s [String],
+    /// `None` while the entry is being computed, which cuts off left
+    /// recursion.
+    memo: HashMap<(&'g str, usize), Option<Parses>>,
+}
+impl<'g, 's> Parser<'g, 's> {
+    fn parse(&mut self, typecode: &'g str, start: usize) -> Parses {
+        match self.memo.get(&(typecode, start)) {
+            Some(Some(parses)) => return parses.clone(),
+            Some(None) => return Vec::new(),
+            None => {}
+        }
+        self.memo.insert((typecode, start), None);
+
+        let mut parses: Parses = Vec::new();
+        if let Some(symbol) = self.symbols.get(start) {
+            if self.grammar.types.get(symbol).is_some_and(|t| t == typecode) {
+                parses.push((start + 1, SyntaxTree::Variable(symbol.clone())));
+            }
+        }
+        for rule in self.grammar.rules.get(typecode).into_iter().flatten() {
+            let mut frontier: Vec<(usize, Vec<SyntaxTree>)> = vec![(start, Vec::new())];
+            for symbol in &rule.symbols {
+                let mut next: Vec<(usize, Vec<SyntaxTree>)> = Vec::new();
+                for (position, children) in frontier {
+                    match symbol {
+                        RuleSymbol::Constant(constant) => {
+                            if self.symbols.get(position) == Some(constant) {
+                                next.push((position + 1, children));
+                            }
+                        }
+                        RuleSymbol::Variable(variable_type) => {
+                            for (end, tree) in self.parse(variable_type, position) {
+                                if next.iter().all(|(e, _)| *e != end) {
+                                    let mut children = children.clone();
+                                    children.push(tree);
+                                    next.push((end, children));
+                                }
+                            }
+                        }
+                    }
+                }
+                frontier = next;
+            }
+            for (end, children) in frontier {
+                if parses.iter().all(|(e, _)| *e != end) {
+                    let children = rule.order.iter().map(|&i| children[i].clone()).collect();
+                    parses.push((end, SyntaxTree::Node(rule.axiom, children)));
+                }
+            }
+        }
+        self.memo.insert((typecode, start), Some(parses.clone()));
+        parses
+    }
+}
+
+impl SyntaxTree {
+    /// Appends the steps that build the expression inside the proof of the
+    /// `$p` entry at `theorem`: the `$f` hypotheses in scope there for its
+    /// variables, and its syntax axioms. Fails with a variable that has no
+    /// such hypothesis.
+    pub fn proof(&self, database: &Database, theorem: usize, steps: &mut Vec<ProofStep>) -> Result<(), String> {
+        match self {
+            Self::Variable(name) => {
+                let floating = (0..theorem)
+                    .find(|&index| {
+                        let entry = &database.entries[index];
+                        entry.kind == EntryKind::FloatingHypothesis
+                            && entry.scope_end > theorem
+                            && database.floating_variable(index).to_string() == *name
+                    })
+                    .ok_or_else(|| name.clone())?;
+                steps.push(ProofStep::Entry(floating));
+            }
+            Self::Node(axiom, children) => {
+                for child in children {
+                    child.proof(database, theorem, steps)?;
+                }
+                steps.push(ProofStep::Entry(*axiom));
+            }
+        }
+        Ok(())
+    }
+
+    /// The math string the tree stands for, without a typecode.
+    pub fn symbols(&self, database: &Database) -> Vec<String> {
+        match self {
+            Self::Variable(variable) => vec![variable.clone()],
+            Self::Node(axiom, children) => {
+                let entry = &database.entries[*axiom];
+                let variables: Vec<String> = entry
+                    .frame
+                    .hypotheses
+                    .iter()
+                    .map(|&hypothesis| database.floating_variable(hypothesis).to_string())
+                    .collect();
+                let mut symbols = Vec::new();
+                for symbol in &entry.symbols[1..] {
+                    let symbol = symbol.to_string();
+                    match variables.iter().position(|variable| *variable == symbol) {
+                        Some(i) => symbols.extend(children[i].symbols(database)),
+                        None => symbols.push(symbol),
+                    }
+                }
+                symbols
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    fn statement(text: &str) -> Vec<String> {
+        text.split_ascii_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn provable_typecodes_take_the_type_of_their_axioms() {
+        // The demo with `=>` instead of `|-`.
+        let text = DEMO.replace("|-", "=>");
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let grammar = Grammar::new(&database);
+
+        let wi = database.index_of("wi").unwrap();
+        let expected = SyntaxTree::Node(wi, vec![SyntaxTree::Variable("ph".to_string()); 2]);
+        assert_eq!(grammar.parse_statement(&statement("=> ( ph -> ph )")), Some(expected.clone()));
+        assert_eq!(grammar.parse_statement(&statement("wff ( ph -> ph )")), Some(expected));
+        assert_eq!(grammar.parse_statement(&statement("|- ( ph -> ph )")), None);
+        assert_eq!(grammar.parse_statement(&statement("=> ( ph -> )")), None);
+    }
+}
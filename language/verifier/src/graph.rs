@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+use crate::database::{Database, Entry, EntryKind};
+use crate::trace::{is_definition, references};
+use crate::verify::VerifyError;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GraphOptions {
+    /// Follow proof references at most this many steps from the roots.
+    pub depth: Option<usize>,
+    /// Keep only the roots and the `$a` statements, connecting each to the
+    /// nearest kept statements below it.
+    pub axioms_only: bool,
+    /// Also follow the syntax steps of proofs.
+    pub syntax: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Theorem,
+    Axiom,
+    Definition,
+}
+impl NodeKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Theorem => "theorem",
+            Self::Axiom => "axiom",
+            Self::Definition => "definition",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    pub index: usize,
+    pub label: String,
+    pub kind: NodeKind,
+    /// Index into `DependencyGraph::clusters`.
+    pub cluster: Option<usize>,
+    /// Whether the proof of the node could not be followed, so that the
+    /// node has no edges to what it uses.
+    pub incomplete: bool,
+}
+
+/// The proof-reference graph below a set of assertions. An edge `(a, b)`
+/// means the proof of node `a` uses node `b`.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<(usize, usize)>,
+    /// Cluster names, usually section headings.
+    pub clusters: Vec<String>,
+    /// Why the proofs of incomplete theorems could not be followed, in
+    /// database order, including those `axioms_only` leaves out.
+    pub skipped: Vec<VerifyError>,
+}
+
+/// Builds the graph of the assertions the proofs of `roots` use, directly or
+/// through other theorems. A proof that cannot be followed leaves its theorem
+/// without edges and is listed in `skipped`.
+pub fn dependency_graph(database: &Database, roots: &[usize], options: GraphOptions) -> DependencyGraph {
+    let mut used: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut skipped: BTreeMap<usize, VerifyError> = BTreeMap::new();
+    let mut pending: Vec<(usize, usize)> = roots.iter().map(|&root| (root, 0)).collect();
+    let mut depths: HashMap<usize, usize> = pending.iter().copied().collect();
+    while let Some((index, depth)) = pending.pop() {
+        if used.contains_key(&index) || options.depth.is_some_and(|cap| depth >= cap) {
+            continue;
+        }
+        let references = match database.entries[index].kind {
+            EntryKind::ProvableAssertion => references(database, index, !options.syntax).unwrap_or_else(|error| {
+                skipped.insert(index, error);
+                Vec::new()
+            }),
+            _ => Vec::new(),
+        };
+        for &reference in &references {
+            if depths.get(&reference).is_none_or(|&d| d > depth + 1) {
+                depths.insert(reference, depth + 1);
+                used.remove(&reference);
+                pending.push((reference, depth + 1));
+            }
+        }
+        used.insert(index, references);
+    }
+
+    let keep = |index: usize| !options.axioms_only || roots.contains(&index) || !is_provable(&database.entries[index]);
+    let mut kept: Vec<usize> = depths.keys().copied().filter(|&index| keep(index)).collect();
+    kept.sort_unstable();
+    let ids: HashMap<usize, usize> = kept.iter().enumerate().map(|(id, &index)| (index, id)).collect();
+
+    let mut edges = BTreeSet::new();
+    for &index in &kept {
+        let mut seen = BTreeSet::new();
+        let mut below: Vec<usize> = used.get(&index).cloned().unwrap_or_default();
+        while let Some(reference) = below.pop() {
+            if !seen.insert(reference) {
+                continue;
+            }
+            match ids.get(&reference) {
+                Some(&id) => {
+                    edges.insert((ids[&index], id));
+                }
+                None => below.extend(used.get(&reference).into_iter().flatten()),
+            }
+        }
+    }
+
+    let nodes = kept
+        .iter()
+        .map(|&index| GraphNode {
+            index,
+            label: database.entries[index].label.to_string(),
+            kind: if is_provable(&database.entries[index]) {
+                NodeKind::Theorem
+            } else if is_definition(database, index) {
+                NodeKind::Definition
+            } else {
+                NodeKind::Axiom
+            },
+            cluster: None,
+            incomplete: skipped.contains_key(&index),
+        })
+        .collect();
+    DependencyGraph {
+        nodes,
+        edges: edges.into_iter().collect(),
+        clusters: Vec::new(),
+        skipped: skipped.into_values().collect(),
+    }
+}
+
+fn is_provable(entry: &Entry) -> bool {
+    entry.kind == EntryKind::ProvableAssertion
+}
+
+impl DependencyGraph {
+    /// Groups the nodes by the name `cluster` gives their entry index, such
+    /// as the heading the statement appears under.
+    pub fn cluster_by(&mut self, mut cluster: impl FnMut(usize) -> Option<String>) {
+        self.clusters.clear();
+        for node in &mut self.nodes {
+            node.cluster = cluster(node.index).map(|name| match self.clusters.iter().position(|c| *c == name) {
+                Some(position) => position,
+                None => {
+                    self.clusters.push(name);
+                    self.clusters.len() - 1
+                }
+            });
+        }
+    }
+
+    /// Graphviz DOT, with one `cluster_` subgraph per cluster.
+    pub fn to_dot(&self) -> String {
+        let mut o = String::from("digraph dependencies {\n  rankdir=BT;\n  node [shape=ellipse];\n");
+        let node = |o: &mut String, indent: &str, node: &GraphNode| {
+            let shape = match node.kind {
+                NodeKind::Theorem if node.incomplete => " [style=dashed]",
+                NodeKind::Theorem => "",
+                NodeKind::Axiom => " [shape=box]",
+                NodeKind::Definition => " [shape=box, style=rounded]",
+            };
+            writeln!(o, "{indent}{}{shape};", dot_id(&node.label)).unwrap();
+        };
+        for (id, name) in self.clusters.iter().enumerate() {
+            writeln!(o, "  subgraph cluster_{id} {{\n    label={};", dot_id(name)).unwrap();
+            for n in self.nodes.iter().filter(|n| n.cluster == Some(id)) {
+                node(&mut o, "    ", n);
+            }
+            o.push_str("  }\n");
+        }
+        for n in self.nodes.iter().filter(|n| n.cluster.is_none()) {
+            node(&mut o, "  ", n);
+        }
+        for &(from, to) in &self.edges {
+            writeln!(o, "  {} -> {};", dot_id(&self.nodes[from].label), dot_id(&self.nodes[to].label)).unwrap();
+        }
+        o.push_str("}\n");
+        o
+    }
+
+    /// GraphML, with the label, kind and cluster of each node as data.
+    pub fn to_graphml(&self) -> String {
+        let mut o = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n",
+            "  <key id=\"kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+            "  <key id=\"cluster\" for=\"node\" attr.name=\"cluster\" attr.type=\"string\"/>\n",
+            "  <key id=\"incomplete\" for=\"node\" attr.name=\"incomplete\" attr.type=\"boolean\"/>\n",
+            "  <graph id=\"dependencies\" edgedefault=\"directed\">\n",
+        ));
+        for (id, node) in self.nodes.iter().enumerate() {
+            writeln!(o, "    <node id=\"n{id}\">").unwrap();
+            writeln!(o, "      <data key=\"label\">{}</data>", escape_xml(&node.label)).unwrap();
+            writeln!(o, "      <data key=\"kind\">{}</data>", node.kind.name()).unwrap();
+            if let Some(cluster) = node.cluster {
+                writeln!(o, "      <data key=\"cluster\">{}</data>", escape_xml(&self.clusters[cluster])).unwrap();
+            }
+            if node.incomplete {
+                o.push_str("      <data key=\"incomplete\">true</data>\n");
+            }
+            o.push_str("    </node>\n");
+        }
+        for (id, &(from, to)) in self.edges.iter().enumerate() {
+            writeln!(o, "    <edge id=\"e{id}\" source=\"n{from}\" target=\"n{to}\"/>").unwrap();
+        }
+        o.push_str("  </graph>\n</graphml>\n");
+        o
+    }
+}
+
+fn dot_id(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify::VerifyErrorKind;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    fn graph(text: &str, roots: &[&str], options: GraphOptions) -> DependencyGraph {
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let roots: Vec<usize> = roots.iter().map(|label| database.index_of(label).unwrap()).collect();
+        let mut graph = dependency_graph(&database, &roots, options);
+        graph.cluster_by(|index| match database.entries[index].kind {
+            EntryKind::ProvableAssertion => Some("Theorems".to_string()),
+            _ => None,
+        });
+        graph
+    }
+
+    #[test]
+    fn dot_output_has_clusters_shapes_and_edges() {
+        let dot = graph(DEMO, &["a1i", "mp2b"], GraphOptions::default()).to_dot();
+        let expected = [
+            "digraph dependencies {",
+            "  rankdir=BT;",
+            "  node [shape=ellipse];",
+            "  subgraph cluster_0 {",
+            "    label=\"Theorems\";",
+            "    \"a1i\";",
+            "    \"mp2b\";",
+            "  }",
+            "  \"ax-mp\" [shape=box];",
+            "  \"ax-1\" [shape=box];",
+            "  \"a1i\" -> \"ax-mp\";",
+            "  \"a1i\" -> \"ax-1\";",
+            "  \"mp2b\" -> \"ax-mp\";",
+            "}",
+        ];
+        assert_eq!(dot, expected.map(|line| line.to_string() + "\n").concat());
+    }
+
+    #[test]
+    fn graphml_output_has_node_data_and_edges() {
+        let options = GraphOptions {
+            syntax: true,
+            ..Default::default()
+        };
+        let graphml = graph(DEMO, &["a1i"], options).to_graphml();
+        assert!(graphml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml "));
+        let labels: Vec<&str> = graphml
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("<data key=\"label\">")?.strip_suffix("</data>"))
+            .collect();
+        assert_eq!(labels, ["wi", "ax-mp", "ax-1", "a1i"]);
+        assert!(graphml.contains("    <node id=\"n3\">\n      <data key=\"label\">a1i</data>\n      \
+                                  <data key=\"kind\">theorem</data>\n      <data key=\"cluster\">Theorems</data>\n"));
+        for (id, target) in [(0, 0), (1, 1), (2, 2)] {
+            let edge = format!("    <edge id=\"e{id}\" source=\"n3\" target=\"n{target}\"/>\n");
+            assert!(graphml.contains(&edge), "{edge}");
+        }
+        assert!(!graphml.contains("<data key=\"incomplete\">"));
+        assert!(graphml.ends_with("  </graph>\n</graphml>\n"));
+    }
+
+    #[test]
+    fn an_incomplete_proof_only_loses_its_own_edges() {
+        let text = DEMO
+            .replacen("$= ( ax-mp ) BCABDEGFG $.", "$= ? $.", 1)
+            .replacen("$= ( ax-mp ) BCABDEGFG $.", "$= ( mp2b ) ABCDEFG $.", 1);
+        let graph = graph(&text, &["mp2c", "a1i"], GraphOptions::default());
+        let nodes: Vec<(&str, bool)> = graph.nodes.iter().map(|node| (node.label.as_str(), node.incomplete)).collect();
+        let expected = [("ax-mp", false), ("ax-1", false), ("a1i", false), ("mp2b", true), ("mp2c", false)];
+        assert_eq!(nodes, expected);
+        assert_eq!(graph.edges.len(), 3);
+        assert_eq!(graph.skipped.len(), 1);
+        assert_eq!(graph.skipped[0].label, "mp2b");
+        assert_eq!(graph.skipped[0].kind, VerifyErrorKind::IncompleteProof);
+        assert!(graph.to_dot().contains("    \"mp2b\" [style=dashed];\n"));
+        assert!(graph.to_graphml().contains("      <data key=\"incomplete\">true</data>\n"));
+    }
+
+    #[test]
+    fn axioms_only_connects_roots_to_the_axioms_below_them() {
+        let mp2c = "mp2c $p |- ch $= ( ax-mp ) BCABDEGFG $.";
+        let text = DEMO.replacen(mp2c, "mp2c $p |- ch $= ( mp2b ) ABCDEFG $.", 1);
+        let options = GraphOptions {
+            axioms_only: true,
+            ..Default::default()
+        };
+        let axioms_only = graph(&text, &["mp2c"], options);
+        let labels: Vec<&str> = axioms_only.nodes.iter().map(|node| node.label.as_str()).collect();
+        assert_eq!(labels, ["ax-mp", "mp2c"]);
+        assert_eq!(axioms_only.edges, [(1, 0)]);
+
+        let options = GraphOptions {
+            depth: Some(1),
+            ..Default::default()
+        };
+        let shallow = graph(&text, &["mp2c"], options);
+        let labels: Vec<&str> = shallow.nodes.iter().map(|node| node.label.as_str()).collect();
+        assert_eq!(labels, ["mp2b", "mp2c"]);
+    }
+}
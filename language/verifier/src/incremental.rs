@@ -0,0 +1,153 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::database::{Database, EntryKind};
+use crate::verify::{verify, VerifyError, VerifyErrorKind};
+
+/// The results of checking proofs, kept between versions of a database so
+/// that only the proofs affected by an edit are checked again.
+///
+/// A result is reused while the fingerprint of its theorem is unchanged: the
+/// theorem's statement, frame and proof, what each label of the proof refers
+/// to, including the frames of the assertions it uses but not their proofs,
+/// and the set of variables.
+#[derive(Debug, Clone, Default)]
+pub struct ProofCache {
+    results: HashMap<String, (u64, Result<(), VerifyErrorKind>)>,
+    checked: usize,
+}
+impl ProofCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks every proof whose fingerprint changed since the last call and
+    /// returns the failures among all proofs, as `verify_all` does.
+    pub fn verify_all(&mut self, database: &Database) -> Vec<VerifyError> {
+        let mut variables: Vec<String> = database.variables.iter().map(|v| v.to_string()).collect();
+        variables.sort();
+        let mut hasher = DefaultHasher::new();
+        variables.hash(&mut hasher);
+        let variables = hasher.finish();
+
+        let mut results = HashMap::new();
+        let mut failures = Vec::new();
+        self.checked = 0;
+        for (index, entry) in database.assertions() {
+            if entry.kind != EntryKind::ProvableAssertion {
+                continue;
+            }
+            let label = entry.label.to_string();
+            let fingerprint = fingerprint(database, index, variables);
+            let result = match self.results.remove(&label) {
+                Some((previous, result)) if previous == fingerprint => result,
+                _ => {
+                    self.checked += 1;
+                    verify(database, index).map_err(|error| error.kind)
+                }
+            };
+            if let Err(kind) = &result {
+                failures.push(VerifyError {
+                    label: label.clone(),
+                    kind: kind.clone(),
+                });
+            }
+            results.insert(label, (fingerprint, result));
+        }
+        self.results = results;
+        failures
+    }
+
+    /// Number of proofs the last `verify_all` checked rather than reused.
+    pub fn checked(&self) -> usize {
+        self.checked
+    }
+}
+
+/// Hashes what checking the proof of the theorem at `index` depends on.
+fn fingerprint(database: &Database, index: usize, variables: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    variables.hash(&mut hasher);
+    interface(database, index, &mut hasher);
+    let entry = &database.entries[index];
+    if let Some(proof) = &entry.proof {
+        for label in &proof.labels {
+            label.to_string().hash(&mut hasher);
+            match database.labels.get(label) {
+                Some(&referenced) => {
+                    let usable = match database.entries[referenced].is_hypothesis() {
+                        true => referenced < index && database.entries[referenced].scope_end > index,
+                        false => referenced < index,
+                    };
+                    usable.hash(&mut hasher);
+                    interface(database, referenced, &mut hasher);
+                }
+                None => None::<()>.hash(&mut hasher),
+            }
+        }
+        for compressed in &proof.compressed_proofs {
+            compressed.0.as_bytes().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Hashes what a proof using the entry at `index` sees of it: its statement,
+/// and for an assertion its mandatory hypotheses and `$d` pairs.
+fn interface(database: &Database, index: usize, hasher: &mut DefaultHasher) {
+    let entry = &database.entries[index];
+    entry.kind.hash(hasher);
+    for symbol in &entry.symbols {
+        symbol.0.as_bytes().hash(hasher);
+    }
+    for &hypothesis in &entry.frame.hypotheses {
+        let hypothesis = &database.entries[hypothesis];
+        hypothesis.label.0.as_bytes().hash(hasher);
+        hypothesis.kind.hash(hasher);
+        for symbol in &hypothesis.symbols {
+            symbol.0.as_bytes().hash(hasher);
+        }
+    }
+    for (x, y) in &entry.frame.disjoint {
+        (x.0.as_bytes(), y.0.as_bytes()).hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    fn verify_all(proofs: &mut ProofCache, text: &str) -> Vec<VerifyError> {
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        proofs.verify_all(&database)
+    }
+
+    #[test]
+    fn only_proofs_affected_by_an_edit_are_checked_again() {
+        let mut proofs = ProofCache::new();
+        assert_eq!(verify_all(&mut proofs, DEMO), []);
+        assert_eq!(proofs.checked(), 4);
+        assert_eq!(verify_all(&mut proofs, DEMO), []);
+        assert_eq!(proofs.checked(), 0);
+
+        let comment = DEMO.replace("Axiom _Simp_.", "Axiom _Simp_, with a longer comment.");
+        assert_eq!(verify_all(&mut proofs, &comment), []);
+        assert_eq!(proofs.checked(), 0);
+
+        let broken = DEMO.replace("$= ( ax-mp ) ABCDE $.", "$= ( ax-mp ) BACDE $.");
+        let failures = verify_all(&mut proofs, &broken);
+        assert_eq!(proofs.checked(), 1);
+        let labels: Vec<&str> = failures.iter().map(|error| error.label.as_str()).collect();
+        assert_eq!(labels, ["mp1"]);
+
+        let axiom = broken.replace("ax-1 $a |- ( ph -> ( ps -> ph ) ) $.", "ax-1 $a |- ( ph -> ( ps -> ps ) ) $.");
+        let failures = verify_all(&mut proofs, &axiom);
+        assert_eq!(proofs.checked(), 1);
+        let labels: Vec<&str> = failures.iter().map(|error| error.label.as_str()).collect();
+        assert_eq!(labels, ["a1i", "mp1"]);
+    }
+}
@@ -0,0 +1,17 @@
+pub mod assistant;
+pub mod compress;
+pub mod database;
+pub mod diff;
+pub mod discouraged;
+pub mod display;
+pub mod grammar;
+pub mod graph;
+pub mod incremental;
+pub mod mathbox;
+pub mod minimize;
+pub mod proof;
+pub mod search;
+pub mod trace;
+pub mod unify;
+pub mod usage;
+pub mod verify;
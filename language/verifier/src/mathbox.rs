@@ -0,0 +1,168 @@
+use std::fmt::Display;
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::database::{Database, EntryKind};
+use crate::usage::UsageIndex;
+
+/// The statements one user keeps apart from the main body of a database.
+#[derive(Debug, Clone)]
+pub struct Mathbox {
+    pub owner: String,
+    /// Bytes of the database text the mathbox spans.
+    pub range: Range<usize>,
+}
+
+/// The mathbox of every entry, `None` for the main body.
+pub fn owners(database: &Database, input: &[u8], mathboxes: &[Mathbox]) -> Vec<Option<usize>> {
+    database
+        .entries
+        .iter()
+        .map(|entry| {
+            let offset = entry.label.0.offset_in(input);
+            mathboxes.iter().position(|mathbox| mathbox.range.contains(&offset))
+        })
+        .collect()
+}
+
+/// A proof using an assertion from a mathbox other than its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrossReference {
+    pub user: String,
+    /// Owner of the mathbox of the proof, `None` for the main body.
+    pub user_owner: Option<String>,
+    pub used: String,
+    pub used_owner: String,
+}
+impl Display for CrossReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.user_owner {
+            Some(owner) => write!(f, "\"{}\" in the mathbox of {owner}", self.user)?,
+            None => write!(f, "\"{}\" in the main body", self.user)?,
+        }
+        write!(f, " uses \"{}\" from the mathbox of {}", self.used, self.used_owner)
+    }
+}
+
+/// Finds the proofs that reference an assertion in another mathbox, or in
+/// any mathbox from the main body. `owners` is as `owners` returns it.
+pub fn cross_references(
+    database: &Database,
+    usage: &UsageIndex,
+    owners: &[Option<usize>],
+    mathboxes: &[Mathbox],
+) -> Vec<CrossReference> {
+    let mut references = Vec::new();
+    for (used, _) in database.assertions() {
+        let Some(used_owner) = owners[used] else {
+            continue;
+        };
+        for &user in usage.direct(used) {
+            if owners[user] != Some(used_owner) {
+                references.push(CrossReference {
+                    user: database.entries[user].label.to_string(),
+                    user_owner: owners[user].map(|owner| mathboxes[owner].owner.clone()),
+                    used: database.entries[used].label.to_string(),
+                    used_owner: mathboxes[used_owner].owner.clone(),
+                });
+            }
+        }
+    }
+    references.sort_by(|a, b| (&a.user, &a.used).cmp(&(&b.user, &b.used)));
+    references
+}
+
+/// A mathbox theorem whose proof uses the main body only, so that it could
+/// move there as it is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Movable {
+    pub label: String,
+    pub owner: String,
+    /// Proofs outside the mathbox that use it.
+    pub users_outside: Vec<String>,
+}
+impl Display for Movable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\" in the mathbox of {}", self.label, self.owner)?;
+        if !self.users_outside.is_empty() {
+            write!(f, " is used by \"{}\"", self.users_outside.join("\", \""))?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds the theorems in mathboxes whose proofs reference no mathbox
+/// assertion. Those used outside their mathbox come first.
+pub fn movable(database: &Database, usage: &UsageIndex, owners: &[Option<usize>], mathboxes: &[Mathbox]) -> Vec<Movable> {
+    let mut movable = Vec::new();
+    for (index, entry) in database.assertions() {
+        let (Some(owner), Some(proof)) = (owners[index], &entry.proof) else {
+            continue;
+        };
+        if entry.kind != EntryKind::ProvableAssertion {
+            continue;
+        }
+        let main_body_only = proof
+            .labels
+            .iter()
+            .filter_map(|label| database.labels.get(label))
+            .all(|&used| database.entries[used].is_hypothesis() || owners[used].is_none());
+        if main_body_only {
+            movable.push(Movable {
+                label: entry.label.to_string(),
+                owner: mathboxes[owner].owner.clone(),
+                users_outside: usage
+                    .direct(index)
+                    .iter()
+                    .filter(|&&user| owners[user] != Some(owner))
+                    .map(|&user| database.entries[user].label.to_string())
+                    .collect(),
+            });
+        }
+    }
+    movable.sort_by_key(|theorem| theorem.users_outside.is_empty());
+    movable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    #[test]
+    fn references_across_mathboxes() {
+        // mp2b in Alice's mathbox, mp2c, proved with mp2b, in Bob's.
+        let text = DEMO.replace("mp2c $p |- ch $= ( ax-mp ) BCABDEGFG $.", "mp2c $p |- ch $= ( mp2b ) ABCDEFG $.");
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let block = |label: &str| {
+            let start = text.find(&format!("{label}.1 $e")).unwrap();
+            start..start + text[start..].find("$}").unwrap()
+        };
+        let mathboxes = [
+            Mathbox {
+                owner: "Alice".to_string(),
+                range: block("mp2b"),
+            },
+            Mathbox {
+                owner: "Bob".to_string(),
+                range: block("mp2c"),
+            },
+        ];
+        let usage = UsageIndex::new(&database);
+        let owners = owners(&database, text.as_bytes(), &mathboxes);
+        assert_eq!(owners[database.index_of("mp2b").unwrap()], Some(0));
+        assert_eq!(owners[database.index_of("ax-mp").unwrap()], None);
+
+        let references = cross_references(&database, &usage, &owners, &mathboxes);
+        assert_eq!(
+            references.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [r#""mp2c" in the mathbox of Bob uses "mp2b" from the mathbox of Alice"#]
+        );
+        let movable = movable(&database, &usage, &owners, &mathboxes);
+        assert_eq!(movable[0].label, "mp2b");
+        assert_eq!(movable[0].users_outside, ["mp2c"]);
+    }
+}
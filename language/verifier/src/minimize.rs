@@ -0,0 +1,406 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::compress::{compress, EncodedProof};
+use crate::database::{Database, EntryKind};
+use crate::discouraged::{has_tag, NEW_USAGE, PROOF_MODIFICATION};
+use crate::grammar::{Grammar, SyntaxTree};
+use crate::proof::{decode, proof_graph, Node, ProofStep};
+use crate::unify::{Term, Unifier};
+use crate::verify::{substitute, substitution, verify, Expression, Stack, VerifyError};
+
+/// A proof made shorter by `minimize_with`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Minimized<'a> {
+    pub label: String,
+    /// Length in bytes of the compressed proof before and after, without
+    /// line breaks.
+    pub before: usize,
+    pub after: usize,
+    /// The assertions the shorter proof applies in place of subproofs.
+    pub used: Vec<String>,
+    #[serde(skip)]
+    pub proof: EncodedProof<'a>,
+}
+
+/// Shortens proofs by replacing subproofs with a single application of an
+/// earlier assertion, as metamath's `minimize_with` does.
+pub struct Minimizer<'d> {
+    database: &'d Database<'d>,
+    grammar: &'d Grammar,
+    /// The typecodes of variables: steps with these typecodes only build
+    /// syntax.
+    syntax_types: HashSet<String>,
+    /// Parsed statements by entry.
+    trees: HashMap<usize, Option<SyntaxTree>>,
+}
+impl<'d> Minimizer<'d> {
+    pub fn new(database: &'d Database<'d>, grammar: &'d Grammar) -> Self {
+        let syntax_types = database
+            .entries
+            .iter()
+            .filter(|entry| entry.kind == EntryKind::FloatingHypothesis)
+            .map(|entry| entry.symbols[0].to_string())
+            .collect();
+        Self {
+            database,
+            grammar,
+            syntax_types,
+            trees: HashMap::new(),
+        }
+    }
+
+    /// Tries every assertion `with` accepts on every essential subproof of
+    /// the `$p` entry at `theorem`. A subproof is replaced when the
+    /// assertion proves the same statement from `$e` hypotheses that other
+    /// steps of the proof already prove, and the compressed proof gets
+    /// shorter.
+    ///
+    /// Assertions tagged as discouraged for new use are not tried unless the
+    /// theorem carries the tag itself, and theorems whose proof modification
+    /// is discouraged are left alone. Returns `None` when nothing got shorter,
+    /// and the error when the proof does not check.
+    pub fn minimize_with(
+        &mut self,
+        theorem: usize,
+        with: impl Fn(usize) -> bool,
+    ) -> Result<Option<Minimized<'d>>, VerifyError> {
+        let database = self.database;
+        if has_tag(database, theorem, PROOF_MODIFICATION) {
+            return Ok(None);
+        }
+        let label = database.entries[theorem].label.to_string();
+        let error = |kind| VerifyError {
+            label: label.clone(),
+            kind,
+        };
+        verify(database, theorem)?;
+        let mut steps = decode(database, theorem)?;
+        let mut best = compress(database, theorem, &steps)?;
+        let before = best.to_string().len();
+        let discouraged = has_tag(database, theorem, NEW_USAGE);
+        let candidates: Vec<usize> = database
+            .assertions()
+            .map(|(index, _)| index)
+            .take_while(|&index| index < theorem)
+            .filter(|&index| with(index) && (discouraged || !has_tag(database, index, NEW_USAGE)))
+            .collect();
+
+        let mut used = Vec::new();
+        for assertion in candidates {
+            // Replacing one subproof changes the others, so the proof is
+            // taken apart again after every change.
+            'replaced: loop {
+                let proof = Proof::new(self, theorem, &steps).map_err(error)?;
+                for node in proof.essential() {
+                    let Some(replaced) = self.replace(&proof, node, assertion) else {
+                        continue;
+                    };
+                    let Ok(shorter) = compress(database, theorem, &replaced) else {
+                        continue;
+                    };
+                    if shorter.to_string().len() < best.to_string().len() {
+                        steps = replaced;
+                        best = shorter;
+                        used.push(database.entries[assertion].label.to_string());
+                        continue 'replaced;
+                    }
+                }
+                break;
+            }
+        }
+        if used.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Minimized {
+            label,
+            before,
+            after: best.to_string().len(),
+            used,
+            proof: best,
+        }))
+    }
+
+    /// The steps of the proof with the subproof at `node` replaced by
+    /// `assertion`, if it fits and the result checks.
+    fn replace(&mut self, proof: &Proof, node: usize, assertion: usize) -> Option<Vec<ProofStep>> {
+        let database = self.database;
+        let (entry, _) = proof.nodes[node];
+        let used = &database.entries[assertion];
+        if entry == assertion || used.typecode().to_string() != proof.expressions[node][0] {
+            return None;
+        }
+        let conclusion = self.tree(assertion)?;
+        let target = proof.tree(self.grammar, node)?;
+        if let (SyntaxTree::Node(f, _), SyntaxTree::Node(g, _)) = (&conclusion, &target) {
+            if f != g {
+                return None;
+            }
+        }
+
+        let mut unifier = Unifier::new(database);
+        let mut fixed = HashMap::new();
+        let mut ids = HashMap::new();
+        let target = unifier.fixed_term(self.grammar, &target, &mut fixed);
+        let conclusion = unifier.term(self.grammar, &conclusion, &mut ids);
+        if !unifier.unify(&conclusion, &target) {
+            return None;
+        }
+
+        // Each `$e` hypothesis is matched to a step that does not depend on
+        // the one replaced.
+        let mut hypotheses = Vec::new();
+        for &hypothesis in &used.frame.hypotheses {
+            if database.entries[hypothesis].kind == EntryKind::EssentialHypothesis {
+                let tree = self.tree(hypothesis)?;
+                let typecode = database.entries[hypothesis].typecode().to_string();
+                hypotheses.push((typecode, unifier.term(self.grammar, &tree, &mut ids)));
+            }
+        }
+        let users = proof.users(node);
+        let available: Vec<usize> = proof.essential().into_iter().filter(|&other| !users[other]).collect();
+        let mut matched = Vec::new();
+        if !proof.match_hypotheses(
+            self.grammar,
+            &mut unifier,
+            &mut fixed,
+            &hypotheses,
+            &available,
+            &mut matched,
+        ) {
+            return None;
+        }
+
+        let mut derivation = Vec::new();
+        let mut essential = matched.into_iter();
+        for &hypothesis in &used.frame.hypotheses {
+            match database.entries[hypothesis].kind {
+                EntryKind::FloatingHypothesis => {
+                    let variable = ids.get(&database.floating_variable(hypothesis).to_string())?;
+                    let term = Term::Variable(*variable);
+                    if !is_ground(&unifier, &term) {
+                        return None;
+                    }
+                    derivation.push(Replacement::Syntax(unifier.resolve(&term)));
+                }
+                _ => derivation.push(Replacement::Node(essential.next()?)),
+            }
+        }
+
+        let mut steps = Vec::new();
+        let mut saved = HashMap::new();
+        proof.emit(proof.root, (node, assertion, &derivation), &mut saved, &mut steps)?;
+        let mut stack = Stack::new(database, proof.theorem);
+        for &step in &steps {
+            stack.step(step).ok()?;
+        }
+        (stack.expressions.as_slice() == [database.entries[proof.theorem].symbols.clone()]).then_some(steps)
+    }
+
+    /// The parsed statement of the entry at `index`.
+    fn tree(&mut self, index: usize) -> Option<SyntaxTree> {
+        let (database, grammar) = (self.database, self.grammar);
+        self.trees
+            .entry(index)
+            .or_insert_with(|| {
+                let symbols: Vec<String> = database.entries[index].symbols.iter().map(|s| s.to_string()).collect();
+                grammar.parse_statement(&symbols)
+            })
+            .clone()
+    }
+}
+
+/// What a replaced subproof applies its assertion to.
+enum Replacement {
+    /// The expression substituted for a variable.
+    Syntax(SyntaxTree),
+    /// A node of the proof proving a `$e` hypothesis.
+    Node(usize),
+}
+
+/// A proof taken apart into a graph of subproofs, with what each proves.
+struct Proof<'d> {
+    database: &'d Database<'d>,
+    theorem: usize,
+    nodes: Vec<Node>,
+    root: usize,
+    expressions: Vec<Vec<String>>,
+    essential: Vec<bool>,
+}
+impl<'d> Proof<'d> {
+    fn new(
+        minimizer: &Minimizer<'d>,
+        theorem: usize,
+        steps: &[ProofStep],
+    ) -> Result<Self, crate::verify::VerifyErrorKind> {
+        let database = minimizer.database;
+        let (nodes, root) = proof_graph(database, steps)?;
+        // Children come before the nodes that use them.
+        let mut expressions: Vec<Expression> = Vec::new();
+        for (entry, children) in &nodes {
+            let used = &database.entries[*entry];
+            let expression = if used.is_hypothesis() {
+                used.symbols.clone()
+            } else {
+                let hypotheses: Vec<Expression> = children.iter().map(|&child| expressions[child].clone()).collect();
+                substitute(
+                    database,
+                    &used.symbols,
+                    &substitution(database, &used.frame.hypotheses, &hypotheses)?,
+                )
+            };
+            expressions.push(expression);
+        }
+        let expressions: Vec<Vec<String>> = expressions
+            .iter()
+            .map(|expression| expression.iter().map(|symbol| symbol.to_string()).collect())
+            .collect();
+        let essential = expressions
+            .iter()
+            .map(|expression| !minimizer.syntax_types.contains(&expression[0]))
+            .collect();
+        Ok(Self {
+            database,
+            theorem,
+            nodes,
+            root,
+            expressions,
+            essential,
+        })
+    }
+
+    /// The nodes proving a statement rather than building syntax, children
+    /// first.
+    fn essential(&self) -> Vec<usize> {
+        (0..self.nodes.len()).filter(|&node| self.essential[node]).collect()
+    }
+
+    /// For every node, whether it is `node` or uses it somewhere in its
+    /// subproof.
+    fn users(&self, node: usize) -> Vec<bool> {
+        let mut users = vec![false; self.nodes.len()];
+        for (i, (_, children)) in self.nodes.iter().enumerate() {
+            users[i] = i == node || children.iter().any(|&child| users[child]);
+        }
+        users
+    }
+
+    fn tree(&self, grammar: &Grammar, node: usize) -> Option<SyntaxTree> {
+        grammar.parse_statement(&self.expressions[node])
+    }
+
+    /// Matches each hypothesis, in order, to one of the `available` nodes,
+    /// backtracking over the choices.
+    fn match_hypotheses(
+        &self,
+        grammar: &Grammar,
+        unifier: &mut Unifier,
+        fixed: &mut HashMap<String, usize>,
+        hypotheses: &[(String, Term)],
+        available: &[usize],
+        matched: &mut Vec<usize>,
+    ) -> bool {
+        let Some(((typecode, hypothesis), rest)) = hypotheses.split_first() else {
+            return true;
+        };
+        for &node in available {
+            if self.expressions[node][0] != *typecode {
+                continue;
+            }
+            let Some(tree) = self.tree(grammar, node) else {
+                continue;
+            };
+            // `fixed_term` may add variables, so both go back on a failed
+            // match.
+            let before = (unifier.clone(), fixed.clone());
+            let term = unifier.fixed_term(grammar, &tree, fixed);
+            if unifier.unify(hypothesis, &term) {
+                matched.push(node);
+                if self.match_hypotheses(grammar, unifier, fixed, rest, available, matched) {
+                    return true;
+                }
+                matched.pop();
+            }
+            (*unifier, *fixed) = before;
+        }
+        false
+    }
+
+    /// Appends the steps of the subproof at `node`, with the node
+    /// `replaced.0` proved by the assertion `replaced.1` applied to
+    /// `replaced.2`. Subproofs are written once and saved for reuse.
+    fn emit(
+        &self,
+        node: usize,
+        replaced: (usize, usize, &[Replacement]),
+        saved: &mut HashMap<usize, usize>,
+        steps: &mut Vec<ProofStep>,
+    ) -> Option<()> {
+        if let Some(&n) = saved.get(&node) {
+            steps.push(ProofStep::Saved(n));
+            return Some(());
+        }
+        let (entry, children) = &self.nodes[node];
+        if node == replaced.0 {
+            for replacement in replaced.2 {
+                match replacement {
+                    Replacement::Syntax(tree) => tree.proof(self.database, self.theorem, steps).ok()?,
+                    Replacement::Node(child) => self.emit(*child, replaced, saved, steps)?,
+                }
+            }
+            steps.push(ProofStep::Entry(replaced.1));
+        } else {
+            for &child in children {
+                self.emit(child, replaced, saved, steps)?;
+            }
+            steps.push(ProofStep::Entry(*entry));
+        }
+        if !children.is_empty() || node == replaced.0 {
+            steps.push(ProofStep::Save);
+            saved.insert(node, saved.len());
+        }
+        Some(())
+    }
+}
+
+/// Whether a term has no variables left that unification could bind.
+fn is_ground(unifier: &Unifier, term: &Term) -> bool {
+    match unifier.walk(term) {
+        Term::Variable(v) => unifier.is_fixed(v),
+        Term::Node(_, children) => children.iter().all(|child| is_ground(unifier, child)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &[u8] = include_bytes!("../../fixtures/demo.mm");
+
+    #[test]
+    fn minimize_with_replaces_a_subproof_by_an_earlier_theorem() {
+        let (_, document) = db_parser::document::document(DEMO).unwrap();
+        let database = Database::new(&document).unwrap();
+        let grammar = Grammar::new(&database);
+        let mut minimizer = Minimizer::new(&database, &grammar);
+        let theorem = database.index_of("mp2c").unwrap();
+
+        let minimized = minimizer.minimize_with(theorem, |_| true).unwrap().unwrap();
+        assert_eq!(minimized.proof.to_string(), "( mp2b ) ABCDEFG");
+        assert_eq!(minimized.used, ["mp2b"]);
+        assert_eq!((minimized.before, minimized.after), (19, 16));
+    }
+
+    #[test]
+    fn minimize_with_leaves_a_proof_alone_without_shorter_candidates() {
+        let (_, document) = db_parser::document::document(DEMO).unwrap();
+        let database = Database::new(&document).unwrap();
+        let grammar = Grammar::new(&database);
+        let mut minimizer = Minimizer::new(&database, &grammar);
+        let theorem = database.index_of("mp2c").unwrap();
+
+        let mp2b = database.index_of("mp2b").unwrap();
+        assert!(minimizer.minimize_with(theorem, |index| index != mp2b).unwrap().is_none());
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use crate::database::Database;
+use crate::verify::{VerifyError, VerifyErrorKind};
+
+/// One element of a compressed proof, in the order it appears.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProofStep {
+    /// Apply the entry with this index: push a hypothesis or use an assertion.
+    Entry(usize),
+    /// Reuse the n-th subproof marked with `Z`, counting from zero.
+    Saved(usize),
+    /// `Z`: remember the result of the previous step.
+    Save,
+    /// `?`: a step not proved yet.
+    Unknown,
+}
+
+/// Decodes the compressed proof of the `$p` entry at `index`.
+///
+/// Numbers 1..=m refer to the m mandatory hypotheses, the next n to the
+/// parenthesized label list, and anything above that to saved subproofs.
+pub fn decode(database: &Database, index: usize) -> Result<Vec<ProofStep>, VerifyError> {
+    let entry = &database.entries[index];
+    let error = |kind| VerifyError {
+        label: entry.label.to_string(),
+        kind,
+    };
+    let proof = entry.proof.as_ref().ok_or_else(|| error(VerifyErrorKind::MissingProof))?;
+
+    let mut references = entry.frame.hypotheses.clone();
+    for label in &proof.labels {
+        let referenced = database
+            .labels
+            .get(label)
+            .copied()
+            .ok_or_else(|| error(VerifyErrorKind::UnknownLabel(label.to_string())))?;
+        references.push(referenced);
+    }
+
+    proof
+        .compressed_proofs
+        .iter()
+        .map(|compressed| match compressed.number() {
+            None if compressed.is_unknown() => Ok(ProofStep::Unknown),
+            None => Ok(ProofStep::Save),
+            Some(number) if number <= references.len() => Ok(ProofStep::Entry(references[number - 1])),
+            Some(number) => Ok(ProofStep::Saved(number - references.len() - 1)),
+        })
+        .collect()
+}
+
+/// An entry and the nodes proving its mandatory hypotheses.
+pub type Node = (usize, Vec<usize>);
+
+/// Rebuilds the proof as a graph in which equal subproofs are one node, and
+/// returns the nodes with the index of the root.
+pub fn proof_graph(database: &Database, steps: &[ProofStep]) -> Result<(Vec<Node>, usize), VerifyErrorKind> {
+    let mut nodes = Vec::new();
+    let mut ids: HashMap<Node, usize> = HashMap::new();
+    let mut stack: Vec<usize> = Vec::new();
+    let mut saved = Vec::new();
+    for &step in steps {
+        match step {
+            ProofStep::Entry(index) => {
+                let entry = &database.entries[index];
+                let arity = if entry.is_hypothesis() { 0 } else { entry.frame.hypotheses.len() };
+                let base = stack
+                    .len()
+                    .checked_sub(arity)
+                    .ok_or_else(|| VerifyErrorKind::StackUnderflow(entry.label.to_string()))?;
+                let node = (index, stack.split_off(base));
+                let id = *ids.entry(node.clone()).or_insert_with(|| {
+                    nodes.push(node);
+                    nodes.len() - 1
+                });
+                stack.push(id);
+            }
+            ProofStep::Saved(n) => stack.push(*saved.get(n).ok_or(VerifyErrorKind::UnknownSavedStep(n))?),
+            ProofStep::Save => saved.push(*stack.last().ok_or(VerifyErrorKind::NothingToSave)?),
+            ProofStep::Unknown => return Err(VerifyErrorKind::IncompleteProof),
+        }
+    }
+    match stack.as_slice() {
+        [root] => Ok((nodes, *root)),
+        _ => Err(VerifyErrorKind::StackNotSingleton(stack.len())),
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use db_parser::terminals::Symbol;
+use serde::Serialize;
+
+use crate::database::{Database, EntryKind};
+
+/// One element of a search pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternToken {
+    /// `$*`: any run of symbols, possibly empty.
+    AnyExpression,
+    /// `$?`: any single symbol.
+    AnySymbol,
+    /// A variable, matching any variable of the same type as long as
+    /// distinct pattern variables match distinct variables.
+    Variable(String),
+    Constant(String),
+}
+
+/// A math string with wildcards, matched against the whole of a statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub tokens: Vec<PatternToken>,
+    /// The type of each variable in the pattern, from the `$f` statements.
+    types: HashMap<String, String>,
+}
+impl Pattern {
+    /// Parses a whitespace-separated pattern such as `|- ( ph -> $* )`.
+    /// Symbols the database declares with `$v` are variables.
+    pub fn parse(database: &Database, pattern: &str) -> Self {
+        let types = database
+            .entries
+            .iter()
+            .filter(|entry| entry.kind == EntryKind::FloatingHypothesis)
+            .map(|entry| (entry.symbols[1].to_string(), entry.symbols[0].to_string()))
+            .collect();
+        let tokens = pattern
+            .split_ascii_whitespace()
+            .map(|token| match token {
+                "$*" => PatternToken::AnyExpression,
+                "$?" => PatternToken::AnySymbol,
+                _ if database.is_variable(&Symbol(token.as_bytes().into())) => PatternToken::Variable(token.to_string()),
+                _ => PatternToken::Constant(token.to_string()),
+            })
+            .collect();
+        Self { tokens, types }
+    }
+
+    pub fn matches(&self, symbols: &[Symbol]) -> bool {
+        let symbols: Vec<String> = symbols.iter().map(|symbol| symbol.to_string()).collect();
+        let mut matcher = Matcher {
+            pattern: self,
+            bindings: HashMap::new(),
+        };
+        matcher.matches(&self.tokens, &symbols)
+    }
+}
+
+struct Matcher<'p> {
+    pattern: &'p Pattern,
+    /// Pattern variable to database variable.
+    bindings: HashMap<&'p str, String>,
+}
+impl<'p> Matcher<'p> {
+    fn matches(&mut self, tokens: &'p [PatternToken], symbols: &[String]) -> bool {
+        let Some((token, rest)) = tokens.split_first() else {
+            return symbols.is_empty();
+        };
+        match token {
+            PatternToken::AnyExpression => (0..=symbols.len()).any(|skip| self.matches(rest, &symbols[skip..])),
+            PatternToken::AnySymbol => !symbols.is_empty() && self.matches(rest, &symbols[1..]),
+            PatternToken::Constant(constant) => symbols.first() == Some(constant) && self.matches(rest, &symbols[1..]),
+            PatternToken::Variable(variable) => {
+                let Some(symbol) = symbols.first() else {
+                    return false;
+                };
+                match self.bindings.get(variable.as_str()) {
+                    Some(bound) => bound == symbol && self.matches(rest, &symbols[1..]),
+                    None => {
+                        let types = &self.pattern.types;
+                        if !types.contains_key(symbol)
+                            || types.get(variable) != types.get(symbol)
+                            || self.bindings.values().any(|bound| bound == symbol)
+                        {
+                            return false;
+                        }
+                        self.bindings.insert(variable, symbol.clone());
+                        let matched = self.matches(rest, &symbols[1..]);
+                        if !matched {
+                            self.bindings.remove(variable.as_str());
+                        }
+                        matched
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchOptions {
+    /// Also match the `$e` hypotheses of each assertion.
+    pub hypotheses: bool,
+}
+
+/// An assertion with a statement matching the pattern.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub label: String,
+    pub keyword: &'static str,
+    pub formula: String,
+    /// The label of the matching `$e` hypothesis, if the conclusion itself
+    /// does not match.
+    pub hypothesis: Option<String>,
+}
+impl Display for SearchHit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.label, self.keyword, self.formula)?;
+        if let Some(hypothesis) = &self.hypothesis {
+            write!(f, " (hypothesis {hypothesis})")?;
+        }
+        Ok(())
+    }
+}
+
+/// The assertions `within` accepts, by entry index, whose conclusion or, with
+/// `options.hypotheses`, one of whose `$e` hypotheses matches `pattern`.
+pub fn search(
+    database: &Database,
+    pattern: &Pattern,
+    options: SearchOptions,
+    within: impl Fn(usize) -> bool,
+) -> Vec<SearchHit> {
+    let mut hits = Vec::new();
+    for (index, entry) in database.assertions() {
+        if !within(index) {
+            continue;
+        }
+        let hypothesis = if pattern.matches(&entry.symbols) {
+            None
+        } else if options.hypotheses {
+            let matching = entry.frame.hypotheses.iter().map(|&i| &database.entries[i]).find(|hypothesis| {
+                hypothesis.kind == EntryKind::EssentialHypothesis && pattern.matches(&hypothesis.symbols)
+            });
+            match matching {
+                Some(hypothesis) => Some(hypothesis.label.to_string()),
+                None => continue,
+            }
+        } else {
+            continue;
+        };
+        hits.push(SearchHit {
+            label: entry.label.to_string(),
+            keyword: entry.kind.keyword(),
+            formula: entry.symbols.iter().map(|symbol| symbol.to_string()).collect::<Vec<_>>().join(" "),
+            hypothesis,
+        });
+    }
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    fn labels(hits: &[SearchHit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.label.as_str()).collect()
+    }
+
+    fn search_demo(pattern: &str, options: SearchOptions) -> Vec<SearchHit> {
+        let (_, document) = db_parser::document::document(DEMO.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        search(&database, &Pattern::parse(&database, pattern), options, |_| true)
+    }
+
+    #[test]
+    fn wildcards_match_runs_of_symbols() {
+        let options = SearchOptions::default();
+        assert_eq!(labels(&search_demo("|- ( $* )", options)), ["ax-1", "ax-2", "a1i"]);
+        assert_eq!(labels(&search_demo("|- ( ph -> $* )", options)), ["ax-1", "a1i"]);
+        assert_eq!(labels(&search_demo("|- ( ( $* ) -> $* )", options)), ["ax-2"]);
+        assert_eq!(labels(&search_demo("|- $?", options)), ["ax-mp", "mp2b", "mp2c", "mp1"]);
+        assert_eq!(labels(&search_demo("wff $? $*", options)), ["wn", "wi"]);
+        assert_eq!(labels(&search_demo("$* -. $*", options)), ["wn"]);
+        assert!(search_demo("|- $? $?", options).is_empty());
+    }
+
+    #[test]
+    fn pattern_variables_match_distinct_variables() {
+        let options = SearchOptions::default();
+        // `ph` and `ps` may swap, but must stay distinct.
+        assert_eq!(labels(&search_demo("|- ( ph -> ps )", options)), ["a1i"]);
+        assert!(search_demo("|- ( ph -> ph )", options).is_empty());
+        assert_eq!(labels(&search_demo("|- ( ch -> ( ph -> ch ) )", options)), ["ax-1"]);
+        assert!(search_demo("|- ( ph -> ( ph -> ph ) )", options).is_empty());
+    }
+
+    #[test]
+    fn hypotheses_are_searched_on_request() {
+        let hits = search_demo("|- ( ph -> $* )", SearchOptions { hypotheses: true });
+        assert_eq!(labels(&hits), ["ax-mp", "ax-1", "a1i", "mp2b", "mp2c", "mp1"]);
+        assert_eq!(hits[0].to_string(), "ax-mp $a |- ps (hypothesis maj)");
+        assert_eq!(hits[2].to_string(), "a1i $p |- ( ps -> ph )");
+        assert_eq!(hits[3].hypothesis.as_deref(), Some("mp2b.2"));
+    }
+
+    #[test]
+    fn search_stays_within_the_given_entries() {
+        let (_, document) = db_parser::document::document(DEMO.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let pattern = Pattern::parse(&database, "|- $*");
+        let before_a1i = database.index_of("a1i").unwrap();
+        let hits = search(&database, &pattern, SearchOptions::default(), |index| index < before_a1i);
+        assert_eq!(labels(&hits), ["ax-mp", "ax-1", "ax-2"]);
+    }
+}
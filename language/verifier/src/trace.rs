@@ -0,0 +1,286 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::database::{Database, EntryKind};
+use crate::proof::{decode, proof_graph};
+use crate::verify::{VerifyError, VerifyErrorKind};
+
+/// The assertions the proof of the `$p` entry at `theorem` applies, each once,
+/// in the order they are first used.
+///
+/// With `essential`, only assertions on the logical path of the proof are
+/// returned: those proving the theorem or one of the `$e` hypotheses of an
+/// assertion on the path. This leaves out the syntax constructors that only
+/// build the formulas substituted for variables.
+pub fn references(database: &Database, theorem: usize, essential: bool) -> Result<Vec<usize>, VerifyError> {
+    let steps = decode(database, theorem)?;
+    let (nodes, root) = proof_graph(database, &steps).map_err(|kind| VerifyError {
+        label: database.entries[theorem].label.to_string(),
+        kind,
+    })?;
+
+    let mut on_path = vec![!essential; nodes.len()];
+    on_path[root] = true;
+    for id in (0..nodes.len()).rev() {
+        if !on_path[id] {
+            continue;
+        }
+        let (index, children) = &nodes[id];
+        for (&hypothesis, &child) in database.entries[*index].frame.hypotheses.iter().zip(children) {
+            if database.entries[hypothesis].kind == EntryKind::EssentialHypothesis {
+                on_path[child] = true;
+            }
+        }
+    }
+
+    let mut seen = vec![false; database.entries.len()];
+    let mut used = Vec::new();
+    for (id, &(index, _)) in nodes.iter().enumerate() {
+        if on_path[id] && database.entries[index].is_assertion() && !seen[index] {
+            seen[index] = true;
+            used.push(index);
+        }
+    }
+    Ok(used)
+}
+
+/// Whether an `$a` entry is a definition, by the set.mm `df-` label
+/// convention. Every other `$a` counts as an axiom.
+pub fn is_definition(database: &Database, index: usize) -> bool {
+    database.entries[index].label.0.as_bytes().starts_with(b"df-")
+}
+
+/// The `$a` statements each theorem ultimately rests on, following the
+/// assertions its proof uses back to axioms and definitions.
+///
+/// Results are memoized, so tracing many theorems of one database shares the
+/// work done for the lemmas they have in common. A proof that cannot be
+/// followed, such as one with unknown steps, counts as using nothing, and a
+/// reference to a later assertion is not followed; both are recorded and
+/// reported for every theorem depending on the proof.
+pub struct TraceBack<'d, 'a> {
+    database: &'d Database<'a>,
+    essential: bool,
+    traced: Vec<Option<Traced>>,
+    problems: Vec<Vec<VerifyError>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Traced {
+    axioms: Vec<usize>,
+    /// Theorems in the dependency chain whose proofs were not followed
+    /// completely.
+    skipped: Vec<usize>,
+}
+
+impl<'d, 'a> TraceBack<'d, 'a> {
+    /// See `references` for `essential`.
+    pub fn new(database: &'d Database<'a>, essential: bool) -> Self {
+        Self {
+            database,
+            essential,
+            traced: vec![None; database.entries.len()],
+            problems: vec![Vec::new(); database.entries.len()],
+        }
+    }
+
+    /// Entry indices of the `$a` statements the assertion at `index` depends
+    /// on, sorted. An `$a` statement depends only on itself.
+    pub fn axioms(&mut self, index: usize) -> &[usize] {
+        &self.trace(index).axioms
+    }
+
+    /// Why the proofs the assertion at `index` depends on, itself included,
+    /// were not followed completely, in database order.
+    pub fn skipped(&mut self, index: usize) -> Vec<&VerifyError> {
+        self.trace(index);
+        let traced = self.traced[index].as_ref().unwrap();
+        traced.skipped.iter().flat_map(|&theorem| &self.problems[theorem]).collect()
+    }
+
+    fn trace(&mut self, index: usize) -> &Traced {
+        // Proofs may only use earlier assertions, so an explicit stack of
+        // pending theorems terminates without recursing once per lemma.
+        let mut pending = vec![index];
+        while let Some(&current) = pending.last() {
+            if self.traced[current].is_some() {
+                pending.pop();
+                continue;
+            }
+            let entry = &self.database.entries[current];
+            if entry.kind != EntryKind::ProvableAssertion {
+                let axioms = if entry.is_assertion() { vec![current] } else { Vec::new() };
+                self.traced[current] = Some(Traced { axioms, skipped: Vec::new() });
+                pending.pop();
+                continue;
+            }
+            let (used, later): (Vec<usize>, Vec<usize>) = match references(self.database, current, self.essential) {
+                Ok(used) => used.into_iter().partition(|&used| used < current),
+                Err(error) => {
+                    self.problems[current] = vec![error];
+                    Default::default()
+                }
+            };
+            let missing: Vec<usize> = used.iter().copied().filter(|&used| self.traced[used].is_none()).collect();
+            if !missing.is_empty() {
+                pending.extend(missing);
+                continue;
+            }
+            if !later.is_empty() {
+                self.problems[current] = later
+                    .iter()
+                    .map(|&later| VerifyError {
+                        label: entry.label.to_string(),
+                        kind: VerifyErrorKind::LaterAssertion(self.database.entries[later].label.to_string()),
+                    })
+                    .collect();
+            }
+
+            let mut traced = Traced::default();
+            for &used in &used {
+                let used = self.traced[used].as_ref().unwrap();
+                traced.axioms.extend(&used.axioms);
+                traced.skipped.extend(&used.skipped);
+            }
+            if !self.problems[current].is_empty() {
+                traced.skipped.push(current);
+            }
+            for indices in [&mut traced.axioms, &mut traced.skipped] {
+                indices.sort_unstable();
+                indices.dedup();
+            }
+            self.traced[current] = Some(traced);
+            pending.pop();
+        }
+        self.traced[index].as_ref().unwrap()
+    }
+
+    /// Whether the assertion at `index` depends on the `$a` statement at
+    /// `axiom`.
+    pub fn uses(&mut self, index: usize, axiom: usize) -> bool {
+        self.axioms(index).binary_search(&axiom).is_ok()
+    }
+
+    /// The dependencies of the assertion at `index`, split into axioms and
+    /// definitions.
+    pub fn report(&mut self, index: usize) -> TraceReport {
+        let database = self.database;
+        let (definitions, axioms): (Vec<usize>, Vec<usize>) =
+            self.axioms(index).iter().partition(|&&axiom| is_definition(database, axiom));
+        let labels = |indices: Vec<usize>| indices.iter().map(|&i| database.entries[i].label.to_string()).collect();
+        TraceReport {
+            label: database.entries[index].label.to_string(),
+            axioms: labels(axioms),
+            definitions: labels(definitions),
+            skipped: self.skipped(index).iter().map(|error| error.to_string()).collect(),
+        }
+    }
+}
+
+/// The axioms and definitions an assertion depends on, in database order.
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceReport {
+    pub label: String,
+    pub axioms: Vec<String>,
+    pub definitions: Vec<String>,
+    /// The proofs in the dependency chain that were not followed
+    /// completely, with the reason.
+    pub skipped: Vec<String>,
+}
+impl TraceReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+impl Display for TraceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} depends on {} axioms and {} definitions", self.label, self.axioms.len(), self.definitions.len())?;
+        for (heading, labels) in [("axioms", &self.axioms), ("definitions", &self.definitions)] {
+            if !labels.is_empty() {
+                writeln!(f, "  {heading}: {}", labels.join(" "))?;
+            }
+        }
+        for skipped in &self.skipped {
+            writeln!(f, "  not followed: {skipped}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    /// The demo database with the given proof of mp2b, and mp2c proved
+    /// through mp2b.
+    fn with_mp2b_proof(mp2b: &str) -> String {
+        DEMO.replacen("$= ( ax-mp ) BCABDEGFG $.", &format!("$= {mp2b} $."), 1)
+            .replacen("$= ( ax-mp ) BCABDEGFG $.", "$= ( mp2b ) ABCDEFG $.", 1)
+    }
+
+    fn labels(database: &Database, indices: &[usize]) -> Vec<String> {
+        indices.iter().map(|&i| database.entries[i].label.to_string()).collect()
+    }
+
+    #[test]
+    fn trace_back_follows_proofs_to_axioms() {
+        let text = with_mp2b_proof("( ax-mp ) BCABDEGFG");
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let a1i = database.index_of("a1i").unwrap();
+        let mp2c = database.index_of("mp2c").unwrap();
+
+        let mut trace = TraceBack::new(&database, true);
+        let axioms = trace.axioms(a1i).to_vec();
+        assert_eq!(labels(&database, &axioms), ["ax-mp", "ax-1"]);
+        let axioms = trace.axioms(mp2c).to_vec();
+        assert_eq!(labels(&database, &axioms), ["ax-mp"]);
+        assert!(trace.uses(mp2c, database.index_of("ax-mp").unwrap()));
+        assert!(!trace.uses(mp2c, database.index_of("ax-1").unwrap()));
+        assert!(trace.skipped(mp2c).is_empty());
+
+        let mut trace = TraceBack::new(&database, false);
+        let axioms = trace.axioms(a1i).to_vec();
+        assert_eq!(labels(&database, &axioms), ["wi", "ax-mp", "ax-1"]);
+        let report = trace.report(a1i);
+        assert_eq!(report.to_string(), "a1i depends on 3 axioms and 0 definitions\n  axioms: wi ax-mp ax-1\n");
+    }
+
+    #[test]
+    fn trace_back_skips_and_reports_an_incomplete_proof() {
+        let text = with_mp2b_proof("?");
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let mut trace = TraceBack::new(&database, true);
+
+        let mp2c = database.index_of("mp2c").unwrap();
+        assert!(trace.axioms(mp2c).is_empty());
+        let report = trace.report(mp2c);
+        assert_eq!(report.skipped, ["mp2b: proof has unknown steps"]);
+        assert!(report.to_string().ends_with("  not followed: mp2b: proof has unknown steps\n"));
+
+        let a1i = database.index_of("a1i").unwrap();
+        assert_eq!(trace.axioms(a1i).len(), 2);
+        assert!(trace.skipped(a1i).is_empty());
+    }
+
+    #[test]
+    fn trace_back_reports_a_reference_to_a_later_assertion() {
+        let text = with_mp2b_proof("( mp1 ) BCABDEGFG");
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let mut trace = TraceBack::new(&database, true);
+
+        let mp2c = database.index_of("mp2c").unwrap();
+        let skipped = trace.skipped(mp2c);
+        let expected = VerifyError {
+            label: "mp2b".to_string(),
+            kind: VerifyErrorKind::LaterAssertion("mp1".to_string()),
+        };
+        assert_eq!(skipped, [&expected]);
+        assert!(trace.axioms(mp2c).is_empty());
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::database::{Database, EntryKind};
+use crate::grammar::{Grammar, SyntaxTree};
+
+/// A syntax tree whose variables are numbered, so that the variables of the
+/// query and of an assertion stay apart even when they have the same name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Term {
+    Variable(usize),
+    Node(usize, Vec<Term>),
+}
+
+/// Robinson unification over terms, with the occurs check and variable
+/// types.
+#[derive(Debug, Clone)]
+pub(crate) struct Unifier<'g> {
+    database: &'g Database<'g>,
+    pub(crate) names: Vec<String>,
+    types: Vec<Option<String>>,
+    /// Variables that stand for themselves and are never bound.
+    fixed: Vec<bool>,
+    pub(crate) bindings: Vec<Option<Term>>,
+}
+impl<'g> Unifier<'g> {
+    pub(crate) fn new(database: &'g Database<'g>) -> Self {
+        Self {
+            database,
+            names: Vec::new(),
+            types: Vec::new(),
+            fixed: Vec::new(),
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Adds a variable and returns its number.
+    pub(crate) fn variable(&mut self, name: String, typecode: Option<String>, fixed: bool) -> usize {
+        self.names.push(name);
+        self.types.push(typecode);
+        self.fixed.push(fixed);
+        self.bindings.push(None);
+        self.names.len() - 1
+    }
+
+    pub(crate) fn is_fixed(&self, variable: usize) -> bool {
+        self.fixed[variable]
+    }
+
+    /// The term of `tree`, numbering its variables through `ids`. Variables
+    /// not in `ids` yet are added as ones that can be bound.
+    pub(crate) fn term(&mut self, grammar: &Grammar, tree: &SyntaxTree, ids: &mut HashMap<String, usize>) -> Term {
+        match tree {
+            SyntaxTree::Variable(name) => Term::Variable(match ids.get(name) {
+                Some(&id) => id,
+                None => {
+                    let id = self.variable(name.clone(), grammar.variable_type(name).map(str::to_string), false);
+                    ids.insert(name.clone(), id);
+                    id
+                }
+            }),
+            SyntaxTree::Node(axiom, children) => {
+                Term::Node(*axiom, children.iter().map(|child| self.term(grammar, child, ids)).collect())
+            }
+        }
+    }
+
+    /// The term of `tree` with its variables, numbered through `ids`, as
+    /// ones that are never bound.
+    pub(crate) fn fixed_term(&mut self, grammar: &Grammar, tree: &SyntaxTree, ids: &mut HashMap<String, usize>) -> Term {
+        match tree {
+            SyntaxTree::Variable(name) => Term::Variable(match ids.get(name) {
+                Some(&id) => id,
+                None => {
+                    let id = self.variable(name.clone(), grammar.variable_type(name).map(str::to_string), true);
+                    ids.insert(name.clone(), id);
+                    id
+                }
+            }),
+            SyntaxTree::Node(axiom, children) => Term::Node(
+                *axiom,
+                children.iter().map(|child| self.fixed_term(grammar, child, ids)).collect(),
+            ),
+        }
+    }
+
+    pub(crate) fn walk(&self, term: &Term) -> Term {
+        let mut term = term.clone();
+        while let Term::Variable(v) = term {
+            match &self.bindings[v] {
+                Some(bound) => term = bound.clone(),
+                None => break,
+            }
+        }
+        term
+    }
+
+    pub(crate) fn type_of(&self, term: &Term) -> Option<String> {
+        match term {
+            Term::Variable(v) => self.types[*v].clone(),
+            Term::Node(axiom, _) => Some(self.database.entries[*axiom].typecode().to_string()),
+        }
+    }
+
+    fn occurs(&self, variable: usize, term: &Term) -> bool {
+        match self.walk(term) {
+            Term::Variable(v) => v == variable,
+            Term::Node(_, children) => children.iter().any(|child| self.occurs(variable, child)),
+        }
+    }
+
+    pub(crate) fn unify(&mut self, a: &Term, b: &Term) -> bool {
+        match (self.walk(a), self.walk(b)) {
+            (Term::Variable(x), Term::Variable(y)) if x == y => true,
+            (Term::Variable(x), term) | (term, Term::Variable(x)) if !self.fixed[x] => {
+                if self.types[x] != self.type_of(&term) || self.occurs(x, &term) {
+                    return false;
+                }
+                self.bindings[x] = Some(term);
+                true
+            }
+            (Term::Node(f, xs), Term::Node(g, ys)) => {
+                f == g && xs.len() == ys.len() && xs.iter().zip(&ys).all(|(x, y)| self.unify(x, y))
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn resolve(&self, term: &Term) -> SyntaxTree {
+        match self.walk(term) {
+            Term::Variable(v) => SyntaxTree::Variable(self.names[v].clone()),
+            Term::Node(axiom, children) => {
+                SyntaxTree::Node(axiom, children.iter().map(|child| self.resolve(child)).collect())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnifyOptions {
+    /// Also unify the query with the `$e` hypotheses of each assertion.
+    pub hypotheses: bool,
+}
+
+/// An assertion whose statement unifies with the query.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnifyHit {
+    pub label: String,
+    pub keyword: &'static str,
+    pub formula: String,
+    /// The label of the unifying `$e` hypothesis, if the conclusion itself
+    /// does not unify.
+    pub hypothesis: Option<String>,
+    /// What each variable of the assertion is replaced with, in terms of the
+    /// variables of the query. Free assertion variables that share a name
+    /// with a query variable are renamed apart; variables replaced with
+    /// themselves are left out.
+    pub substitution: BTreeMap<String, String>,
+    /// Number of query variables the match had to instantiate. Zero means
+    /// the assertion proves the query as it stands.
+    pub instantiated: usize,
+    /// Size of the syntax tree of the matched statement; larger trees are
+    /// more specific.
+    pub size: usize,
+}
+impl Display for UnifyHit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.label, self.keyword, self.formula)?;
+        if let Some(hypothesis) = &self.hypothesis {
+            write!(f, " (hypothesis {hypothesis})")?;
+        }
+        for (variable, expression) in &self.substitution {
+            write!(f, "\n  {variable} := {expression}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Unifies `query`, a statement with its typecode, with the conclusion of
+/// every assertion `within` accepts and, with `options.hypotheses`, with
+/// their `$e` hypotheses.
+///
+/// Hits come most specific first: those that prove the query without
+/// instantiating its variables, then by decreasing size of the matched
+/// statement.
+pub fn unify_search(
+    database: &Database,
+    grammar: &Grammar,
+    query: &[String],
+    options: UnifyOptions,
+    within: impl Fn(usize) -> bool,
+) -> Option<Vec<UnifyHit>> {
+    let query_tree = grammar.parse_statement(query)?;
+    let mut hits = Vec::new();
+    for (index, entry) in database.assertions() {
+        if !within(index) {
+            continue;
+        }
+        let mut candidates = vec![(index, None)];
+        if options.hypotheses {
+            candidates.extend(
+                entry
+                    .frame
+                    .hypotheses
+                    .iter()
+                    .filter(|&&i| database.entries[i].kind == EntryKind::EssentialHypothesis)
+                    .map(|&i| (i, Some(i))),
+            );
+        }
+        for (statement, hypothesis) in candidates {
+            let symbols: Vec<String> = database.entries[statement].symbols.iter().map(|s| s.to_string()).collect();
+            if symbols[0] != query[0] {
+                continue;
+            }
+            let Some(tree) = grammar.parse_statement(&symbols) else {
+                continue;
+            };
+            if let Some(hit) = unify_with(database, grammar, index, &tree, &query_tree, hypothesis) {
+                hits.push(hit);
+                break;
+            }
+        }
+    }
+    hits.sort_by_key(|hit| (hit.instantiated, std::cmp::Reverse(hit.size)));
+    Some(hits)
+}
+
+fn unify_with(
+    database: &Database,
+    grammar: &Grammar,
+    index: usize,
+    tree: &SyntaxTree,
+    query: &SyntaxTree,
+    hypothesis: Option<usize>,
+) -> Option<UnifyHit> {
+    let mut unifier = Unifier::new(database);
+    let mut assertion_ids = HashMap::new();
+    let assertion = unifier.term(grammar, tree, &mut assertion_ids);
+    let mut query_ids = HashMap::new();
+    let query = unifier.term(grammar, query, &mut query_ids);
+    if !unifier.unify(&assertion, &query) {
+        return None;
+    }
+
+    // Free assertion variables keep their name unless a query variable has
+    // it, so that `ph` in the substitution is always the query's `ph`.
+    let mut used: HashSet<String> = query_ids.keys().cloned().collect();
+    let mut clashing: Vec<usize> = Vec::new();
+    for (name, &id) in &assertion_ids {
+        if unifier.bindings[id].is_none() && !used.insert(name.clone()) {
+            clashing.push(id);
+        }
+    }
+    clashing.sort_unstable();
+    for id in clashing {
+        let fresh = fresh_variable(database, grammar, &unifier.names[id], &used);
+        used.insert(fresh.clone());
+        unifier.names[id] = fresh;
+    }
+
+    let join = |tree: SyntaxTree| tree.symbols(database).join(" ");
+    let substitution = assertion_ids
+        .iter()
+        .map(|(name, &id)| (name.clone(), join(unifier.resolve(&Term::Variable(id)))))
+        .filter(|(name, expression)| name != expression)
+        .collect();
+    let entry = &database.entries[index];
+    Some(UnifyHit {
+        label: entry.label.to_string(),
+        keyword: entry.kind.keyword(),
+        formula: entry.symbols.iter().map(|symbol| symbol.to_string()).collect::<Vec<_>>().join(" "),
+        hypothesis: hypothesis.map(|i| database.entries[i].label.to_string()),
+        substitution,
+        instantiated: query_ids.values().filter(|&&id| unifier.bindings[id].is_some()).count(),
+        size: tree.size(),
+    })
+}
+
+/// The first variable of the type of `name`, in database order, that is not
+/// `used`, or `name` primed as often as needed if there is none.
+fn fresh_variable(database: &Database, grammar: &Grammar, name: &str, used: &HashSet<String>) -> String {
+    let typecode = grammar.variable_type(name);
+    let fresh = database
+        .entries
+        .iter()
+        .filter(|entry| entry.kind == EntryKind::FloatingHypothesis)
+        .filter(|entry| Some(entry.symbols[0].to_string().as_str()) == typecode)
+        .map(|entry| entry.symbols[1].to_string())
+        .find(|variable| !used.contains(variable));
+    fresh.unwrap_or_else(|| {
+        let mut primed = format!("{name}'");
+        while used.contains(&primed) {
+            primed.push('\'');
+        }
+        primed
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &[u8] = include_bytes!("../../fixtures/demo.mm");
+
+    fn search(statement: &str) -> Vec<UnifyHit> {
+        let (_, document) = db_parser::document::document(DEMO).unwrap();
+        let database = Database::new(&document).unwrap();
+        let grammar = Grammar::new(&database);
+        let query: Vec<String> = statement.split_ascii_whitespace().map(str::to_string).collect();
+        unify_search(&database, &grammar, &query, UnifyOptions::default(), |_| true).unwrap()
+    }
+
+    #[test]
+    fn unify_search_puts_exact_matches_first() {
+        let hits = search("|- ( ch -> ( ( ps -> ch ) -> ch ) )");
+        let labels: Vec<&str> = hits.iter().map(|hit| hit.label.as_str()).collect();
+        assert_eq!(labels, ["ax-1", "a1i", "ax-mp", "mp2b", "mp2c", "mp1"]);
+        assert_eq!(hits[0].instantiated, 0);
+        let substitution: Vec<(&str, &str)> =
+            hits[0].substitution.iter().map(|(v, e)| (v.as_str(), e.as_str())).collect();
+        assert_eq!(substitution, [("ph", "ch"), ("ps", "( ps -> ch )")]);
+    }
+
+    #[test]
+    fn unify_search_instantiates_query_variables() {
+        let hits = search("|- ( ph -> ps )");
+        let ax1 = hits.iter().find(|hit| hit.label == "ax-1").unwrap();
+        assert_eq!(ax1.instantiated, 1);
+        // ax-1's `ps` stays free and is renamed apart from the query's `ps`;
+        // its `ph` becomes the query's `ph` and is not listed.
+        let substitution: Vec<(&str, &str)> =
+            ax1.substitution.iter().map(|(v, e)| (v.as_str(), e.as_str())).collect();
+        assert_eq!(substitution, [("ps", "ch")]);
+        assert_eq!(ax1.to_string(), "ax-1 $a |- ( ph -> ( ps -> ph ) )\n  ps := ch");
+        let ax2 = hits.iter().find(|hit| hit.label == "ax-2").unwrap();
+        assert_eq!(ax2.instantiated, 2);
+    }
+
+    #[test]
+    fn unify_search_rejects_a_cyclic_match() {
+        // ax-1 would need ph := ( ph -> ps ).
+        let hits = search("|- ( ph -> ( ps -> ( ph -> ps ) ) )");
+        let labels: Vec<&str> = hits.iter().map(|hit| hit.label.as_str()).collect();
+        assert_eq!(labels, ["a1i", "ax-mp", "mp2b", "mp2c", "mp1"]);
+    }
+}
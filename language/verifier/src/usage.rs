@@ -0,0 +1,103 @@
+use crate::database::Database;
+
+/// For every assertion, the theorems whose proofs reference it.
+///
+/// Built from the label lists of the proofs alone, without decoding them, so
+/// a `$p` counts as using every assertion its proof lists.
+#[derive(Debug, Clone, Default)]
+pub struct UsageIndex {
+    users: Vec<Vec<usize>>,
+}
+impl UsageIndex {
+    pub fn new(database: &Database) -> Self {
+        let mut users = vec![Vec::new(); database.entries.len()];
+        for (index, entry) in database.entries.iter().enumerate() {
+            let Some(proof) = &entry.proof else {
+                continue;
+            };
+            for label in &proof.labels {
+                if let Some(&used) = database.labels.get(label) {
+                    if database.entries[used].is_assertion() && users[used].last() != Some(&index) {
+                        users[used].push(index);
+                    }
+                }
+            }
+        }
+        Self { users }
+    }
+
+    /// Theorems whose proofs reference the entry at `index`, in database
+    /// order.
+    pub fn direct(&self, index: usize) -> &[usize] {
+        &self.users[index]
+    }
+
+    /// Theorems that depend on the entry at `index` through any chain of
+    /// proofs, in database order.
+    pub fn transitive(&self, index: usize) -> Vec<usize> {
+        let mut seen = vec![false; self.users.len()];
+        let mut pending = vec![index];
+        let mut users = Vec::new();
+        while let Some(current) = pending.pop() {
+            for &user in &self.users[current] {
+                if !seen[user] {
+                    seen[user] = true;
+                    users.push(user);
+                    pending.push(user);
+                }
+            }
+        }
+        users.sort_unstable();
+        users
+    }
+
+    pub fn count(&self, index: usize) -> usize {
+        self.users[index].len()
+    }
+
+    pub fn transitive_count(&self, index: usize) -> usize {
+        self.transitive(index).len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    fn labels(database: &Database, indices: &[usize]) -> Vec<String> {
+        indices.iter().map(|&i| database.entries[i].label.to_string()).collect()
+    }
+
+    #[test]
+    fn direct_users_are_the_proofs_listing_an_assertion() {
+        let (_, document) = db_parser::document::document(DEMO.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let usage = UsageIndex::new(&database);
+
+        let ax_mp = database.index_of("ax-mp").unwrap();
+        assert_eq!(usage.count(ax_mp), 4);
+        assert_eq!(labels(&database, usage.direct(ax_mp)), ["a1i", "mp2b", "mp2c", "mp1"]);
+        assert_eq!(labels(&database, usage.direct(database.index_of("wi").unwrap())), ["a1i"]);
+        assert_eq!(usage.count(database.index_of("ax-2").unwrap()), 0);
+        assert_eq!(usage.count(database.index_of("mp2b").unwrap()), 0);
+    }
+
+    #[test]
+    fn transitive_users_follow_chains_of_proofs() {
+        // mp2c proved through mp2b uses ax-mp only indirectly.
+        let mp2c = "mp2c $p |- ch $= ( ax-mp ) BCABDEGFG $.";
+        let text = DEMO.replacen(mp2c, "mp2c $p |- ch $= ( mp2b ) ABCDEFG $.", 1);
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        let usage = UsageIndex::new(&database);
+
+        let ax_mp = database.index_of("ax-mp").unwrap();
+        assert_eq!(labels(&database, usage.direct(ax_mp)), ["a1i", "mp2b", "mp1"]);
+        assert_eq!(labels(&database, &usage.transitive(ax_mp)), ["a1i", "mp2b", "mp2c", "mp1"]);
+        assert_eq!(usage.transitive_count(ax_mp), 4);
+        assert_eq!(labels(&database, &usage.transitive(database.index_of("mp2b").unwrap())), ["mp2c"]);
+        assert!(usage.transitive(database.index_of("mp1").unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use db_parser::terminals::Symbol;
+
+use crate::database::{Database, EntryKind};
+use crate::proof::{decode, ProofStep};
+
+pub type Expression<'a> = Vec<Symbol<'a>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub label: String,
+    pub kind: VerifyErrorKind,
+}
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.label, self.kind)
+    }
+}
+impl std::error::Error for VerifyError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyErrorKind {
+    MissingProof,
+    UnknownLabel(String),
+    InactiveHypothesis(String),
+    LaterAssertion(String),
+    StackUnderflow(String),
+    TypecodeMismatch(String),
+    HypothesisMismatch(String),
+    DisjointViolation(String, String),
+    UnknownSavedStep(usize),
+    NothingToSave,
+    StackNotSingleton(usize),
+    ConclusionMismatch,
+    IncompleteProof,
+}
+impl Display for VerifyErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingProof => write!(f, "no proof"),
+            Self::UnknownLabel(label) => write!(f, "unknown label {label}"),
+            Self::InactiveHypothesis(label) => write!(f, "hypothesis {label} is not in scope"),
+            Self::LaterAssertion(label) => write!(f, "{label} is not declared before the proof"),
+            Self::StackUnderflow(label) => write!(f, "not enough hypotheses on the stack for {label}"),
+            Self::TypecodeMismatch(label) => write!(f, "typecode mismatch for {label}"),
+            Self::HypothesisMismatch(label) => write!(f, "essential hypothesis {label} does not match"),
+            Self::DisjointViolation(x, y) => write!(f, "disjoint variable violation for {x}, {y}"),
+            Self::UnknownSavedStep(n) => write!(f, "saved step {n} does not exist"),
+            Self::NothingToSave => write!(f, "Z does not follow a step"),
+            Self::StackNotSingleton(len) => write!(f, "{len} expressions left on the stack"),
+            Self::ConclusionMismatch => write!(f, "proof does not prove the assertion"),
+            Self::IncompleteProof => write!(f, "proof has unknown steps"),
+        }
+    }
+}
+
+/// Checks the proof of the `$p` entry at `index`.
+pub fn verify(database: &Database, index: usize) -> Result<(), VerifyError> {
+    let steps = decode(database, index)?;
+    let entry = &database.entries[index];
+    let mut stack = Stack::new(database, index);
+    for step in steps {
+        stack.step(step).map_err(|kind| VerifyError {
+            label: entry.label.to_string(),
+            kind,
+        })?;
+    }
+    let kind = match stack.expressions.as_slice() {
+        [conclusion] if *conclusion == entry.symbols => return Ok(()),
+        [_] => VerifyErrorKind::ConclusionMismatch,
+        expressions => VerifyErrorKind::StackNotSingleton(expressions.len()),
+    };
+    Err(VerifyError {
+        label: entry.label.to_string(),
+        kind,
+    })
+}
+
+/// Checks every proof in the database and returns the failures.
+pub fn verify_all(database: &Database) -> Vec<VerifyError> {
+    database
+        .assertions()
+        .filter(|(_, entry)| entry.kind == EntryKind::ProvableAssertion)
+        .filter_map(|(index, _)| verify(database, index).err())
+        .collect()
+}
+
+/// The proof stack of one theorem.
+pub struct Stack<'d, 'a> {
+    database: &'d Database<'a>,
+    theorem: usize,
+    disjoint: HashSet<(Symbol<'a>, Symbol<'a>)>,
+    pub expressions: Vec<Expression<'a>>,
+    pub saved: Vec<Expression<'a>>,
+}
+impl<'d, 'a> Stack<'d, 'a> {
+    pub fn new(database: &'d Database<'a>, theorem: usize) -> Self {
+        let disjoint = database.entries[theorem]
+            .frame
+            .disjoint
+            .iter()
+            .flat_map(|&(x, y)| [(x, y), (y, x)])
+            .collect();
+        Self {
+            database,
+            theorem,
+            disjoint,
+            expressions: Vec::new(),
+            saved: Vec::new(),
+        }
+    }
+
+    pub fn step(&mut self, step: ProofStep) -> Result<(), VerifyErrorKind> {
+        match step {
+            ProofStep::Entry(index) => {
+                let expression = self.apply(index)?;
+                self.expressions.push(expression);
+            }
+            ProofStep::Saved(n) => {
+                let expression = self.saved.get(n).ok_or(VerifyErrorKind::UnknownSavedStep(n))?;
+                self.expressions.push(expression.clone());
+            }
+            ProofStep::Save => {
+                let expression = self.expressions.last().ok_or(VerifyErrorKind::NothingToSave)?;
+                self.saved.push(expression.clone());
+            }
+            ProofStep::Unknown => return Err(VerifyErrorKind::IncompleteProof),
+        }
+        Ok(())
+    }
+
+    /// Pops the hypotheses of the entry at `index` and returns what it proves.
+    fn apply(&mut self, index: usize) -> Result<Expression<'a>, VerifyErrorKind> {
+        let entry = &self.database.entries[index];
+        if entry.is_hypothesis() {
+            if index > self.theorem || entry.scope_end <= self.theorem {
+                return Err(VerifyErrorKind::InactiveHypothesis(entry.label.to_string()));
+            }
+            return Ok(entry.symbols.clone());
+        }
+        if index >= self.theorem {
+            return Err(VerifyErrorKind::LaterAssertion(entry.label.to_string()));
+        }
+
+        let hypotheses = &entry.frame.hypotheses;
+        let base = self
+            .expressions
+            .len()
+            .checked_sub(hypotheses.len())
+            .ok_or_else(|| VerifyErrorKind::StackUnderflow(entry.label.to_string()))?;
+        let substitution = substitution(self.database, hypotheses, &self.expressions[base..])?;
+
+        for &(x, y) in &entry.frame.disjoint {
+            let (Some(x_expr), Some(y_expr)) = (substitution.get(&x), substitution.get(&y)) else {
+                continue;
+            };
+            for a in x_expr.iter().filter(|symbol| self.database.is_variable(symbol)) {
+                for b in y_expr.iter().filter(|symbol| self.database.is_variable(symbol)) {
+                    if a == b || !self.disjoint.contains(&(*a, *b)) {
+                        return Err(VerifyErrorKind::DisjointViolation(a.to_string(), b.to_string()));
+                    }
+                }
+            }
+        }
+
+        self.expressions.truncate(base);
+        Ok(substitute(self.database, &entry.symbols, &substitution))
+    }
+}
+
+/// Matches the mandatory `hypotheses` of an assertion against the
+/// `expressions` on the stack, returning the substitution for its variables.
+pub fn substitution<'a>(
+    database: &Database<'a>,
+    hypotheses: &[usize],
+    expressions: &[Expression<'a>],
+) -> Result<HashMap<Symbol<'a>, Vec<Symbol<'a>>>, VerifyErrorKind> {
+    let mut substitution = HashMap::new();
+    for (&hypothesis, expression) in hypotheses.iter().zip(expressions) {
+        let hyp = &database.entries[hypothesis];
+        if hyp.kind == EntryKind::FloatingHypothesis {
+            if expression.first() != Some(&hyp.typecode()) {
+                return Err(VerifyErrorKind::TypecodeMismatch(hyp.label.to_string()));
+            }
+            substitution.insert(hyp.symbols[1], expression[1..].to_vec());
+        }
+    }
+    for (&hypothesis, expression) in hypotheses.iter().zip(expressions) {
+        let hyp = &database.entries[hypothesis];
+        if hyp.kind == EntryKind::EssentialHypothesis
+            && substitute(database, &hyp.symbols, &substitution) != *expression
+        {
+            return Err(VerifyErrorKind::HypothesisMismatch(hyp.label.to_string()));
+        }
+    }
+    Ok(substitution)
+}
+
+pub fn substitute<'a>(
+    database: &Database<'a>,
+    symbols: &[Symbol<'a>],
+    substitution: &HashMap<Symbol<'a>, Vec<Symbol<'a>>>,
+) -> Expression<'a> {
+    let mut expression = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        match substitution.get(symbol) {
+            Some(replacement) if database.is_variable(symbol) => expression.extend_from_slice(replacement),
+            _ => expression.push(*symbol),
+        }
+    }
+    expression
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.mm");
+
+    /// Checks `label` in the demo database with its proof `old` replaced by
+    /// `new`.
+    fn verify_with(label: &str, old: &str, new: &str) -> Result<(), VerifyErrorKind> {
+        let start = DEMO.find(&format!("{label} $p")).unwrap();
+        let text = format!("{}{}", &DEMO[..start], DEMO[start..].replacen(old, new, 1));
+        let (_, document) = db_parser::document::document(text.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        verify(&database, database.index_of(label).unwrap()).map_err(|error| error.kind)
+    }
+
+    #[test]
+    fn the_demo_database_verifies() {
+        let (_, document) = db_parser::document::document(DEMO.as_bytes()).unwrap();
+        let database = Database::new(&document).unwrap();
+        assert_eq!(verify_all(&database), []);
+    }
+
+    #[test]
+    fn broken_proofs_fail_with_the_reason() {
+        let proof = "( ax-mp ) ABCDE";
+        let cases = [
+            ("( ax-mp ) BACDE", VerifyErrorKind::HypothesisMismatch("min".to_string())),
+            ("( ax-mp ) ABCE", VerifyErrorKind::StackUnderflow("ax-mp".to_string())),
+            ("( ax-mp ) ABCD", VerifyErrorKind::StackNotSingleton(4)),
+            ("( ) C", VerifyErrorKind::ConclusionMismatch),
+            ("( ax-mp ) ABCDEZF", VerifyErrorKind::StackNotSingleton(2)),
+            ("( ax-mp ) ABCF", VerifyErrorKind::UnknownSavedStep(0)),
+            ("?", VerifyErrorKind::IncompleteProof),
+        ];
+        for (broken, kind) in cases {
+            assert_eq!(verify_with("mp1", proof, broken), Err(kind), "{broken}");
+        }
+        let later = verify_with("a1i", "( wi ax-1 ax-mp ) ABADCABEF", "( mp1 ) ABCD");
+        assert_eq!(later, Err(VerifyErrorKind::LaterAssertion("mp1".to_string())));
+    }
+}
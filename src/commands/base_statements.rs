@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use super::{read_database, CommandResult, OutputArgs, RangeArgs, RenderArgs};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    #[command(flatten)]
+    range: RangeArgs,
+    #[command(flatten)]
+    render: RenderArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let mut document = finder::base_statements::base_statements(args.range.slice(&contents)?);
+    if let Some(renderer) = args.render.renderer(&contents)? {
+        document.map_symbols(|symbol| renderer.symbol(symbol).to_string());
+    }
+    args.output.write(&document.to_string())?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use finder::title_tree::{TitleNode, TitleTree};
+use verifier::database::Database;
+use verifier::diff::{Diff, Heading};
+
+use super::{parse_document, read_database, CommandResult, OutputArgs, RenderArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the old version of the .mm database
+    old: PathBuf,
+    /// Path to the new version
+    new: PathBuf,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    render: RenderArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let old_contents = read_database(&args.old)?;
+    let old_document = parse_document(&old_contents)?;
+    let old = Database::new(&old_document)?;
+    let new_contents = read_database(&args.new)?;
+    let new_document = parse_document(&new_contents)?;
+    let new = Database::new(&new_document)?;
+
+    let old_headings = headings(&TitleTree::from_document(&old_document, &old_contents));
+    let new_headings = headings(&TitleTree::from_document(&new_document, &new_contents));
+    let mut diff = Diff::new(&old, &old_headings, &new, &new_headings);
+    // Each side is written with the typesetting of its own database.
+    if let (Some(old_renderer), Some(new_renderer)) =
+        (args.render.renderer(&old_contents)?, args.render.renderer(&new_contents)?)
+    {
+        for changed in &mut diff.changed_statements {
+            changed.before = old_renderer.render(&changed.before);
+            changed.after = new_renderer.render(&changed.after);
+        }
+        for changed in &mut diff.changed_disjoint {
+            changed.added.iter_mut().for_each(|pair| *pair = new_renderer.render(pair));
+            changed.removed.iter_mut().for_each(|pair| *pair = old_renderer.render(pair));
+        }
+    }
+    let o = match args.format {
+        Format::Text => diff.to_string(),
+        Format::Json => serde_json::to_string_pretty(&diff)?,
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Every heading of `tree` in document order, with the title above it.
+fn headings(tree: &TitleTree) -> Vec<Heading> {
+    fn visit(nodes: &[TitleNode], parent: Option<&str>, headings: &mut Vec<Heading>) {
+        for node in nodes {
+            headings.push(Heading {
+                title: node.title.clone(),
+                ord: node.ord.clone(),
+                parent: parent.map(str::to_string),
+            });
+            visit(&node.children, Some(&node.title), headings);
+        }
+    }
+    let mut headings = Vec::new();
+    visit(&tree.nodes, None, &mut headings);
+    headings
+}
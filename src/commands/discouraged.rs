@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use finder::range::{resolve_range, RangeBound};
+use verifier::database::Database;
+use verifier::discouraged::{discouraged_file, modified_proofs, new_usage_violations};
+use verifier::usage::UsageIndex;
+
+use super::{parse_document, read_database, CommandResult, OutputArgs};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Report theorems tagged "(Proof modification is discouraged.)" whose
+    /// proofs differ from this database
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+    /// A heading or statement whose proofs may use discouraged assertions,
+    /// such as title:<mathbox heading>; repeatable
+    #[arg(long)]
+    allow: Vec<RangeBound>,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let usage = UsageIndex::new(&database);
+
+    let allowed = args
+        .allow
+        .iter()
+        .map(|bound| resolve_range(&contents, bound, bound))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut violations = new_usage_violations(&database, &usage, |index| {
+        let offset = database.entries[index].label.0.offset_in(&contents);
+        allowed.iter().any(|range| range.contains(&offset))
+    });
+    if let Some(path) = &args.baseline {
+        let baseline_contents = read_database(path)?;
+        let baseline_document = parse_document(&baseline_contents)?;
+        let baseline = Database::new(&baseline_document)?;
+        violations.extend(modified_proofs(&database, &baseline));
+    }
+
+    for violation in &violations {
+        eprintln!("warning: {violation}");
+    }
+    args.output.write(&discouraged_file(&database, &usage))?;
+    Ok(if violations.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use finder::title_tree::title_tree;
+use verifier::database::{Database, EntryKind};
+use verifier::graph::{dependency_graph, GraphOptions};
+
+use super::{parse_document, read_database, CommandResult, OutputArgs, RangeArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Dot,
+    Graphml,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Labels of the assertions to start from
+    labels: Vec<String>,
+    /// Also start from every theorem in this range
+    #[command(flatten)]
+    range: RangeArgs,
+    /// Keep only the starting theorems and the axioms and definitions
+    #[arg(long)]
+    axioms_only: bool,
+    /// Follow proof references at most this many steps
+    #[arg(long)]
+    depth: Option<usize>,
+    /// Also follow the syntax steps of proofs
+    #[arg(long)]
+    syntax: bool,
+    /// Group the statements by the heading they appear under
+    #[arg(long)]
+    cluster: bool,
+    #[arg(short, long, value_enum, default_value_t = Format::Dot)]
+    format: Format,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let offset = |index: usize| database.entries[index].label.0.offset_in(&contents);
+
+    let mut roots = args
+        .labels
+        .iter()
+        .map(|label| database.index_of(label).ok_or_else(|| format!("unknown label {label}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    if args.range.is_set() {
+        let range = args.range.resolve(&contents)?;
+        roots.extend(
+            database
+                .assertions()
+                .filter(|(index, entry)| entry.kind == EntryKind::ProvableAssertion && range.contains(&offset(*index)))
+                .map(|(index, _)| index),
+        );
+    }
+    if roots.is_empty() {
+        return Err("give the labels to start from, or a range with --from and --to".into());
+    }
+
+    let options = GraphOptions {
+        depth: args.depth,
+        axioms_only: args.axioms_only,
+        syntax: args.syntax,
+    };
+    let mut graph = dependency_graph(&database, &roots, options);
+    for error in &graph.skipped {
+        eprintln!("warning: not followed: {error}");
+    }
+    if args.cluster {
+        let tree = title_tree(&contents);
+        let headings = tree.flatten();
+        graph.cluster_by(|index| {
+            let offset = offset(index);
+            headings
+                .iter()
+                .rfind(|node| (node.offset..node.end).contains(&offset))
+                .map(|node| format!("{} {}", node.ord_string(), node.title))
+        });
+    }
+    let o = match args.format {
+        Format::Dot => graph.to_dot(),
+        Format::Graphml => graph.to_graphml(),
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use db_parser::typesetting::typesetting;
+use exporter::html::{site, write_site, SiteOptions};
+use finder::title_tree::TitleTree;
+use verifier::database::Database;
+
+use super::{parse_document, read_database, CommandResult};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Directory to write the pages to
+    #[arg(short, long, default_value = "html")]
+    directory: PathBuf,
+    /// Typeset math with althtmldef (usually Unicode) instead of htmldef
+    #[arg(long)]
+    alt: bool,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let tree = TitleTree::from_document(&document, &contents);
+    let typesetting = typesetting(&contents)?;
+
+    let pages = site(&contents, &database, &tree, &typesetting, SiteOptions { alt: args.alt });
+    write_site(&args.directory, &pages)
+        .map_err(|error| format!("cannot write to {}: {error}", args.directory.display()))?;
+    println!("{} pages written to {}", pages.len(), args.directory.display());
+    Ok(ExitCode::SUCCESS)
+}
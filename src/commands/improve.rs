@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use verifier::assistant::{Assistant, Improvement, ShownStep};
+use verifier::compress::replace_proof;
+use verifier::database::{Database, EntryKind};
+use verifier::grammar::Grammar;
+
+use super::{parse_document, read_database, CommandResult, OutputArgs, RenderArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Label of the $p statement whose `?` steps to prove
+    label: String,
+    /// How many assertions deep to search below each step
+    #[arg(short, long, default_value_t = 1)]
+    depth: usize,
+    /// Write the proof back to the database if no step is left unknown
+    #[arg(long)]
+    save: bool,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    render: RenderArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+struct Report {
+    improvements: Vec<Improvement>,
+    unknown: Vec<ShownStep>,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let theorem = database
+        .index_of(&args.label)
+        .filter(|&index| database.entries[index].kind == EntryKind::ProvableAssertion)
+        .ok_or_else(|| format!("no $p statement labeled {}", args.label))?;
+    let grammar = Grammar::new(&database);
+    let mut assistant = Assistant::new(&database, &grammar, theorem)?;
+
+    let mut report = Report {
+        improvements: assistant.improve_all(args.depth),
+        unknown: assistant.unknown(),
+    };
+    if args.save && assistant.is_complete() {
+        let proof = assistant.proof()?;
+        std::fs::write(&args.database, replace_proof(&contents, &database, theorem, &proof)?)?;
+    }
+    if let Some(renderer) = args.render.renderer(&contents)? {
+        let render = |formula: &mut Vec<String>| *formula = vec![renderer.render(&formula.join(" "))];
+        report
+            .improvements
+            .iter_mut()
+            .for_each(|improvement| render(&mut improvement.formula));
+        report.unknown.iter_mut().for_each(|step| render(&mut step.formula));
+    }
+    let o = match args.format {
+        Format::Text => {
+            let mut o = String::new();
+            for improvement in &report.improvements {
+                o.push_str(&format!("{} {}\n", improvement.label, improvement.formula.join(" ")));
+            }
+            for step in &report.unknown {
+                o.push_str(&format!("? {}\n", step.formula.join(" ")));
+            }
+            o
+        }
+        Format::Json => serde_json::to_string_pretty(&serde_json::json!({
+            "improvements": report.improvements,
+            "unknown": report.unknown,
+        }))?,
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use db_parser::typesetting::typesetting;
+use exporter::latex::{latex, LatexOptions, ProofLayout};
+use verifier::database::Database;
+
+use super::{parse_document, read_database, CommandResult, OutputArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Layout {
+    Tabular,
+    Fitch,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Labels of the assertions to typeset
+    #[arg(required = true)]
+    labels: Vec<String>,
+    /// Also typeset the proofs, in this layout
+    #[arg(long, value_enum)]
+    proof: Option<Layout>,
+    /// Write a complete document instead of fragments
+    #[arg(long)]
+    standalone: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let typesetting = typesetting(&contents)?;
+
+    let indices = args
+        .labels
+        .iter()
+        .map(|label| database.index_of(label).ok_or_else(|| format!("unknown label {label}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let options = LatexOptions {
+        proof: args.proof.map(|layout| match layout {
+            Layout::Tabular => ProofLayout::Tabular,
+            Layout::Fitch => ProofLayout::Fitch,
+        }),
+        standalone: args.standalone,
+    };
+    args.output.write(&latex(&database, &typesetting, &indices, options))?;
+    Ok(ExitCode::SUCCESS)
+}
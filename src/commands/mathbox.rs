@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use finder::title_tree::TitleTree;
+use serde_json::json;
+use verifier::database::Database;
+use verifier::mathbox::{cross_references, movable, owners, Mathbox};
+use verifier::usage::UsageIndex;
+
+use super::{parse_document, read_database, CommandResult, OutputArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Also list the mathbox theorems that could move to the main body
+    #[arg(long)]
+    movable: bool,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let usage = UsageIndex::new(&database);
+
+    let mathboxes: Vec<Mathbox> = TitleTree::from_document(&document, &contents)
+        .mathboxes()
+        .into_iter()
+        .map(|(owner, node)| Mathbox {
+            owner: owner.to_string(),
+            range: node.offset..node.end,
+        })
+        .collect();
+    let owners = owners(&database, &contents, &mathboxes);
+    let references = cross_references(&database, &usage, &owners, &mathboxes);
+    let movable = match args.movable {
+        true => movable(&database, &usage, &owners, &mathboxes),
+        false => Vec::new(),
+    };
+
+    let o = match args.format {
+        Format::Text => {
+            let mut o: String = references.iter().map(|reference| format!("{reference}\n")).collect();
+            if args.movable {
+                o.push_str("\nCould move to the main body:\n");
+                o.extend(movable.iter().map(|theorem| format!("{theorem}\n")));
+            }
+            o
+        }
+        Format::Json => match args.movable {
+            true => serde_json::to_string_pretty(&json!({ "references": references, "movable": movable }))?,
+            false => serde_json::to_string_pretty(&json!({ "references": references }))?,
+        },
+    };
+    args.output.write(&o)?;
+    Ok(if references.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
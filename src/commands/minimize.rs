@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use verifier::compress::replace_proofs;
+use verifier::database::{Database, EntryKind};
+use verifier::grammar::Grammar;
+use verifier::minimize::Minimizer;
+
+use super::{parse_document, read_database, CommandResult, OutputArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Labels of the $p statements to minimize; `*` matches any run of
+    /// characters, `?` one, and a comma separates alternatives
+    theorems: String,
+    /// Labels of the assertions to try, in the same syntax
+    #[arg(long, default_value = "*")]
+    with: String,
+    /// Report the savings without writing the proofs back
+    #[arg(long)]
+    dry_run: bool,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let grammar = Grammar::new(&database);
+    let mut minimizer = Minimizer::new(&database, &grammar);
+
+    let mut minimized = Vec::new();
+    for (theorem, entry) in database.assertions() {
+        if entry.kind != EntryKind::ProvableAssertion || !matches(&args.theorems, &entry.label.to_string()) {
+            continue;
+        }
+        let with = |index: usize| matches(&args.with, &database.entries[index].label.to_string());
+        // A proof that does not check is left as it is.
+        match minimizer.minimize_with(theorem, with) {
+            Ok(Some(result)) => minimized.push((theorem, result)),
+            Ok(None) => {}
+            Err(error) => eprintln!("warning: {error}"),
+        }
+    }
+    if !args.dry_run && !minimized.is_empty() {
+        let proofs: Vec<_> = minimized
+            .iter()
+            .map(|(theorem, result)| (*theorem, result.proof.clone()))
+            .collect();
+        std::fs::write(&args.database, replace_proofs(&contents, &database, &proofs)?)?;
+    }
+
+    let results: Vec<_> = minimized.into_iter().map(|(_, result)| result).collect();
+    let o = match args.format {
+        Format::Text => {
+            let mut o = String::new();
+            for result in &results {
+                o.push_str(&format!(
+                    "{}: {} -> {} bytes, {} shorter, using {}\n",
+                    result.label,
+                    result.before,
+                    result.after,
+                    result.before - result.after,
+                    result.used.join(" ")
+                ));
+            }
+            let saved: usize = results.iter().map(|result| result.before - result.after).sum();
+            o.push_str(&format!("{} proofs shortened by {saved} bytes\n", results.len()));
+            o
+        }
+        Format::Json => serde_json::to_string_pretty(&results)?,
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Whether `label` matches one of the comma-separated wildcard patterns.
+fn matches(patterns: &str, label: &str) -> bool {
+    fn glob(pattern: &[u8], label: &[u8]) -> bool {
+        match (pattern.split_first(), label.split_first()) {
+            (None, _) => label.is_empty(),
+            (Some((b'*', rest)), _) => (0..=label.len()).any(|skip| glob(rest, &label[skip..])),
+            (Some((b'?', rest)), Some((_, tail))) => glob(rest, tail),
+            (Some((p, rest)), Some((c, tail))) => p == c && glob(rest, tail),
+            (Some(_), None) => false,
+        }
+    }
+    patterns
+        .split(',')
+        .any(|pattern| glob(pattern.trim().as_bytes(), label.as_bytes()))
+}
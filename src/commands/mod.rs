@@ -0,0 +1,110 @@
+pub mod base_statements;
+pub mod diff;
+pub mod discouraged;
+pub mod graph;
+pub mod html;
+pub mod improve;
+pub mod latex;
+pub mod mathbox;
+pub mod minimize;
+pub mod parse;
+pub mod prove;
+pub mod search;
+pub mod show_proof;
+pub mod stats;
+pub mod text_search;
+pub mod titles;
+pub mod trace_back;
+pub mod unify;
+pub mod usage;
+pub mod verify;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use db_parser::document::Document;
+use db_parser::unicode::UnicodeRenderer;
+use finder::range::{resolve_range, RangeBound};
+
+pub type CommandResult = Result<std::process::ExitCode, Box<dyn Error>>;
+
+#[derive(clap::Args)]
+pub struct RangeArgs {
+    /// Where to start reading the database: a byte offset,
+    /// title:<heading>, outline:<1.2.3> or label:<label>
+    #[arg(long)]
+    pub from: Option<RangeBound>,
+    /// Where to stop reading the database, inclusive of the heading or
+    /// statement it names; a byte offset is exclusive
+    #[arg(long)]
+    pub to: Option<RangeBound>,
+}
+impl RangeArgs {
+    pub fn slice<'a>(&self, contents: &'a [u8]) -> Result<&'a [u8], Box<dyn Error>> {
+        Ok(&contents[self.resolve(contents)?])
+    }
+
+    /// The byte range of `contents` the bounds select, the whole of it by
+    /// default.
+    pub fn resolve(&self, contents: &[u8]) -> Result<Range<usize>, Box<dyn Error>> {
+        let from = self.from.clone().unwrap_or(RangeBound::Offset(0));
+        let to = self.to.clone().unwrap_or(RangeBound::Offset(contents.len()));
+        Ok(resolve_range(contents, &from, &to)?)
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.from.is_some() || self.to.is_some()
+    }
+}
+
+#[derive(clap::Args)]
+pub struct OutputArgs {
+    /// Write the result to this file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+impl OutputArgs {
+    pub fn write(&self, contents: &str) -> Result<(), Box<dyn Error>> {
+        match &self.output {
+            Some(path) => File::create(path)?.write_all(contents.as_bytes())?,
+            None => std::io::stdout().write_all(contents.as_bytes())?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(clap::Args)]
+pub struct RenderArgs {
+    /// Write formulas in Unicode, using the althtmldef of the database's $t
+    /// comment where it has one
+    #[arg(short, long)]
+    pub unicode: bool,
+}
+impl RenderArgs {
+    /// The renderer for the database text `contents`, if Unicode output was
+    /// asked for.
+    pub fn renderer(&self, contents: &[u8]) -> Result<Option<UnicodeRenderer>, Box<dyn Error>> {
+        if !self.unicode {
+            return Ok(None);
+        }
+        Ok(Some(UnicodeRenderer::new(&db_parser::typesetting::typesetting(contents)?)))
+    }
+}
+
+pub fn read_database(path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    std::fs::read(path).map_err(|error| format!("cannot read {}: {error}", path.display()).into())
+}
+
+/// Parses `contents`, failing if anything but whitespace is left over.
+pub fn parse_document(contents: &[u8]) -> Result<Document<'_>, Box<dyn Error>> {
+    let (rest, document) = db_parser::document::document(contents)
+        .map_err(|error| format!("cannot parse database: {error:?}"))?;
+    if let Some(position) = rest.iter().position(|c| !c.is_ascii_whitespace()) {
+        let offset = contents.len() - rest.len() + position;
+        return Err(format!("parsing stopped at byte {offset}").into());
+    }
+    Ok(document)
+}
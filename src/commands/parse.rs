@@ -0,0 +1,33 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use super::{parse_document, read_database, CommandResult, OutputArgs, RangeArgs};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    #[command(flatten)]
+    range: RangeArgs,
+    /// Print the whole syntax tree instead of a summary
+    #[arg(long)]
+    dump: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(args.range.slice(&contents)?)?;
+
+    let mut o = String::new();
+    if args.dump {
+        writeln!(o, "{document:#?}")?;
+    } else {
+        writeln!(o, "{} major parts", document.major_parts.len())?;
+        writeln!(o, "{} top-level statements", document.statements().count())?;
+    }
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
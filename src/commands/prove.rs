@@ -0,0 +1,177 @@
+use std::error::Error;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use db_parser::unicode::UnicodeRenderer;
+use verifier::assistant::{Assistant, ShownStep};
+use verifier::compress::replace_proof;
+use verifier::database::{Database, EntryKind};
+use verifier::grammar::Grammar;
+
+use super::{parse_document, read_database, CommandResult, RenderArgs};
+
+const HELP: &str = "\
+show                  list every step of the proof
+unknown               list the steps still to prove
+assign <step> <label> prove a step with a hypothesis or an earlier assertion
+improve <step> [<n>]  search for a proof of a step, n assertions deep (1)
+improve all [<n>]     the same for every unknown step
+let $<n> = <math>     replace a work variable with an expression
+undo                  take back the last change
+save                  write the finished proof back to the database
+quit                  leave without saving
+<step> is a step number or `last`, the last unknown step.
+";
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Label of the $p statement to prove; its proof may be `?` or contain
+    /// `?` steps
+    label: String,
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let theorem = database
+        .index_of(&args.label)
+        .filter(|&index| database.entries[index].kind == EntryKind::ProvableAssertion)
+        .ok_or_else(|| format!("no $p statement labeled {}", args.label))?;
+    let grammar = Grammar::new(&database);
+    let renderer = args.render.renderer(&contents)?;
+    let mut assistant = Assistant::new(&database, &grammar, theorem)?;
+
+    let session = Session {
+        contents: &contents,
+        database: &database,
+        renderer: renderer.as_ref(),
+        path: &args.database,
+    };
+    session.print(&assistant.unknown());
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("prove> ");
+        std::io::stdout().flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            println!();
+            break;
+        };
+        let words: Vec<&str> = line.split_ascii_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["quit" | "exit"] => break,
+            ["help"] => print!("{HELP}"),
+            command => {
+                if let Err(error) = session.command(&mut assistant, command) {
+                    println!("?{error}");
+                }
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+struct Session<'s> {
+    contents: &'s [u8],
+    database: &'s Database<'s>,
+    renderer: Option<&'s UnicodeRenderer>,
+    path: &'s PathBuf,
+}
+impl Session<'_> {
+    fn command(&self, assistant: &mut Assistant, command: &[&str]) -> Result<(), Box<dyn Error>> {
+        match command {
+            ["show"] => self.print(&assistant.steps()),
+            ["unknown"] => self.print(&assistant.unknown()),
+            ["assign", step, label] => {
+                assistant.assign(step_number(assistant, step)?, label)?;
+                self.print(&assistant.unknown());
+            }
+            ["improve", "all", depth @ ..] => {
+                let improvements = assistant.improve_all(search_depth(depth)?);
+                for improvement in &improvements {
+                    println!(
+                        "proved {} with {}",
+                        self.render(&improvement.formula),
+                        improvement.label
+                    );
+                }
+                self.print(&assistant.unknown());
+            }
+            ["improve", step, depth @ ..] => {
+                match assistant.improve(step_number(assistant, step)?, search_depth(depth)?)? {
+                    Some(label) => {
+                        println!("proved with {label}");
+                        self.print(&assistant.unknown());
+                    }
+                    None => println!("no proof found within the depth"),
+                }
+            }
+            ["let", variable, "=", expression @ ..] => {
+                let expression: Vec<String> = expression.iter().map(|s| s.to_string()).collect();
+                assistant.let_variable(variable, &expression)?;
+                self.print(&assistant.unknown());
+            }
+            ["undo"] => match assistant.undo() {
+                true => self.print(&assistant.unknown()),
+                false => println!("nothing to undo"),
+            },
+            ["save"] => {
+                let proof = assistant.proof()?;
+                let output = replace_proof(self.contents, self.database, assistant.theorem(), &proof)?;
+                std::fs::write(self.path, output)?;
+                println!(
+                    "saved the proof of {} to {}",
+                    self.database.entries[assistant.theorem()].label,
+                    self.path.display()
+                );
+            }
+            _ => return Err(format!("unknown command {:?}, try help", command.join(" ")).into()),
+        }
+        Ok(())
+    }
+
+    fn print(&self, steps: &[ShownStep]) {
+        if steps.is_empty() {
+            println!("no unknown steps; save to write the proof");
+        }
+        for step in steps {
+            let formula = self.render(&step.formula);
+            let label = step.label.as_deref().unwrap_or("?");
+            println!("{:>4} {}{label} {formula}", step.number, "  ".repeat(step.depth));
+        }
+    }
+
+    fn render(&self, formula: &[String]) -> String {
+        let formula = formula.join(" ");
+        match self.renderer {
+            Some(renderer) => renderer.render(&formula),
+            None => formula,
+        }
+    }
+}
+
+/// The number of the step `word` names: a number, or `last` for the last
+/// unknown step.
+fn step_number(assistant: &Assistant, word: &str) -> Result<usize, Box<dyn Error>> {
+    match word {
+        "last" => Ok(assistant.unknown().last().ok_or("there are no unknown steps")?.number),
+        _ => Ok(word.parse().map_err(|_| format!("{word:?} is not a step number"))?),
+    }
+}
+
+/// The depth `improve` searches to: the optional word after the step, 1 by
+/// default.
+fn search_depth(words: &[&str]) -> Result<usize, Box<dyn Error>> {
+    match words {
+        [] => Ok(1),
+        [depth] => Ok(depth.parse().map_err(|_| format!("{depth:?} is not a depth"))?),
+        _ => Err("improve takes a step and at most a depth".into()),
+    }
+}
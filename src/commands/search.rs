@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use verifier::database::Database;
+use verifier::search::{search, Pattern, SearchOptions};
+
+use super::{parse_document, read_database, CommandResult, OutputArgs, RangeArgs, RenderArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Math string to match against whole statements, such as
+    /// "|- ( ph -> $* )"; $* matches any symbols and $? one symbol
+    pattern: String,
+    /// Also match the $e hypotheses of each assertion
+    #[arg(long)]
+    hypotheses: bool,
+    /// Only search statements in this range
+    #[command(flatten)]
+    range: RangeArgs,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    render: RenderArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let range = args.range.resolve(&contents)?;
+
+    let pattern = Pattern::parse(&database, &args.pattern);
+    let options = SearchOptions {
+        hypotheses: args.hypotheses,
+    };
+    let mut hits = search(&database, &pattern, options, |index| {
+        range.contains(&database.entries[index].label.0.offset_in(&contents))
+    });
+    if let Some(renderer) = args.render.renderer(&contents)? {
+        for hit in &mut hits {
+            hit.formula = renderer.render(&hit.formula);
+        }
+    }
+    let o = match args.format {
+        Format::Text => hits.iter().map(|hit| format!("{hit}\n")).collect(),
+        Format::Json => serde_json::to_string_pretty(&hits)?,
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
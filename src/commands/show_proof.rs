@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use verifier::database::Database;
+use verifier::display::{show_proof, ShowOptions};
+
+use super::{parse_document, read_database, CommandResult, OutputArgs, RenderArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Label of the theorem to show
+    label: String,
+    /// Also list the steps that build the syntax of formulas
+    #[arg(long)]
+    syntax: bool,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    render: RenderArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+
+    let index = database.index_of(&args.label).ok_or_else(|| format!("unknown label {}", args.label))?;
+    let mut listing = show_proof(&database, index, ShowOptions { syntax: args.syntax })?;
+    if let Some(renderer) = args.render.renderer(&contents)? {
+        for step in &mut listing.steps {
+            step.formula = renderer.render(&step.formula);
+        }
+    }
+    let o = match args.format {
+        Format::Text => listing.to_string(),
+        Format::Json => listing.to_json(),
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use finder::stats::Stats;
+
+use super::{parse_document, read_database, CommandResult, OutputArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Number of entries in each ranking
+    #[arg(short = 'n', long, default_value_t = 10)]
+    top: usize,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let stats = Stats::from_document(&document, &contents, args.top);
+    let o = match args.format {
+        Format::Text => stats.to_string(),
+        Format::Json => stats.to_json(),
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
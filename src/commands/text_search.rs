@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use finder::text_index::TextIndex;
+
+use super::{CommandResult, OutputArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Words to look for in labels, heading titles and comments
+    query: String,
+    /// Print at most this many results
+    #[arg(short = 'n', long, default_value_t = 20)]
+    limit: usize,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let index = TextIndex::load_or_build(&args.database)
+        .map_err(|error| format!("cannot index {}: {error}", args.database.display()))?;
+    let mut hits = index.search(&args.query);
+    hits.truncate(args.limit);
+    let o = match args.format {
+        Format::Text => hits.iter().map(|hit| format!("{hit}\n")).collect(),
+        Format::Json => serde_json::to_string_pretty(&hits)?,
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use super::{read_database, CommandResult, OutputArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    Markdown,
+    Html,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let tree = finder::title_tree::title_tree(&contents);
+    for warning in &tree.warnings {
+        eprintln!("warning: {warning}");
+    }
+    let o = match args.format {
+        Format::Text => tree.to_string(),
+        Format::Json => tree.to_json(),
+        Format::Markdown => tree.to_markdown(),
+        Format::Html => tree.to_html(),
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use verifier::database::Database;
+use verifier::trace::TraceBack;
+
+use super::{parse_document, read_database, CommandResult, OutputArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Labels of the assertions to trace
+    #[arg(required = true)]
+    labels: Vec<String>,
+    /// Also follow the syntax steps of proofs, not only the essential ones
+    #[arg(long)]
+    syntax: bool,
+    /// Only report whether each assertion depends on this axiom or definition
+    #[arg(long)]
+    uses: Option<String>,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let index_of = |label: &str| database.index_of(label).ok_or_else(|| format!("unknown label {label}"));
+
+    let mut trace = TraceBack::new(&database, !args.syntax);
+    let o = match &args.uses {
+        Some(axiom) => {
+            let axiom = index_of(axiom)?;
+            let mut uses = Vec::new();
+            for label in &args.labels {
+                let index = index_of(label)?;
+                for error in trace.skipped(index) {
+                    eprintln!("warning: not followed: {error}");
+                }
+                uses.push((label.clone(), trace.uses(index, axiom)));
+            }
+            match args.format {
+                Format::Text => uses
+                    .iter()
+                    .map(|(label, used)| format!("{label}: {}\n", if *used { "yes" } else { "no" }))
+                    .collect(),
+                Format::Json => serde_json::to_string_pretty(&uses.into_iter().collect::<BTreeMap<_, _>>())?,
+            }
+        }
+        None => {
+            let mut reports = Vec::new();
+            for label in &args.labels {
+                reports.push(trace.report(index_of(label)?));
+            }
+            match args.format {
+                Format::Text => reports.iter().map(|report| report.to_string()).collect(),
+                Format::Json => serde_json::to_string_pretty(&reports)?,
+            }
+        }
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
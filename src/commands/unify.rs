@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use verifier::database::Database;
+use verifier::grammar::Grammar;
+use verifier::unify::{unify_search, UnifyOptions};
+
+use super::{parse_document, read_database, CommandResult, OutputArgs, RangeArgs, RenderArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Statement to prove, typecode first, such as "|- ( ph -> ph )"
+    statement: String,
+    /// Also unify with the $e hypotheses of each assertion
+    #[arg(long)]
+    hypotheses: bool,
+    /// Print at most this many assertions
+    #[arg(short = 'n', long)]
+    limit: Option<usize>,
+    /// Only search statements in this range
+    #[command(flatten)]
+    range: RangeArgs,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    render: RenderArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let range = args.range.resolve(&contents)?;
+
+    let grammar = Grammar::new(&database);
+    let query: Vec<String> = args.statement.split_ascii_whitespace().map(str::to_string).collect();
+    let options = UnifyOptions {
+        hypotheses: args.hypotheses,
+    };
+    let mut hits = unify_search(&database, &grammar, &query, options, |index| {
+        range.contains(&database.entries[index].label.0.offset_in(&contents))
+    })
+    .ok_or_else(|| format!("cannot parse {:?} with the syntax axioms of the database", args.statement))?;
+    if let Some(limit) = args.limit {
+        hits.truncate(limit);
+    }
+    if let Some(renderer) = args.render.renderer(&contents)? {
+        for hit in &mut hits {
+            hit.formula = renderer.render(&hit.formula);
+            hit.substitution = hit
+                .substitution
+                .iter()
+                .map(|(variable, expression)| (renderer.render(variable), renderer.render(expression)))
+                .collect();
+        }
+    }
+    let o = match args.format {
+        Format::Text => hits.iter().map(|hit| format!("{hit}\n")).collect(),
+        Format::Json => serde_json::to_string_pretty(&hits)?,
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use serde_json::json;
+use verifier::database::Database;
+use verifier::usage::UsageIndex;
+
+use super::{parse_document, read_database, CommandResult, OutputArgs};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Label of the assertion to look up
+    label: String,
+    /// Include theorems that use it through other theorems
+    #[arg(short, long)]
+    recursive: bool,
+    /// Only print the number of theorems
+    #[arg(short, long)]
+    count: bool,
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+    let index = database.index_of(&args.label).ok_or_else(|| format!("unknown label {}", args.label))?;
+
+    let usage = UsageIndex::new(&database);
+    let users = if args.recursive { usage.transitive(index) } else { usage.direct(index).to_vec() };
+    let labels: Vec<String> = users.iter().map(|&user| database.entries[user].label.to_string()).collect();
+    let o = match (args.format, args.count) {
+        (Format::Text, true) => format!("{}\n", labels.len()),
+        (Format::Text, false) => {
+            let scope = if args.recursive { "directly or indirectly" } else { "directly" };
+            let mut o = format!("{} is used {scope} by {} statements\n", args.label, labels.len());
+            for label in &labels {
+                o.push_str(label);
+                o.push('\n');
+            }
+            o
+        }
+        (Format::Json, true) => json!({ "label": args.label, "recursive": args.recursive, "count": labels.len() }).to_string(),
+        (Format::Json, false) => serde_json::to_string_pretty(
+            &json!({ "label": args.label, "recursive": args.recursive, "count": labels.len(), "users": labels }),
+        )?,
+    };
+    args.output.write(&o)?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,50 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use verifier::database::{Database, EntryKind};
+use verifier::verify::verify;
+
+use super::{parse_document, read_database, CommandResult, OutputArgs};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to the .mm database
+    database: PathBuf,
+    /// Only verify these theorems
+    #[arg(short, long = "label")]
+    labels: Vec<String>,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: Args) -> CommandResult {
+    let contents = read_database(&args.database)?;
+    let document = parse_document(&contents)?;
+    let database = Database::new(&document)?;
+
+    let theorems = if args.labels.is_empty() {
+        database
+            .assertions()
+            .filter(|(_, entry)| entry.kind == EntryKind::ProvableAssertion)
+            .map(|(index, _)| index)
+            .collect()
+    } else {
+        args.labels
+            .iter()
+            .map(|label| database.index_of(label).ok_or_else(|| format!("unknown label {label}")))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut o = String::new();
+    let mut failures = 0;
+    for &index in &theorems {
+        if let Err(error) = verify(&database, index) {
+            writeln!(o, "{error}")?;
+            failures += 1;
+        }
+    }
+    writeln!(o, "{} proofs checked, {failures} failed", theorems.len())?;
+    args.output.write(&o)?;
+    Ok(if failures == 0 { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
@@ -1,3 +1,105 @@
-fn main() {
-    println!("Hello, world!");
+mod commands;
+
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+/// The nom parsers recurse once per nested heading and block, which does not
+/// fit in the default main thread stack for set.mm.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Parser)]
+#[command(name = "metamath-rs", version, about = "Tools for Metamath databases")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check every proof in a database
+    Verify(commands::verify::Args),
+    /// Parse a database and report where parsing stopped
+    Parse(commands::parse::Args),
+    /// Print the heading outline of a database
+    Titles(commands::titles::Args),
+    /// List the `$c` and `$a` statements under their headings
+    BaseStatements(commands::base_statements::Args),
+    /// List the steps of a proof with the formula each one proves
+    ShowProof(commands::show_proof::Args),
+    /// List the axioms and definitions a theorem depends on
+    TraceBack(commands::trace_back::Args),
+    /// List the theorems whose proofs use an assertion
+    Usage(commands::usage::Args),
+    /// Export the proof-reference graph as DOT or GraphML
+    Graph(commands::graph::Args),
+    /// Check the discouraged tags and write set.mm's `discouraged` listing
+    Discouraged(commands::discouraged::Args),
+    /// Find the assertions whose statements match a pattern
+    Search(commands::search::Args),
+    /// Find the assertions that prove a statement after substitution
+    Unify(commands::unify::Args),
+    /// Look up statements and headings by the words of their comments
+    TextSearch(commands::text_search::Args),
+    /// Write a static HTML site with a page per assertion
+    Html(commands::html::Args),
+    /// Typeset assertions and their proofs in LaTeX
+    Latex(commands::latex::Args),
+    /// Prove a $p statement interactively, starting from its `?` steps
+    Prove(commands::prove::Args),
+    /// Prove the `?` steps of a $p statement by a bounded search
+    Improve(commands::improve::Args),
+    /// Shorten proofs by using earlier assertions in place of subproofs
+    Minimize(commands::minimize::Args),
+    /// Report proofs that use another user's mathbox
+    Mathbox(commands::mathbox::Args),
+    /// Count statements per heading and rank proofs, theorems and contributors
+    Stats(commands::stats::Args),
+    /// Compare the statements, proofs and outline of two versions of a database
+    Diff(commands::diff::Args),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let worker = match std::thread::Builder::new().stack_size(STACK_SIZE).spawn(move || run(cli)) {
+        Ok(worker) => worker,
+        Err(error) => {
+            eprintln!("error: cannot start the worker thread: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // A panic in the worker has already been reported by the panic hook.
+    worker.join().unwrap_or(ExitCode::FAILURE)
+}
+
+fn run(cli: Cli) -> ExitCode {
+    let result = match cli.command {
+        Command::Verify(args) => commands::verify::run(args),
+        Command::Parse(args) => commands::parse::run(args),
+        Command::Titles(args) => commands::titles::run(args),
+        Command::BaseStatements(args) => commands::base_statements::run(args),
+        Command::ShowProof(args) => commands::show_proof::run(args),
+        Command::TraceBack(args) => commands::trace_back::run(args),
+        Command::Usage(args) => commands::usage::run(args),
+        Command::Graph(args) => commands::graph::run(args),
+        Command::Discouraged(args) => commands::discouraged::run(args),
+        Command::Search(args) => commands::search::run(args),
+        Command::Unify(args) => commands::unify::run(args),
+        Command::TextSearch(args) => commands::text_search::run(args),
+        Command::Html(args) => commands::html::run(args),
+        Command::Latex(args) => commands::latex::run(args),
+        Command::Prove(args) => commands::prove::run(args),
+        Command::Improve(args) => commands::improve::run(args),
+        Command::Minimize(args) => commands::minimize::run(args),
+        Command::Mathbox(args) => commands::mathbox::run(args),
+        Command::Stats(args) => commands::stats::run(args),
+        Command::Diff(args) => commands::diff::run(args),
+    };
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
 }